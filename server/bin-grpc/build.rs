@@ -0,0 +1,15 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the protoc shipped as a crate so the build needs neither a system
+    // installation nor network access.
+    std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+
+    tonic_build::configure().compile_protos(
+        &["proto/broccoli.proto"],
+        &[
+            std::path::PathBuf::from("proto"),
+            protoc_bin_vendored::include_path()?,
+        ],
+    )?;
+
+    Ok(())
+}
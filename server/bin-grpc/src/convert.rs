@@ -0,0 +1,238 @@
+use chrono::{DateTime, NaiveDateTime};
+use engine::dto::{Direction, EdgeQuery};
+use engine::error::{Error, ErrorKind, ErrorResponse};
+use engine::model::{Edge, Vertex};
+use prost_types::value::Kind;
+use prost_types::Timestamp;
+use tonic::{Code, Status};
+
+use crate::proto;
+
+// Timestamps are stored without a time zone. The connections of this server
+// run in UTC, so the timestamps it writes and compares are UTC too.
+
+pub fn timestamp(at: NaiveDateTime) -> Timestamp {
+    let at = at.and_utc();
+    Timestamp {
+        seconds: at.timestamp(),
+        nanos: at.timestamp_subsec_nanos() as i32,
+    }
+}
+
+pub fn naive_date_time(at: Timestamp) -> Result<NaiveDateTime, Status> {
+    u32::try_from(at.nanos)
+        .ok()
+        .filter(|nanos| *nanos < 1_000_000_000)
+        .and_then(|nanos| DateTime::from_timestamp(at.seconds, nanos))
+        .map(|at| at.naive_utc())
+        .ok_or_else(|| Status::invalid_argument("Invalid timestamp"))
+}
+
+// Edge properties are JSON objects, which map one to one onto structs.
+
+pub fn json_object(properties: prost_types::Struct) -> serde_json::Map<String, serde_json::Value> {
+    properties
+        .fields
+        .into_iter()
+        .map(|(key, value)| (key, json_value(value)))
+        .collect()
+}
+
+fn json_value(value: prost_types::Value) -> serde_json::Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::BoolValue(value)) => value.into(),
+        Some(Kind::NumberValue(value)) => value.into(),
+        Some(Kind::StringValue(value)) => value.into(),
+        Some(Kind::ListValue(list)) => list.values.into_iter().map(json_value).collect(),
+        Some(Kind::StructValue(value)) => json_object(value).into(),
+    }
+}
+
+pub fn proto_struct(properties: serde_json::Value) -> prost_types::Struct {
+    let serde_json::Value::Object(properties) = properties else {
+        return prost_types::Struct::default();
+    };
+
+    prost_types::Struct {
+        fields: properties
+            .into_iter()
+            .map(|(key, value)| (key, proto_value(value)))
+            .collect(),
+    }
+}
+
+fn proto_value(value: serde_json::Value) -> prost_types::Value {
+    let kind = match value {
+        serde_json::Value::Null => Kind::NullValue(0),
+        serde_json::Value::Bool(value) => Kind::BoolValue(value),
+        serde_json::Value::Number(value) => Kind::NumberValue(value.as_f64().unwrap_or_default()),
+        serde_json::Value::String(value) => Kind::StringValue(value),
+        serde_json::Value::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.into_iter().map(proto_value).collect(),
+        }),
+        value @ serde_json::Value::Object(_) => Kind::StructValue(proto_struct(value)),
+    };
+
+    prost_types::Value { kind: Some(kind) }
+}
+
+pub fn edge_query(query: Option<proto::EdgeQuery>) -> Result<EdgeQuery, Status> {
+    let Some(query) = query else {
+        return Ok(EdgeQuery::default());
+    };
+
+    Ok(EdgeQuery {
+        direction: query.direction().into(),
+        as_of: query.as_of.map(naive_date_time).transpose()?,
+        label: query.label,
+        min_weight: query.min_weight,
+        valid_at: query.valid_at.map(naive_date_time).transpose()?,
+        valid_during: None,
+    })
+}
+
+/// Reports an engine error with the closest gRPC code. Version conflicts are
+/// `ABORTED`, as the caller is expected to re-read and retry.
+pub fn status(error: Error) -> Status {
+    let response = ErrorResponse::from(&error);
+    let code = match response.kind {
+        ErrorKind::Validation => Code::InvalidArgument,
+        ErrorKind::NotFound => Code::NotFound,
+        ErrorKind::AlreadyExists => Code::AlreadyExists,
+        ErrorKind::DeleteRestricted => Code::FailedPrecondition,
+        ErrorKind::Conflict => Code::Aborted,
+        ErrorKind::Internal => Code::Internal,
+    };
+
+    Status::new(code, response.message)
+}
+
+impl From<proto::Direction> for Direction {
+    fn from(direction: proto::Direction) -> Self {
+        match direction {
+            proto::Direction::Outgoing => Direction::Outgoing,
+            proto::Direction::Incoming => Direction::Incoming,
+            proto::Direction::Both => Direction::Both,
+        }
+    }
+}
+
+impl From<Vertex> for proto::Vertex {
+    fn from(vertex: Vertex) -> Self {
+        proto::Vertex {
+            id: vertex.id,
+            name: vertex.name,
+            r#type: vertex.type_,
+            created_by: vertex.created_by,
+            created_at: Some(timestamp(vertex.created_at)),
+            updated_by: vertex.updated_by,
+            updated_at: Some(timestamp(vertex.updated_at)),
+            version: vertex.version,
+            labels: vertex.labels,
+        }
+    }
+}
+
+impl From<Edge> for proto::Edge {
+    fn from(edge: Edge) -> Self {
+        proto::Edge {
+            id: edge.id,
+            from_vertex_id: edge.from_vertex_id,
+            from_vertex_type: edge.from_vertex_type,
+            to_vertex_id: edge.to_vertex_id,
+            to_vertex_type: edge.to_vertex_type,
+            label: edge.label,
+            created_by: edge.created_by,
+            created_at: Some(timestamp(edge.created_at)),
+            updated_by: edge.updated_by,
+            updated_at: Some(timestamp(edge.updated_at)),
+            version: edge.version,
+            undirected: edge.undirected,
+            edge_key: edge.edge_key,
+            properties: Some(proto_struct(edge.properties)),
+            weight: edge.weight,
+            valid_from: edge.valid_from.map(timestamp),
+            valid_to: edge.valid_to.map(timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    #[test]
+    fn test_timestamp() {
+        let at = NaiveDate::from_ymd_opt(2024, 8, 12)
+            .unwrap()
+            .and_hms_nano_opt(13, 25, 24, 123_456_789)
+            .unwrap();
+        let converted = timestamp(at);
+        assert_eq!(converted.seconds, 1_723_469_124);
+        assert_eq!(converted.nanos, 123_456_789);
+        assert_eq!(naive_date_time(converted).unwrap(), at);
+
+        // Before the epoch the seconds round down and the nanos stay positive.
+        let at = NaiveDate::from_ymd_opt(1969, 12, 31)
+            .unwrap()
+            .and_hms_milli_opt(23, 59, 59, 500)
+            .unwrap();
+        let converted = timestamp(at);
+        assert_eq!(converted.seconds, -1);
+        assert_eq!(converted.nanos, 500_000_000);
+        assert_eq!(naive_date_time(converted).unwrap(), at);
+    }
+
+    #[test]
+    fn test_naive_date_time() {
+        let epoch = naive_date_time(Timestamp::default()).unwrap();
+        assert_eq!(epoch, DateTime::UNIX_EPOCH.naive_utc());
+
+        for (seconds, nanos) in [(0, -1), (59, 1_000_000_000), (i64::MAX, 0)] {
+            let error = naive_date_time(Timestamp { seconds, nanos }).unwrap_err();
+            assert_eq!(error.code(), Code::InvalidArgument, "{seconds}s {nanos}ns");
+        }
+    }
+
+    #[test]
+    fn test_proto_struct() {
+        let properties = json!({
+            "null": null,
+            "bool": true,
+            "number": 1.5,
+            "string": "broccoli",
+            "list": [1.0, "two", [null]],
+            "object": {"nested": {"deeper": false}},
+        });
+
+        let converted = proto_struct(properties.clone());
+        assert_eq!(converted.fields.len(), 6);
+        assert_eq!(converted.fields["null"].kind, Some(Kind::NullValue(0)));
+        assert_eq!(
+            converted.fields["number"].kind,
+            Some(Kind::NumberValue(1.5))
+        );
+        let Some(Kind::StructValue(object)) = &converted.fields["object"].kind else {
+            panic!("object is not a struct");
+        };
+        assert!(matches!(
+            object.fields["nested"].kind,
+            Some(Kind::StructValue(_))
+        ));
+
+        assert_eq!(
+            serde_json::Value::Object(json_object(converted)),
+            properties
+        );
+
+        // Properties are always objects, anything else converts to nothing.
+        assert_eq!(proto_struct(json!([1, 2])), prost_types::Struct::default());
+        assert_eq!(
+            proto_struct(serde_json::Value::Null),
+            prost_types::Struct::default()
+        );
+    }
+}
@@ -0,0 +1,52 @@
+// tonic fixes the error type of every RPC to `Status`, which is large.
+#![allow(clippy::result_large_err)]
+
+mod convert;
+mod service;
+
+use std::env;
+
+use diesel::{ConnectionError, ConnectionResult};
+use diesel_async::pooled_connection::deadpool::Pool;
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use diesel_async::{AsyncConnection, AsyncPgConnection, SimpleAsyncConnection};
+use tonic::transport::Server;
+
+use crate::proto::graph_server::GraphServer;
+use crate::service::GraphService;
+
+pub mod proto {
+    tonic::include_proto!("broccoli.v1");
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let address = env::var("GRPC_ADDRESS")
+        .unwrap_or_else(|_| "[::]:50051".to_string())
+        .parse()?;
+
+    let mut config = ManagerConfig::default();
+    config.custom_setup = Box::new(|url| Box::pin(establish(url)));
+    let manager =
+        AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(database_url, config);
+    let pool = Pool::builder(manager).build()?;
+
+    Server::builder()
+        .add_service(GraphServer::new(GraphService::new(pool)))
+        .serve(address)
+        .await?;
+
+    Ok(())
+}
+
+/// Opens a connection in UTC, the time zone timestamps are sent to clients in.
+async fn establish(database_url: &str) -> ConnectionResult<AsyncPgConnection> {
+    let mut conn = AsyncPgConnection::establish(database_url).await?;
+    conn.batch_execute("SET TIME ZONE 'UTC'")
+        .await
+        .map_err(ConnectionError::CouldntSetupConfiguration)?;
+    Ok(conn)
+}
@@ -0,0 +1,535 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel_async::AsyncPgConnection;
+use engine::dto::{EdgeQuery, NewEdge, NewVertex, UpdateEdge, UpdateVertex};
+use engine::error::Error;
+use engine::model::{Edge, Vertex};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use validator::Validate;
+
+use crate::convert::{edge_query, json_object, naive_date_time, status};
+use crate::proto::{self, graph_server::Graph};
+
+const PAGE_SIZE: i64 = 1000;
+
+pub type DbPool = Pool<AsyncPgConnection>;
+
+type ResultStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+pub struct GraphService {
+    pool: DbPool,
+}
+
+impl GraphService {
+    pub fn new(pool: DbPool) -> Self {
+        GraphService { pool }
+    }
+
+    async fn connection(&self) -> Result<Object<AsyncPgConnection>, Status> {
+        connection(&self.pool).await
+    }
+
+    /// Streams the pages of `pages` as they are read.
+    fn stream_pages<P, U>(&self, pages: P) -> ResultStream<U>
+    where
+        P: Pages,
+        U: From<P::Item> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(PAGE_SIZE as usize);
+        tokio::spawn(send_pages(self.pool.clone(), pages, sender));
+        Box::pin(ReceiverStream::new(receiver))
+    }
+}
+
+#[tonic::async_trait]
+impl Graph for GraphService {
+    async fn create_vertex(
+        &self,
+        request: Request<proto::CreateVertexRequest>,
+    ) -> Result<Response<proto::Vertex>, Status> {
+        let request = request.into_inner();
+        let new_vertex = NewVertex {
+            name: request.name,
+            type_: request.r#type,
+            created_by: request.created_by,
+            labels: request.labels,
+        };
+        new_vertex.validate().map_err(|e| status(e.into()))?;
+
+        let mut conn = self.connection().await?;
+        let vertex = engine::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn get_vertex(
+        &self,
+        request: Request<proto::GetVertexRequest>,
+    ) -> Result<Response<proto::Vertex>, Status> {
+        let request = request.into_inner();
+        let as_of = request.as_of.map(naive_date_time).transpose()?;
+
+        let mut conn = self.connection().await?;
+        let vertex = engine::api::get_vertex_by_id(&mut conn, request.id, as_of)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn update_vertex(
+        &self,
+        request: Request<proto::UpdateVertexRequest>,
+    ) -> Result<Response<proto::Vertex>, Status> {
+        let request = request.into_inner();
+        let update = UpdateVertex {
+            name: request.name,
+            type_: request.r#type,
+            labels: request.labels.map(|label_set| label_set.labels),
+            updated_by: request.updated_by,
+        };
+        update.validate().map_err(|e| status(e.into()))?;
+
+        let mut conn = self.connection().await?;
+        let vertex =
+            engine::api::update_vertex(&mut conn, request.id, &update, request.expected_version)
+                .await
+                .map_err(status)?;
+
+        Ok(Response::new(vertex.into()))
+    }
+
+    async fn delete_vertex(
+        &self,
+        request: Request<proto::DeleteRequest>,
+    ) -> Result<Response<proto::DeleteResponse>, Status> {
+        let request = request.into_inner();
+
+        let mut conn = self.connection().await?;
+        let deleted = engine::api::soft_delete_vertex_by_id(
+            &mut conn,
+            request.id,
+            request.expected_version,
+            &request.deleted_by,
+        )
+        .await
+        .map_err(status)?;
+
+        Ok(Response::new(proto::DeleteResponse {
+            deleted: deleted > 0,
+        }))
+    }
+
+    async fn create_edge(
+        &self,
+        request: Request<proto::CreateEdgeRequest>,
+    ) -> Result<Response<proto::Edge>, Status> {
+        let request = request.into_inner();
+        let new_edge = NewEdge {
+            from_vertex_id: request.from_vertex_id,
+            to_vertex_id: request.to_vertex_id,
+            label: request.label,
+            created_by: request.created_by,
+            undirected: request.undirected,
+            edge_key: request.edge_key,
+            properties: request.properties.map(json_object).unwrap_or_default(),
+            weight: request.weight.unwrap_or(1.0),
+            valid_from: request.valid_from.map(naive_date_time).transpose()?,
+            valid_to: request.valid_to.map(naive_date_time).transpose()?,
+        };
+        new_edge.validate().map_err(|e| status(e.into()))?;
+
+        let mut conn = self.connection().await?;
+        let edge = engine::api::create_edge(&mut conn, &new_edge)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn update_edge(
+        &self,
+        request: Request<proto::UpdateEdgeRequest>,
+    ) -> Result<Response<proto::Edge>, Status> {
+        let request = request.into_inner();
+        let update = UpdateEdge {
+            label: request.label,
+            weight: request.weight,
+            valid_from: request
+                .valid_from
+                .map(naive_date_time)
+                .transpose()?
+                .map(Some),
+            valid_to: request.valid_to.map(naive_date_time).transpose()?.map(Some),
+            updated_by: request.updated_by,
+        };
+        update.validate().map_err(|e| status(e.into()))?;
+
+        let mut conn = self.connection().await?;
+        let edge =
+            engine::api::update_edge(&mut conn, request.id, &update, request.expected_version)
+                .await
+                .map_err(status)?;
+
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn delete_edge(
+        &self,
+        request: Request<proto::DeleteRequest>,
+    ) -> Result<Response<proto::DeleteResponse>, Status> {
+        let request = request.into_inner();
+
+        let mut conn = self.connection().await?;
+        let deleted = engine::api::soft_delete_edge_by_id(
+            &mut conn,
+            request.id,
+            request.expected_version,
+            &request.deleted_by,
+        )
+        .await
+        .map_err(status)?;
+
+        Ok(Response::new(proto::DeleteResponse {
+            deleted: deleted > 0,
+        }))
+    }
+
+    type GetIncidentEdgesStream = ResultStream<proto::Edge>;
+
+    async fn get_incident_edges(
+        &self,
+        request: Request<proto::IncidentRequest>,
+    ) -> Result<Response<Self::GetIncidentEdgesStream>, Status> {
+        let request = request.into_inner();
+        let query = edge_query(request.query)?;
+
+        let mut conn = self.connection().await?;
+        let edges = engine::api::get_incident_edges(&mut conn, request.vertex_id, &query)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(stream_of(edges)))
+    }
+
+    type GetNeighborsStream = ResultStream<proto::Vertex>;
+
+    async fn get_neighbors(
+        &self,
+        request: Request<proto::IncidentRequest>,
+    ) -> Result<Response<Self::GetNeighborsStream>, Status> {
+        let request = request.into_inner();
+        let query = edge_query(request.query)?;
+
+        let mut conn = self.connection().await?;
+        let vertices = engine::api::get_neighbors(&mut conn, request.vertex_id, &query)
+            .await
+            .map_err(status)?;
+
+        Ok(Response::new(stream_of(vertices)))
+    }
+
+    type TraverseStream = ResultStream<proto::Vertex>;
+
+    async fn traverse(
+        &self,
+        request: Request<proto::TraverseRequest>,
+    ) -> Result<Response<Self::TraverseStream>, Status> {
+        let request = request.into_inner();
+        if request.start_vertex_id < 1 {
+            return Err(status(
+                Error::Validation(validator::ValidationErrors::new()),
+            ));
+        }
+        let traversal = Traversal {
+            query: edge_query(request.query)?,
+            remaining_depth: request.max_depth,
+            visited: HashSet::from([request.start_vertex_id]),
+            frontier: vec![request.start_vertex_id],
+            pending: Vec::new(),
+        };
+
+        Ok(Response::new(self.stream_pages(traversal)))
+    }
+
+    type ExportVerticesStream = ResultStream<proto::Vertex>;
+
+    async fn export_vertices(
+        &self,
+        request: Request<proto::ExportRequest>,
+    ) -> Result<Response<Self::ExportVerticesStream>, Status> {
+        let export = Export::<Vertex>::after(request.into_inner().after_id);
+        Ok(Response::new(self.stream_pages(export)))
+    }
+
+    type ExportEdgesStream = ResultStream<proto::Edge>;
+
+    async fn export_edges(
+        &self,
+        request: Request<proto::ExportRequest>,
+    ) -> Result<Response<Self::ExportEdgesStream>, Status> {
+        let export = Export::<Edge>::after(request.into_inner().after_id);
+        Ok(Response::new(self.stream_pages(export)))
+    }
+}
+
+fn stream_of<T, U>(items: Vec<T>) -> ResultStream<U>
+where
+    U: From<T> + Send + 'static,
+{
+    let items = items
+        .into_iter()
+        .map(|item| Ok(item.into()))
+        .collect::<Vec<_>>();
+    Box::pin(tokio_stream::iter(items))
+}
+
+/// A streamed response, read from the database a page at a time.
+trait Pages: Send + 'static {
+    type Item: Send + 'static;
+
+    /// The next page, or `None` once the response is complete.
+    fn next_page(
+        &mut self,
+        conn: &mut AsyncPgConnection,
+    ) -> impl Future<Output = Result<Option<Vec<Self::Item>>, Error>> + Send;
+}
+
+/// Sends `pages` one after the other, taking a connection per page so that a
+/// slow client holds neither a connection nor the whole response in memory.
+async fn send_pages<P, U>(pool: DbPool, mut pages: P, sender: mpsc::Sender<Result<U, Status>>)
+where
+    P: Pages,
+    U: From<P::Item>,
+{
+    loop {
+        let page = match connection(&pool).await {
+            Ok(mut conn) => pages.next_page(&mut conn).await.map_err(status),
+            Err(e) => Err(e),
+        };
+        let page = match page {
+            Ok(Some(page)) => page,
+            Ok(None) => return,
+            Err(e) => {
+                let _ = sender.send(Err(e)).await;
+                return;
+            }
+        };
+
+        for item in page {
+            if sender.send(Ok(item.into())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Rows exported in id order.
+trait Row: Sized + Send + 'static {
+    fn id(&self) -> i32;
+
+    fn page(
+        conn: &mut AsyncPgConnection,
+        after_id: i32,
+        limit: i64,
+    ) -> impl Future<Output = Result<Vec<Self>, Error>> + Send;
+}
+
+impl Row for Vertex {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    async fn page(
+        conn: &mut AsyncPgConnection,
+        after_id: i32,
+        limit: i64,
+    ) -> Result<Vec<Self>, Error> {
+        engine::api::get_vertices_page(conn, after_id, limit).await
+    }
+}
+
+impl Row for Edge {
+    fn id(&self) -> i32 {
+        self.id
+    }
+
+    async fn page(
+        conn: &mut AsyncPgConnection,
+        after_id: i32,
+        limit: i64,
+    ) -> Result<Vec<Self>, Error> {
+        engine::api::get_edges_page(conn, after_id, limit).await
+    }
+}
+
+/// The live rows with an id greater than `after_id`.
+struct Export<T> {
+    after_id: i32,
+    done: bool,
+    rows: PhantomData<fn() -> T>,
+}
+
+impl<T> Export<T> {
+    fn after(after_id: i32) -> Self {
+        Export {
+            after_id,
+            done: false,
+            rows: PhantomData,
+        }
+    }
+}
+
+impl<T: Row> Pages for Export<T> {
+    type Item = T;
+
+    async fn next_page(&mut self, conn: &mut AsyncPgConnection) -> Result<Option<Vec<T>>, Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let page = T::page(conn, self.after_id, PAGE_SIZE).await?;
+        self.done = page.len() < PAGE_SIZE as usize;
+        if let Some(row) = page.last() {
+            self.after_id = row.id();
+        }
+        Ok(Some(page))
+    }
+}
+
+/// The vertices reached by [`engine::api::traverse`], hop by hop. Only the
+/// ids of a hop are held; its vertices are loaded a page at a time.
+struct Traversal {
+    query: EdgeQuery,
+    remaining_depth: u32,
+    visited: HashSet<i32>,
+    frontier: Vec<i32>,
+    pending: Vec<i32>,
+}
+
+impl Pages for Traversal {
+    type Item = Vertex;
+
+    async fn next_page(
+        &mut self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<Vec<Vertex>>, Error> {
+        while self.pending.is_empty() {
+            if self.remaining_depth == 0 || self.frontier.is_empty() {
+                return Ok(None);
+            }
+
+            self.frontier =
+                engine::api::traverse_hop(conn, &self.frontier, &mut self.visited, &self.query)
+                    .await?;
+            self.pending = self.frontier.clone();
+            self.remaining_depth -= 1;
+        }
+
+        let count = self.pending.len().min(PAGE_SIZE as usize);
+        let ids = self.pending.drain(..count).collect::<Vec<_>>();
+        let mut vertices = engine::api::get_vertices_by_ids(conn, &ids, self.query.as_of)
+            .await?
+            .into_iter()
+            .map(|vertex| (vertex.id, vertex))
+            .collect::<HashMap<_, _>>();
+        Ok(Some(
+            ids.iter()
+                .filter_map(|vertex_id| vertices.remove(vertex_id))
+                .collect(),
+        ))
+    }
+}
+
+async fn connection(pool: &DbPool) -> Result<Object<AsyncPgConnection>, Status> {
+    pool.get()
+        .await
+        .map_err(|e| status(Error::Other(std::io::Error::other(e))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+    use tokio_stream::StreamExt;
+
+    #[tokio::test]
+    async fn test_traverse() {
+        dotenvy::from_path("../engine/.env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+        let pool = Pool::builder(manager).build().unwrap();
+        let service = GraphService::new(pool.clone());
+        let mut conn = pool.get().await.unwrap();
+
+        // a -> b -> c -> d and a -> e
+        let mut ids = Vec::new();
+        for name in ["a", "b", "c", "d", "e"] {
+            let new_vertex = NewVertex {
+                name: format!("grpctraverse{name}"),
+                type_: "grpctraverse".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            let vertex = engine::api::create_vertex(&mut conn, &new_vertex)
+                .await
+                .unwrap();
+            ids.push(vertex.id);
+        }
+        for (from, to) in [(0, 1), (1, 2), (2, 3), (0, 4)] {
+            let new_edge = NewEdge {
+                from_vertex_id: ids[from],
+                to_vertex_id: ids[to],
+                label: "grpctraverse".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            engine::api::create_edge(&mut conn, &new_edge)
+                .await
+                .unwrap();
+        }
+
+        let request = proto::TraverseRequest {
+            start_vertex_id: ids[0],
+            max_depth: 2,
+            query: Some(proto::EdgeQuery {
+                label: Some("grpctraverse".to_string()),
+                ..Default::default()
+            }),
+        };
+        let stream = service
+            .traverse(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let reached = stream
+            .map(|vertex| vertex.unwrap().id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(reached, [ids[1], ids[4], ids[2]]);
+
+        let request = proto::TraverseRequest {
+            start_vertex_id: 0,
+            max_depth: 2,
+            query: None,
+        };
+        let error = service.traverse(Request::new(request)).await.err().unwrap();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        for id in ids {
+            engine::api::soft_delete_vertex_by_id(&mut conn, id, None, "test")
+                .await
+                .unwrap();
+        }
+    }
+}
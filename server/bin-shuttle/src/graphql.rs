@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+
+use actix_web::{get, post, web, HttpResponse};
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_graphql::http::GraphiQLSource;
+use async_graphql::{Context, EmptySubscription, Enum, ErrorExtensions, Json, Object, Schema};
+use chrono::NaiveDateTime;
+use diesel_async::pooled_connection::deadpool::Object as PooledConnection;
+use diesel_async::AsyncPgConnection;
+use engine::dto::{Direction, EdgeQuery, NewEdge, NewVertex};
+use engine::error::{Error, ErrorResponse};
+use engine::model::{Edge, Vertex};
+use validator::Validate;
+
+use crate::rest::DbPool;
+
+pub type GraphQLSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+pub fn schema(pool: DbPool) -> GraphQLSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(pool)
+        .finish()
+}
+
+#[post("/graphql")]
+pub async fn post_graphql(
+    schema: web::Data<GraphQLSchema>,
+    pool: web::Data<DbPool>,
+    request: web::Json<async_graphql::Request>,
+) -> HttpResponse {
+    // The loaders are created per request so that their caches never outlive it.
+    let request = request
+        .into_inner()
+        .data(DataLoader::new(
+            VertexLoader(pool.get_ref().clone()),
+            tokio::spawn,
+        ))
+        .data(DataLoader::new(
+            IncidentEdgeLoader(pool.get_ref().clone()),
+            tokio::spawn,
+        ));
+
+    HttpResponse::Ok().json(schema.execute(request).await)
+}
+
+#[get("/graphql")]
+pub async fn get_graphiql() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+#[graphql(name = "Direction", remote = "engine::dto::Direction")]
+pub enum DirectionArgument {
+    Outgoing,
+    Incoming,
+    Both,
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn vertex(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+    ) -> async_graphql::Result<Option<VertexNode>> {
+        let vertex = vertex_loader(ctx).load_one(id).await?;
+        Ok(vertex.map(VertexNode))
+    }
+
+    /// The vertices among `ids` that exist, in the order of `ids`.
+    async fn vertices(
+        &self,
+        ctx: &Context<'_>,
+        ids: Vec<i32>,
+    ) -> async_graphql::Result<Vec<VertexNode>> {
+        let mut vertices = vertex_loader(ctx).load_many(ids.iter().copied()).await?;
+        Ok(ids
+            .iter()
+            .filter_map(|id| vertices.remove(id))
+            .map(VertexNode)
+            .collect())
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn create_vertex(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        #[graphql(name = "type")] type_: String,
+        created_by: String,
+        #[graphql(default)] labels: Vec<String>,
+    ) -> async_graphql::Result<VertexNode> {
+        let new_vertex = NewVertex {
+            name,
+            type_,
+            created_by,
+            labels,
+        };
+        new_vertex.validate().map_err(Error::from).map_err(error)?;
+
+        let mut conn = connection(ctx).await?;
+        let vertex = engine::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .map_err(error)?;
+        Ok(VertexNode(vertex))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn create_edge(
+        &self,
+        ctx: &Context<'_>,
+        from_vertex_id: i32,
+        to_vertex_id: i32,
+        label: String,
+        created_by: String,
+        #[graphql(default)] undirected: bool,
+        edge_key: Option<String>,
+        #[graphql(default)] properties: Json<serde_json::Map<String, serde_json::Value>>,
+        #[graphql(default = 1.0)] weight: f64,
+        valid_from: Option<NaiveDateTime>,
+        valid_to: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<EdgeNode> {
+        let new_edge = NewEdge {
+            from_vertex_id,
+            to_vertex_id,
+            label,
+            created_by,
+            undirected,
+            edge_key,
+            properties: properties.0,
+            weight,
+            valid_from,
+            valid_to,
+        };
+        new_edge.validate().map_err(Error::from).map_err(error)?;
+
+        let mut conn = connection(ctx).await?;
+        let edge = engine::api::create_edge(&mut conn, &new_edge)
+            .await
+            .map_err(error)?;
+        Ok(EdgeNode(edge))
+    }
+
+    /// Moves the vertex and its edges to the trash, following the delete
+    /// policies of their labels. Returns whether the vertex existed.
+    async fn delete_vertex(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        deleted_by: String,
+        expected_version: Option<i32>,
+    ) -> async_graphql::Result<bool> {
+        let mut conn = connection(ctx).await?;
+        let deleted =
+            engine::api::soft_delete_vertex_by_id(&mut conn, id, expected_version, &deleted_by)
+                .await
+                .map_err(error)?;
+        Ok(deleted > 0)
+    }
+
+    /// Moves the edge to the trash. Returns whether the edge existed.
+    async fn delete_edge(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        deleted_by: String,
+        expected_version: Option<i32>,
+    ) -> async_graphql::Result<bool> {
+        let mut conn = connection(ctx).await?;
+        let deleted =
+            engine::api::soft_delete_edge_by_id(&mut conn, id, expected_version, &deleted_by)
+                .await
+                .map_err(error)?;
+        Ok(deleted > 0)
+    }
+}
+
+pub struct VertexNode(Vertex);
+
+#[Object(name = "Vertex")]
+impl VertexNode {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    #[graphql(name = "type")]
+    async fn type_(&self) -> &str {
+        &self.0.type_
+    }
+
+    /// All labels of the vertex, its type included.
+    async fn labels(&self) -> Vec<&str> {
+        self.0.labels.iter().map(String::as_str).collect()
+    }
+
+    async fn created_by(&self) -> &str {
+        &self.0.created_by
+    }
+
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    async fn updated_by(&self) -> &str {
+        &self.0.updated_by
+    }
+
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    async fn version(&self) -> i32 {
+        self.0.version
+    }
+
+    /// Edges leaving the vertex, optionally only those with `label`, leading
+    /// to vertices of `type` or valid at `validAt`.
+    async fn out_edges(
+        &self,
+        ctx: &Context<'_>,
+        label: Option<String>,
+        #[graphql(name = "type")] type_: Option<String>,
+        valid_at: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<Vec<EdgeNode>> {
+        let edges = self
+            .incident_edges(ctx, Direction::Outgoing, label, type_, valid_at)
+            .await?;
+        Ok(edges.into_iter().map(EdgeNode).collect())
+    }
+
+    /// Edges arriving at the vertex, optionally only those with `label`,
+    /// coming from vertices of `type` or valid at `validAt`.
+    async fn in_edges(
+        &self,
+        ctx: &Context<'_>,
+        label: Option<String>,
+        #[graphql(name = "type")] type_: Option<String>,
+        valid_at: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<Vec<EdgeNode>> {
+        let edges = self
+            .incident_edges(ctx, Direction::Incoming, label, type_, valid_at)
+            .await?;
+        Ok(edges.into_iter().map(EdgeNode).collect())
+    }
+
+    /// Vertices one hop away, optionally only of `type` or along edges with
+    /// `label` or valid at `validAt`.
+    async fn neighbors(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default_with = "DirectionArgument::Outgoing")] direction: DirectionArgument,
+        label: Option<String>,
+        #[graphql(name = "type")] type_: Option<String>,
+        valid_at: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<Vec<VertexNode>> {
+        let edges = self
+            .incident_edges(ctx, direction.into(), label, type_, valid_at)
+            .await?;
+
+        let mut neighbor_ids = Vec::new();
+        for edge in &edges {
+            let neighbor_id = self.neighbor_of(edge).0;
+            if !neighbor_ids.contains(&neighbor_id) {
+                neighbor_ids.push(neighbor_id);
+            }
+        }
+
+        let mut vertices = vertex_loader(ctx)
+            .load_many(neighbor_ids.iter().copied())
+            .await?;
+        Ok(neighbor_ids
+            .iter()
+            .filter_map(|id| vertices.remove(id))
+            .map(VertexNode)
+            .collect())
+    }
+}
+
+impl VertexNode {
+    async fn incident_edges(
+        &self,
+        ctx: &Context<'_>,
+        direction: Direction,
+        label: Option<String>,
+        type_: Option<String>,
+        valid_at: Option<NaiveDateTime>,
+    ) -> async_graphql::Result<Vec<Edge>> {
+        let key = IncidentEdges {
+            vertex_id: self.0.id,
+            direction,
+            label,
+        };
+        let mut edges = ctx
+            .data_unchecked::<DataLoader<IncidentEdgeLoader>>()
+            .load_one(key)
+            .await?
+            .unwrap_or_default();
+
+        if let Some(type_) = type_ {
+            edges.retain(|edge| self.neighbor_of(edge).1 == type_);
+        }
+        if let Some(valid_at) = valid_at {
+            edges.retain(|edge| edge.validity().contains(valid_at));
+        }
+        Ok(edges)
+    }
+
+    /// The id and type of the other endpoint of `edge`.
+    fn neighbor_of<'a>(&self, edge: &'a Edge) -> (i32, &'a str) {
+        if edge.from_vertex_id == self.0.id {
+            (edge.to_vertex_id, &edge.to_vertex_type)
+        } else {
+            (edge.from_vertex_id, &edge.from_vertex_type)
+        }
+    }
+}
+
+pub struct EdgeNode(Edge);
+
+#[Object(name = "Edge")]
+impl EdgeNode {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn label(&self) -> &str {
+        &self.0.label
+    }
+
+    async fn from_vertex_id(&self) -> i32 {
+        self.0.from_vertex_id
+    }
+
+    async fn from_vertex_type(&self) -> &str {
+        &self.0.from_vertex_type
+    }
+
+    async fn to_vertex_id(&self) -> i32 {
+        self.0.to_vertex_id
+    }
+
+    async fn to_vertex_type(&self) -> &str {
+        &self.0.to_vertex_type
+    }
+
+    async fn created_by(&self) -> &str {
+        &self.0.created_by
+    }
+
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    async fn updated_by(&self) -> &str {
+        &self.0.updated_by
+    }
+
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    async fn version(&self) -> i32 {
+        self.0.version
+    }
+
+    async fn undirected(&self) -> bool {
+        self.0.undirected
+    }
+
+    async fn edge_key(&self) -> Option<&str> {
+        self.0.edge_key.as_deref()
+    }
+
+    async fn properties(&self) -> Json<&serde_json::Value> {
+        Json(&self.0.properties)
+    }
+
+    async fn weight(&self) -> f64 {
+        self.0.weight
+    }
+
+    async fn valid_from(&self) -> Option<NaiveDateTime> {
+        self.0.valid_from
+    }
+
+    async fn valid_to(&self) -> Option<NaiveDateTime> {
+        self.0.valid_to
+    }
+
+    #[graphql(name = "fromVertex")]
+    async fn source_vertex(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<VertexNode>> {
+        let vertex = vertex_loader(ctx).load_one(self.0.from_vertex_id).await?;
+        Ok(vertex.map(VertexNode))
+    }
+
+    #[graphql(name = "toVertex")]
+    async fn target_vertex(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<VertexNode>> {
+        let vertex = vertex_loader(ctx).load_one(self.0.to_vertex_id).await?;
+        Ok(vertex.map(VertexNode))
+    }
+}
+
+/// Loads live vertices by id.
+pub struct VertexLoader(DbPool);
+
+impl Loader<i32> for VertexLoader {
+    type Value = Vertex;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Vertex>, Self::Error> {
+        let mut conn = pooled_connection(&self.0).await?;
+        let vertices = engine::api::get_vertices_by_ids(&mut conn, keys, None)
+            .await
+            .map_err(error)?;
+
+        Ok(vertices
+            .into_iter()
+            .map(|vertex| (vertex.id, vertex))
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IncidentEdges {
+    pub vertex_id: i32,
+    pub direction: Direction,
+    pub label: Option<String>,
+}
+
+/// Loads the live edges of vertices, with one query per direction and label
+/// asked for.
+pub struct IncidentEdgeLoader(DbPool);
+
+impl Loader<IncidentEdges> for IncidentEdgeLoader {
+    type Value = Vec<Edge>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[IncidentEdges],
+    ) -> Result<HashMap<IncidentEdges, Vec<Edge>>, Self::Error> {
+        let mut vertex_ids = HashMap::<(Direction, Option<String>), Vec<i32>>::new();
+        for key in keys {
+            vertex_ids
+                .entry((key.direction, key.label.clone()))
+                .or_default()
+                .push(key.vertex_id);
+        }
+
+        let mut conn = pooled_connection(&self.0).await?;
+        let mut result = HashMap::new();
+        for ((direction, label), vertex_ids) in vertex_ids {
+            let query = EdgeQuery {
+                direction,
+                label: label.clone(),
+                ..Default::default()
+            };
+            let mut edges =
+                engine::api::get_incident_edges_by_vertex_ids(&mut conn, &vertex_ids, &query)
+                    .await
+                    .map_err(error)?;
+            for vertex_id in vertex_ids {
+                let key = IncidentEdges {
+                    vertex_id,
+                    direction,
+                    label: label.clone(),
+                };
+                result.insert(key, edges.remove(&vertex_id).unwrap_or_default());
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+fn vertex_loader<'a>(ctx: &Context<'a>) -> &'a DataLoader<VertexLoader> {
+    ctx.data_unchecked::<DataLoader<VertexLoader>>()
+}
+
+async fn connection(
+    ctx: &Context<'_>,
+) -> async_graphql::Result<PooledConnection<AsyncPgConnection>> {
+    pooled_connection(ctx.data_unchecked::<DbPool>()).await
+}
+
+async fn pooled_connection(
+    pool: &DbPool,
+) -> async_graphql::Result<PooledConnection<AsyncPgConnection>> {
+    pool.get()
+        .await
+        .map_err(|e| error(Error::Other(std::io::Error::other(e))))
+}
+
+/// Reports an engine error the way the REST endpoints do, with the
+/// [`ErrorResponse`] kind as the `kind` extension.
+fn error(error: Error) -> async_graphql::Error {
+    let response = ErrorResponse::from(&error);
+    async_graphql::Error::new(response.message).extend_with(|_, extensions| {
+        extensions.set(
+            "kind",
+            async_graphql::to_value(response.kind).unwrap_or_default(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use diesel::connection::InstrumentationEvent;
+    use diesel_async::pooled_connection::deadpool::Pool;
+    use diesel_async::pooled_connection::{
+        AsyncDieselConnectionManager, ManagerConfig, RecyclingMethod,
+    };
+    use diesel_async::AsyncConnection;
+    use futures_util::FutureExt;
+
+    /// A pool whose connections record every statement they run, without
+    /// the pings of recycling.
+    fn recording_pool(database_url: String, statements: Arc<Mutex<Vec<String>>>) -> DbPool {
+        let mut config = ManagerConfig::default();
+        config.recycling_method = RecyclingMethod::Fast;
+        config.custom_setup = Box::new(move |url| {
+            let statements = statements.clone();
+            async move {
+                let mut conn = AsyncPgConnection::establish(url).await?;
+                conn.set_instrumentation(move |event: InstrumentationEvent<'_>| {
+                    if let InstrumentationEvent::StartQuery { query, .. } = event {
+                        statements.lock().unwrap().push(query.to_string());
+                    }
+                });
+                Ok(conn)
+            }
+            .boxed()
+        });
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(
+            database_url,
+            config,
+        );
+        Pool::builder(manager).build().unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_loaders_batch() {
+        dotenvy::from_path("../engine/.env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let statements = Arc::new(Mutex::new(Vec::new()));
+        let pool = recording_pool(database_url, statements.clone());
+        let mut conn = pool.get().await.unwrap();
+
+        let mut vertices = Vec::new();
+        for name in ["source", "target"] {
+            for n in 0..3 {
+                let new_vertex = NewVertex {
+                    name: format!("graphqlbatch{name}{n}"),
+                    type_: "graphqlbatch".to_string(),
+                    created_by: "test".to_string(),
+                    labels: Vec::new(),
+                };
+                let vertex = engine::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap();
+                vertices.push(vertex.id);
+            }
+        }
+        let (source_ids, target_ids) = vertices.split_at(3);
+        for (&from_vertex_id, &to_vertex_id) in source_ids.iter().zip(target_ids) {
+            let new_edge = NewEdge {
+                from_vertex_id,
+                to_vertex_id,
+                label: "graphqlbatch".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            engine::api::create_edge(&mut conn, &new_edge)
+                .await
+                .unwrap();
+        }
+        statements.lock().unwrap().clear();
+
+        let query = format!(
+            "{{ vertices(ids: {source_ids:?}) {{ outEdges(label: \"graphqlbatch\") {{ toVertex {{ name }} }} }} }}"
+        );
+        let request = async_graphql::Request::new(query)
+            .data(DataLoader::new(VertexLoader(pool.clone()), tokio::spawn))
+            .data(DataLoader::new(
+                IncidentEdgeLoader(pool.clone()),
+                tokio::spawn,
+            ));
+        let response = schema(pool.clone()).execute(request).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let data = response.data.into_json().unwrap();
+        let names: Vec<&str> = data["vertices"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|vertex| vertex["outEdges"][0]["toVertex"]["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "graphqlbatchtarget0",
+                "graphqlbatchtarget1",
+                "graphqlbatchtarget2"
+            ]
+        );
+
+        // One query for the sources, one for their edges and one for the
+        // targets, however many vertices are asked for.
+        let statements = statements.lock().unwrap().clone();
+        assert_eq!(statements.len(), 3, "{statements:#?}");
+
+        for id in vertices {
+            engine::api::soft_delete_vertex_by_id(&mut conn, id, None, "test")
+                .await
+                .unwrap();
+        }
+    }
+}
@@ -1,3 +1,50 @@
-fn main() {
-    println!("Hello, world!");
+mod graphql;
+mod openapi;
+mod rest;
+mod sse;
+
+use actix_web::web::{self, ServiceConfig};
+use diesel_async::pooled_connection::deadpool::Pool;
+use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+use diesel_async::AsyncPgConnection;
+use shuttle_actix_web::ShuttleActixWeb;
+use shuttle_runtime::SecretStore;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+
+#[shuttle_runtime::main]
+async fn main(
+    #[shuttle_runtime::Secrets] secrets: SecretStore,
+) -> ShuttleActixWeb<impl FnOnce(&mut ServiceConfig) + Send + Clone + 'static> {
+    let database_url = secrets
+        .get("DATABASE_URL")
+        .expect("DATABASE_URL must be set");
+
+    let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(&database_url);
+    let pool = Pool::builder(manager)
+        .build()
+        .expect("Error building the connection pool");
+    let schema = web::Data::new(graphql::schema(pool.clone()));
+    let pool = web::Data::new(pool);
+    let changes = web::Data::new(sse::ChangeBroadcast::start(&database_url));
+
+    let config = move |cfg: &mut ServiceConfig| {
+        cfg.app_data(pool)
+            .app_data(changes)
+            .app_data(schema)
+            .service(rest::create_vertex)
+            .service(rest::get_vertex)
+            .service(rest::delete_vertex)
+            .service(rest::create_edge)
+            .service(rest::delete_edge)
+            .service(sse::get_changes)
+            .service(graphql::post_graphql)
+            .service(graphql::get_graphiql)
+            .service(
+                SwaggerUi::new("/swagger-ui/{_:.*}")
+                    .url("/openapi.json", openapi::ApiDoc::openapi()),
+            );
+    };
+
+    Ok(config.into())
 }
@@ -0,0 +1,71 @@
+use engine::dto::{NewEdge, NewVertex};
+use engine::error::{ErrorKind, ErrorResponse};
+use engine::model::{DeletePolicy, DeletePreview, Edge, IncidentLabel, Vertex};
+use utoipa::OpenApi;
+
+use crate::rest;
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        rest::create_vertex,
+        rest::get_vertex,
+        rest::delete_vertex,
+        rest::create_edge,
+        rest::delete_edge,
+    ),
+    components(schemas(
+        NewVertex,
+        NewEdge,
+        Vertex,
+        Edge,
+        DeletePreview,
+        IncidentLabel,
+        DeletePolicy,
+        ErrorKind,
+        ErrorResponse
+    )),
+    tags(
+        (name = "vertex"),
+        (name = "edge"),
+    )
+)]
+pub struct ApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use engine::constant::MIN_IDENTIFIER_LENGTH;
+    use engine::pattern::{EDGE_LABEL_LIKE, USERNAME_LIKE};
+    use utoipa_swagger_ui::SwaggerUi;
+
+    #[actix_web::test]
+    async fn test_openapi_json() {
+        let app =
+            test::init_service(App::new().service(
+                SwaggerUi::new("/swagger-ui/{_:.*}").url("/openapi.json", ApiDoc::openapi()),
+            ))
+            .await;
+
+        let request = test::TestRequest::get().uri("/openapi.json").to_request();
+        let document: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        let schemas = &document["components"]["schemas"];
+
+        for schema in ["NewVertex", "Vertex"] {
+            let properties = schemas[schema]["properties"].as_object().unwrap();
+            assert!(properties.contains_key("type"), "{schema} has no type");
+            assert!(!properties.contains_key("type_"), "{schema} has type_");
+        }
+
+        let created_by = &schemas["NewVertex"]["properties"]["created_by"];
+        assert_eq!(created_by["pattern"], USERNAME_LIKE.as_str());
+        assert_eq!(created_by["minLength"], MIN_IDENTIFIER_LENGTH);
+
+        let new_edge = &schemas["NewEdge"]["properties"];
+        assert_eq!(new_edge["label"]["pattern"], EDGE_LABEL_LIKE.as_str());
+        assert_eq!(new_edge["label"]["minLength"], MIN_IDENTIFIER_LENGTH);
+        assert_eq!(new_edge["from_vertex_id"]["minimum"], 1);
+        assert_eq!(new_edge["to_vertex_id"]["minimum"], 1);
+    }
+}
@@ -0,0 +1,433 @@
+use std::fmt;
+
+use actix_web::http::{header, StatusCode};
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, ResponseError};
+use diesel_async::pooled_connection::deadpool::{Object, Pool};
+use diesel_async::AsyncPgConnection;
+use engine::dto::{NewEdge, NewVertex};
+use engine::error::{Error, ErrorKind, ErrorResponse};
+use engine::model::{version_from_etag, DeletePolicy, DeletePreview, Edge, Vertex};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use validator::Validate;
+
+pub type DbPool = Pool<AsyncPgConnection>;
+
+/// An engine error turned into a JSON [`ErrorResponse`] with a matching status.
+#[derive(Debug)]
+pub struct ApiError(Error);
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        ApiError(error)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self.0.kind() {
+            ErrorKind::Validation => StatusCode::BAD_REQUEST,
+            ErrorKind::NotFound => StatusCode::NOT_FOUND,
+            ErrorKind::AlreadyExists | ErrorKind::DeleteRestricted => StatusCode::CONFLICT,
+            ErrorKind::Conflict => StatusCode::PRECONDITION_FAILED,
+            ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(ErrorResponse::from(&self.0))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteQuery {
+    /// User recorded as having deleted the entity.
+    pub deleted_by: String,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeleteVertexQuery {
+    /// User recorded as having deleted the vertex.
+    pub deleted_by: String,
+    /// Only report what the delete would remove.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/vertices",
+    tag = "vertex",
+    request_body = NewVertex,
+    responses(
+        (status = 201, body = Vertex, headers(("ETag" = String))),
+        (status = 400, body = ErrorResponse),
+        (status = 409, body = ErrorResponse, description = "A vertex with the same name and type exists"),
+    )
+)]
+#[post("/vertices")]
+pub async fn create_vertex(
+    pool: web::Data<DbPool>,
+    new_vertex: web::Json<NewVertex>,
+) -> Result<HttpResponse, ApiError> {
+    new_vertex.validate().map_err(Error::from)?;
+
+    let mut conn = connection(&pool).await?;
+    let vertex = engine::api::create_vertex(&mut conn, &new_vertex).await?;
+
+    Ok(HttpResponse::Created()
+        .insert_header((header::ETAG, vertex.etag()))
+        .json(vertex))
+}
+
+#[utoipa::path(
+    get,
+    path = "/vertices/{id}",
+    tag = "vertex",
+    params(("id" = i32, Path)),
+    responses(
+        (status = 200, body = Vertex, headers(("ETag" = String))),
+        (status = 304, description = "The vertex matches `If-None-Match`"),
+        (status = 404, body = ErrorResponse),
+    )
+)]
+#[get("/vertices/{id}")]
+pub async fn get_vertex(
+    pool: web::Data<DbPool>,
+    id: web::Path<i32>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let mut conn = connection(&pool).await?;
+    let vertex = engine::api::get_vertex_by_id(&mut conn, id.into_inner(), None).await?;
+
+    let etag = vertex.etag();
+    let cached = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(version_from_etag)
+        .is_some_and(|version| version == vertex.version);
+    if cached {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .finish());
+    }
+
+    Ok(HttpResponse::Ok()
+        .insert_header((header::ETAG, etag))
+        .json(vertex))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/vertices/{id}",
+    tag = "vertex",
+    description = "Moves the vertex and its edges to the trash, following the delete policies of their labels.",
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = Option<String>, Header, description = "ETag the vertex must still have"),
+        DeleteVertexQuery,
+    ),
+    responses(
+        (status = 200, body = DeletePreview, description = "What the delete would remove, for a dry run"),
+        (status = 204),
+        (status = 404, body = ErrorResponse),
+        (status = 409, body = ErrorResponse, description = "Edges of a restricting label exist"),
+        (status = 412, body = ErrorResponse, description = "The vertex no longer matches `If-Match`"),
+    )
+)]
+#[delete("/vertices/{id}")]
+pub async fn delete_vertex(
+    pool: web::Data<DbPool>,
+    id: web::Path<i32>,
+    query: web::Query<DeleteVertexQuery>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let expected_version = if_match(&request)?;
+
+    let mut conn = connection(&pool).await?;
+    let preview = engine::api::delete_vertex(
+        &mut conn,
+        id.into_inner(),
+        DeletePolicy::default(),
+        query.dry_run,
+        false,
+        expected_version,
+        &query.deleted_by,
+    )
+    .await?;
+    if query.dry_run {
+        return Ok(HttpResponse::Ok().json(preview));
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/edges",
+    tag = "edge",
+    request_body = NewEdge,
+    responses(
+        (status = 201, body = Edge, headers(("ETag" = String))),
+        (status = 400, body = ErrorResponse),
+        (status = 404, body = ErrorResponse, description = "An endpoint does not exist"),
+        (status = 409, body = ErrorResponse, description = "The edge exists"),
+    )
+)]
+#[post("/edges")]
+pub async fn create_edge(
+    pool: web::Data<DbPool>,
+    new_edge: web::Json<NewEdge>,
+) -> Result<HttpResponse, ApiError> {
+    new_edge.validate().map_err(Error::from)?;
+
+    let mut conn = connection(&pool).await?;
+    let edge = engine::api::create_edge(&mut conn, &new_edge).await?;
+
+    Ok(HttpResponse::Created()
+        .insert_header((header::ETAG, edge.etag()))
+        .json(edge))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/edges/{id}",
+    tag = "edge",
+    description = "Moves the edge to the trash.",
+    params(
+        ("id" = i32, Path),
+        ("If-Match" = Option<String>, Header, description = "ETag the edge must still have"),
+        DeleteQuery,
+    ),
+    responses(
+        (status = 204),
+        (status = 404, body = ErrorResponse),
+        (status = 412, body = ErrorResponse, description = "The edge no longer matches `If-Match`"),
+    )
+)]
+#[delete("/edges/{id}")]
+pub async fn delete_edge(
+    pool: web::Data<DbPool>,
+    id: web::Path<i32>,
+    query: web::Query<DeleteQuery>,
+    request: HttpRequest,
+) -> Result<HttpResponse, ApiError> {
+    let expected_version = if_match(&request)?;
+
+    let mut conn = connection(&pool).await?;
+    let deleted = engine::api::soft_delete_edge_by_id(
+        &mut conn,
+        id.into_inner(),
+        expected_version,
+        &query.deleted_by,
+    )
+    .await?;
+    if deleted == 0 {
+        return Err(Error::Database(diesel::result::Error::NotFound).into());
+    }
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn connection(pool: &DbPool) -> Result<Object<AsyncPgConnection>, ApiError> {
+    pool.get()
+        .await
+        .map_err(|e| Error::Other(std::io::Error::other(e)).into())
+}
+
+/// The version required by the `If-Match` header, if any.
+pub fn if_match(request: &HttpRequest) -> Result<Option<i32>, ApiError> {
+    let Some(value) = request.headers().get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+
+    value
+        .to_str()
+        .ok()
+        .and_then(version_from_etag)
+        .map(Some)
+        .ok_or_else(|| Error::Validation(validator::ValidationErrors::new()).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use diesel_async::pooled_connection::AsyncDieselConnectionManager;
+
+    #[actix_web::test]
+    async fn test_vertex_preconditions() {
+        dotenvy::from_path("../engine/.env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+        let pool = Pool::builder(manager).build().unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .service(create_vertex)
+                .service(get_vertex)
+                .service(delete_vertex),
+        )
+        .await;
+
+        let request = test::TestRequest::post()
+            .uri("/vertices")
+            .set_json(serde_json::json!({
+                "name": "restpreconditions",
+                "type": "restpreconditions",
+                "created_by": "test",
+                "labels": [],
+            }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        let vertex: Vertex = test::read_body_json(response).await;
+        assert_eq!(etag, vertex.etag());
+        let uri = format!("/vertices/{}", vertex.id);
+
+        let request = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header((header::IF_NONE_MATCH, etag.as_str()))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::ETAG).unwrap(), etag.as_str());
+
+        let stale = format!("\"{}\"", vertex.version + 1);
+        let request = test::TestRequest::get()
+            .uri(&uri)
+            .insert_header((header::IF_NONE_MATCH, stale.as_str()))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let delete_uri = format!("{uri}?deleted_by=test");
+        let request = test::TestRequest::delete()
+            .uri(&delete_uri)
+            .insert_header((header::IF_MATCH, "not an etag"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let request = test::TestRequest::delete()
+            .uri(&delete_uri)
+            .insert_header((header::IF_MATCH, stale.as_str()))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PRECONDITION_FAILED);
+        let error: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(error["kind"], "conflict");
+
+        let request = test::TestRequest::delete()
+            .uri(&delete_uri)
+            .insert_header((header::IF_MATCH, format!("W/{etag}")))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let request = test::TestRequest::get().uri(&uri).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_delete_vertex_preview() {
+        dotenvy::from_path("../engine/.env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = AsyncDieselConnectionManager::<AsyncPgConnection>::new(database_url);
+        let pool = Pool::builder(manager).build().unwrap();
+
+        let mut conn = pool.get().await.unwrap();
+        let mut vertices = Vec::new();
+        for name in ["restdeletehub", "restdeletespoke"] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: "restdeletepreview".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                engine::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let new_edge = NewEdge {
+            from_vertex_id: vertices[0].id,
+            to_vertex_id: vertices[1].id,
+            label: "restdeleterestrict".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        engine::api::create_edge(&mut conn, &new_edge)
+            .await
+            .unwrap();
+        let new_setting = engine::dto::NewEdgeLabelSetting {
+            label: "restdeleterestrict".to_string(),
+            delete_policy: DeletePolicy::Restrict,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops: false,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        engine::api::save_edge_label_setting(&mut conn, &new_setting)
+            .await
+            .unwrap();
+        drop(conn);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(pool))
+                .service(get_vertex)
+                .service(delete_vertex),
+        )
+        .await;
+        let uri = format!("/vertices/{}", vertices[0].id);
+
+        let request = test::TestRequest::delete()
+            .uri(&format!("{uri}?deleted_by=test&dry_run=true"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let preview: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(preview["deleted"], false);
+        assert_eq!(preview["neighbors"][0]["id"], vertices[1].id);
+        assert_eq!(
+            preview["incident_labels"],
+            serde_json::json!([{
+                "label": "restdeleterestrict",
+                "delete_policy": "restrict",
+                "edge_count": 1,
+            }])
+        );
+
+        let request = test::TestRequest::delete()
+            .uri(&format!("{uri}?deleted_by=test"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let error: serde_json::Value = test::read_body_json(response).await;
+        assert_eq!(error["kind"], "delete_restricted");
+
+        let request = test::TestRequest::get().uri(&uri).to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::web::{self, Bytes};
+use actix_web::{get, HttpResponse, Responder};
+use engine::change::{ChangeEntity, ChangeEvent, ChangeFilter};
+use futures_util::{stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+const CHANGE_BUFFER: usize = 1024;
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Fans the engine's change notifications out to the connected clients, so the
+/// service holds a single `LISTEN` connection however many clients there are.
+pub struct ChangeBroadcast {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl ChangeBroadcast {
+    pub fn start(database_url: &str) -> Self {
+        let (sender, _) = broadcast::channel(CHANGE_BUFFER);
+
+        let mut subscription = engine::change::subscribe(database_url, ChangeFilter::default());
+        let forward = sender.clone();
+        tokio::spawn(async move {
+            while let Some(event) = subscription.next().await {
+                match event {
+                    // Fails only while nobody is connected.
+                    Ok(event) => {
+                        let _ = forward.send(event);
+                    }
+                    Err(e) => tracing::error!("Change subscription failed: {e}"),
+                }
+            }
+        });
+
+        ChangeBroadcast { sender }
+    }
+}
+
+/// Query of `/changes`. Every parameter is optional and narrows the stream
+/// down further; `vertex_id` follows the vertex and its incident edges.
+#[derive(Debug, Deserialize)]
+pub struct ChangeQuery {
+    pub entity: Option<ChangeEntity>,
+    pub vertex_id: Option<i32>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub label: Option<String>,
+}
+
+impl From<ChangeQuery> for ChangeFilter {
+    fn from(query: ChangeQuery) -> Self {
+        ChangeFilter {
+            entity: query.entity,
+            vertex_ids: query.vertex_id.into_iter().collect(),
+            types: query.type_.into_iter().collect(),
+            labels: query.label.into_iter().collect(),
+        }
+    }
+}
+
+/// Streams the matching changes as server-sent events.
+///
+/// Each change is sent as a JSON `data` line with the outbox event id as its
+/// `id`. A client that falls too far behind receives a `lagged` event telling
+/// how many changes it missed, and should reload what it displays.
+#[get("/changes")]
+pub async fn get_changes(
+    changes: web::Data<ChangeBroadcast>,
+    query: web::Query<ChangeQuery>,
+) -> impl Responder {
+    let filter = ChangeFilter::from(query.into_inner());
+    let receiver = changes.sender.subscribe();
+    let keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+
+    let events = stream::unfold(
+        (receiver, keep_alive, filter),
+        |(mut receiver, mut keep_alive, filter)| async move {
+            let message = loop {
+                tokio::select! {
+                    event = receiver.recv() => match event {
+                        Ok(event) if filter.matches(&event) => break change_message(&event),
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            break format!("event: lagged\ndata: {{\"skipped\":{skipped}}}\n\n")
+                        }
+                        Err(RecvError::Closed) => return None,
+                    },
+                    _ = keep_alive.tick() => break ": keep-alive\n\n".to_string(),
+                }
+            };
+
+            Some((
+                Ok::<_, actix_web::Error>(Bytes::from(message)),
+                (receiver, keep_alive, filter),
+            ))
+        },
+    );
+
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events)
+}
+
+fn change_message(event: &ChangeEvent) -> String {
+    // A `ChangeEvent` only holds plain values, so serializing cannot fail.
+    let data = serde_json::to_string(event).expect("change events serialize to JSON");
+    format!("id: {}\ndata: {data}\n\n", event.event_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::future::poll_fn;
+    use std::pin::{pin, Pin};
+
+    use super::*;
+    use actix_web::body::MessageBody;
+    use actix_web::{test, App};
+    use chrono::DateTime;
+    use engine::change::ChangeOperation;
+
+    fn vertex_event(event_id: i64, id: i32) -> ChangeEvent {
+        ChangeEvent {
+            event_id,
+            entity: ChangeEntity::Vertex,
+            operation: ChangeOperation::Insert,
+            id,
+            type_: Some("sse".to_string()),
+            label: None,
+            from_vertex_id: None,
+            from_vertex_type: None,
+            to_vertex_id: None,
+            to_vertex_type: None,
+            version: 1,
+            actor: "test".to_string(),
+            changed_at: DateTime::UNIX_EPOCH.naive_utc(),
+        }
+    }
+
+    async fn next_message<B>(mut body: Pin<&mut B>) -> String
+    where
+        B: MessageBody,
+        B::Error: fmt::Debug,
+    {
+        let chunk = poll_fn(|cx| body.as_mut().poll_next(cx))
+            .await
+            .unwrap()
+            .unwrap();
+        String::from_utf8(chunk.to_vec()).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn test_get_changes() {
+        let (sender, _) = broadcast::channel(CHANGE_BUFFER);
+        let changes = web::Data::new(ChangeBroadcast {
+            sender: sender.clone(),
+        });
+        let app = test::init_service(App::new().app_data(changes).service(get_changes)).await;
+
+        let request = test::TestRequest::get()
+            .uri("/changes?vertex_id=1")
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let mut body = pin!(response.into_body());
+
+        // The keep-alive timer fires as soon as the stream starts.
+        assert_eq!(next_message(body.as_mut()).await, ": keep-alive\n\n");
+
+        sender.send(vertex_event(10, 2)).unwrap();
+        let event = vertex_event(11, 1);
+        sender.send(event.clone()).unwrap();
+        let data = serde_json::to_string(&event).unwrap();
+        assert_eq!(
+            next_message(body.as_mut()).await,
+            format!("id: 11\ndata: {data}\n\n")
+        );
+
+        for event_id in 0..CHANGE_BUFFER as i64 + 2 {
+            sender.send(vertex_event(event_id, 1)).unwrap();
+        }
+        assert_eq!(
+            next_message(body.as_mut()).await,
+            "event: lagged\ndata: {\"skipped\":2}\n\n"
+        );
+        assert!(next_message(body.as_mut()).await.starts_with("id: 2\n"));
+    }
+}
@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS record_edge_history ON edge;
+DROP TRIGGER IF EXISTS record_vertex_history ON vertex;
+DROP FUNCTION IF EXISTS record_edge_history;
+DROP FUNCTION IF EXISTS record_vertex_history;
+DROP FUNCTION IF EXISTS history_actor;
+DROP TABLE IF EXISTS edge_history;
+DROP TABLE IF EXISTS vertex_history;
+DROP FUNCTION IF EXISTS reject_history_change;
@@ -0,0 +1,111 @@
+-- Append-only change log for vertices and edges. Rows are written by triggers,
+-- so cascaded deletes are captured as well. The actor of a delete is taken from
+-- the transaction-local `broccoli.actor` setting when present.
+CREATE TABLE vertex_history (
+    id BIGSERIAL PRIMARY KEY,
+    vertex_id INT NOT NULL,
+    operation VARCHAR(6) NOT NULL CHECK (operation IN ('INSERT', 'UPDATE', 'DELETE')),
+    old_row JSONB,
+    new_row JSONB,
+    changed_by VARCHAR(255) NOT NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX vertex_history_vertex_id ON vertex_history (vertex_id);
+CREATE INDEX vertex_history_changed_at ON vertex_history (changed_at);
+CREATE INDEX vertex_history_changed_by ON vertex_history (changed_by);
+
+CREATE TABLE edge_history (
+    id BIGSERIAL PRIMARY KEY,
+    edge_id INT NOT NULL,
+    operation VARCHAR(6) NOT NULL CHECK (operation IN ('INSERT', 'UPDATE', 'DELETE')),
+    old_row JSONB,
+    new_row JSONB,
+    changed_by VARCHAR(255) NOT NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX edge_history_edge_id ON edge_history (edge_id);
+CREATE INDEX edge_history_changed_at ON edge_history (changed_at);
+CREATE INDEX edge_history_changed_by ON edge_history (changed_by);
+
+CREATE OR REPLACE FUNCTION history_actor(fallback VARCHAR)
+RETURNS VARCHAR AS $$
+BEGIN
+    RETURN COALESCE(NULLIF(current_setting('broccoli.actor', true), ''), fallback);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_vertex_history()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'INSERT') THEN
+        INSERT INTO vertex_history (vertex_id, operation, new_row, changed_by)
+        VALUES (NEW.id, TG_OP, to_jsonb(NEW), NEW.created_by);
+        RETURN NEW;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        INSERT INTO vertex_history (vertex_id, operation, old_row, new_row, changed_by)
+        VALUES (NEW.id, TG_OP, to_jsonb(OLD), to_jsonb(NEW), NEW.updated_by);
+        RETURN NEW;
+    ELSE
+        INSERT INTO vertex_history (vertex_id, operation, old_row, changed_by)
+        VALUES (OLD.id, TG_OP, to_jsonb(OLD), history_actor(OLD.updated_by));
+        RETURN OLD;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION record_edge_history()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'INSERT') THEN
+        INSERT INTO edge_history (edge_id, operation, new_row, changed_by)
+        VALUES (NEW.id, TG_OP, to_jsonb(NEW), NEW.created_by);
+        RETURN NEW;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        INSERT INTO edge_history (edge_id, operation, old_row, new_row, changed_by)
+        VALUES (NEW.id, TG_OP, to_jsonb(OLD), to_jsonb(NEW), NEW.updated_by);
+        RETURN NEW;
+    ELSE
+        INSERT INTO edge_history (edge_id, operation, old_row, changed_by)
+        VALUES (OLD.id, TG_OP, to_jsonb(OLD), history_actor(OLD.updated_by));
+        RETURN OLD;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_vertex_history
+AFTER INSERT OR UPDATE OR DELETE ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION record_vertex_history();
+
+CREATE TRIGGER record_edge_history
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION record_edge_history();
+
+-- The log itself is never rewritten.
+CREATE OR REPLACE FUNCTION reject_history_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER vertex_history_append_only
+BEFORE UPDATE OR DELETE OR TRUNCATE ON vertex_history
+FOR EACH STATEMENT
+EXECUTE FUNCTION reject_history_change();
+
+CREATE TRIGGER edge_history_append_only
+BEFORE UPDATE OR DELETE OR TRUNCATE ON edge_history
+FOR EACH STATEMENT
+EXECUTE FUNCTION reject_history_change();
+
+-- Seed the log with the rows that already exist so that every live row has an
+-- INSERT image to start from.
+INSERT INTO vertex_history (vertex_id, operation, new_row, changed_by, changed_at)
+SELECT id, 'INSERT', to_jsonb(vertex), created_by, created_at FROM vertex;
+
+INSERT INTO edge_history (edge_id, operation, new_row, changed_by, changed_at)
+SELECT id, 'INSERT', to_jsonb(edge), created_by, created_at FROM edge;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS edge_history_to_vertex_id;
+DROP INDEX IF EXISTS edge_history_from_vertex_id;
@@ -0,0 +1,5 @@
+-- Point-in-time reads look up the edges of a vertex from their logged images.
+CREATE INDEX edge_history_from_vertex_id
+    ON edge_history (((COALESCE(new_row, old_row) ->> 'from_vertex_id')::INT));
+CREATE INDEX edge_history_to_vertex_id
+    ON edge_history (((COALESCE(new_row, old_row) ->> 'to_vertex_id')::INT));
@@ -0,0 +1,16 @@
+DROP INDEX IF EXISTS edge_from_to_label;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label);
+
+DROP INDEX IF EXISTS vertex_name_type;
+CREATE UNIQUE INDEX vertex_name_type ON vertex (name, type);
+
+DROP INDEX IF EXISTS edge_deleted_at;
+DROP INDEX IF EXISTS vertex_deleted_at;
+
+ALTER TABLE edge
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
+
+ALTER TABLE vertex
+    DROP COLUMN deleted_by,
+    DROP COLUMN deleted_at;
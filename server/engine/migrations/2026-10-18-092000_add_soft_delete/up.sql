@@ -0,0 +1,17 @@
+ALTER TABLE vertex
+    ADD COLUMN deleted_at TIMESTAMP,
+    ADD COLUMN deleted_by VARCHAR(255);
+
+ALTER TABLE edge
+    ADD COLUMN deleted_at TIMESTAMP,
+    ADD COLUMN deleted_by VARCHAR(255);
+
+CREATE INDEX vertex_deleted_at ON vertex (deleted_at);
+CREATE INDEX edge_deleted_at ON edge (deleted_at);
+
+-- Rows in the trash must not block re-creating the same vertex or edge.
+DROP INDEX vertex_name_type;
+CREATE UNIQUE INDEX vertex_name_type ON vertex (name, type) WHERE deleted_at IS NULL;
+
+DROP INDEX edge_from_to_label;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL;
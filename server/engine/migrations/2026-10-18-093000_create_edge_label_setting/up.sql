@@ -0,0 +1,16 @@
+-- Per-label settings for edges. Labels without a row use the defaults.
+-- (`edge_label` is already taken by the index on `edge.label`.)
+CREATE TABLE edge_label_setting (
+    label VARCHAR(255) PRIMARY KEY,
+    delete_policy VARCHAR(8) NOT NULL DEFAULT 'cascade'
+        CHECK (delete_policy IN ('cascade', 'restrict', 'detach')),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_by VARCHAR(255) NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by VARCHAR(255) NOT NULL
+);
+
+CREATE TRIGGER update_edge_label_setting_updated_at
+BEFORE UPDATE ON edge_label_setting
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,6 @@
+DROP TRIGGER IF EXISTS increment_edge_version ON edge;
+DROP TRIGGER IF EXISTS increment_vertex_version ON vertex;
+DROP FUNCTION IF EXISTS increment_version_column;
+
+ALTER TABLE edge DROP COLUMN version;
+ALTER TABLE vertex DROP COLUMN version;
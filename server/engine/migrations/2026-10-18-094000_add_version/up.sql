@@ -0,0 +1,22 @@
+-- Optimistic concurrency control: every update bumps the row version, and
+-- writers can require the version they last read.
+ALTER TABLE vertex ADD COLUMN version INT NOT NULL DEFAULT 1;
+ALTER TABLE edge ADD COLUMN version INT NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION increment_version_column()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version = OLD.version + 1;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER increment_vertex_version
+BEFORE UPDATE ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION increment_version_column();
+
+CREATE TRIGGER increment_edge_version
+BEFORE UPDATE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION increment_version_column();
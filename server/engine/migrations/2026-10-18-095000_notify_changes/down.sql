@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS notify_edge_change ON edge;
+DROP TRIGGER IF EXISTS notify_vertex_change ON vertex;
+DROP FUNCTION IF EXISTS notify_change;
@@ -0,0 +1,64 @@
+-- Publishes a compact JSON event on the `broccoli_changes` channel for every
+-- change to a vertex or an edge. Moving a row to the trash is published as a
+-- DELETE and restoring it as an INSERT, since that is what readers observe.
+CREATE OR REPLACE FUNCTION notify_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    operation VARCHAR := TG_OP;
+    actor VARCHAR;
+    payload JSONB;
+BEGIN
+    IF (TG_OP = 'INSERT') THEN
+        actor := NEW.created_by;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        actor := NEW.updated_by;
+        IF (OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL) THEN
+            operation := 'DELETE';
+        ELSIF (OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL) THEN
+            operation := 'INSERT';
+        ELSIF (NEW.deleted_at IS NOT NULL) THEN
+            RETURN NEW;
+        END IF;
+    ELSE
+        actor := history_actor(OLD.updated_by);
+    END IF;
+
+    IF (TG_TABLE_NAME = 'vertex') THEN
+        payload := jsonb_build_object(
+            'entity', 'vertex',
+            'id', COALESCE(NEW.id, OLD.id),
+            'type', COALESCE(NEW.type, OLD.type)
+        );
+    ELSE
+        payload := jsonb_build_object(
+            'entity', 'edge',
+            'id', COALESCE(NEW.id, OLD.id),
+            'label', COALESCE(NEW.label, OLD.label),
+            'from_vertex_id', COALESCE(NEW.from_vertex_id, OLD.from_vertex_id),
+            'from_vertex_type', COALESCE(NEW.from_vertex_type, OLD.from_vertex_type),
+            'to_vertex_id', COALESCE(NEW.to_vertex_id, OLD.to_vertex_id),
+            'to_vertex_type', COALESCE(NEW.to_vertex_type, OLD.to_vertex_type)
+        );
+    END IF;
+
+    payload := payload || jsonb_build_object(
+        'operation', operation,
+        'version', COALESCE(NEW.version, OLD.version),
+        'actor', actor,
+        'changed_at', NOW()::TIMESTAMP
+    );
+    PERFORM pg_notify('broccoli_changes', payload::TEXT);
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER notify_vertex_change
+AFTER INSERT OR UPDATE OR DELETE ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION notify_change();
+
+CREATE TRIGGER notify_edge_change
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION notify_change();
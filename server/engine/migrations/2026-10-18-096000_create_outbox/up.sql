@@ -0,0 +1,139 @@
+-- Transactional outbox: every change to a vertex or an edge is written to
+-- `outbox` by a trigger in the same transaction as the change itself. Live
+-- subscribers are notified from the outbox, and webhook deliveries are
+-- scheduled from it.
+CREATE TABLE outbox (
+    id BIGSERIAL PRIMARY KEY,
+    event JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    dispatched_at TIMESTAMP
+);
+
+CREATE INDEX outbox_undispatched ON outbox (id) WHERE dispatched_at IS NULL;
+CREATE INDEX outbox_created_at ON outbox (created_at);
+
+CREATE TABLE webhook (
+    id SERIAL PRIMARY KEY,
+    url VARCHAR(2048) NOT NULL,
+    secret VARCHAR(255) NOT NULL,
+    filter JSONB NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_by VARCHAR(255) NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by VARCHAR(255) NOT NULL
+);
+
+CREATE TRIGGER update_webhook_updated_at
+BEFORE UPDATE ON webhook
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TABLE webhook_delivery (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id INT NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    outbox_id BIGINT NOT NULL REFERENCES outbox(id) ON DELETE CASCADE,
+    attempts INT NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    delivered_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX webhook_delivery_webhook_outbox ON webhook_delivery (webhook_id, outbox_id);
+CREATE INDEX webhook_delivery_pending ON webhook_delivery (next_attempt_at) WHERE delivered_at IS NULL;
+
+CREATE TABLE webhook_dead_letter (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id INT NOT NULL REFERENCES webhook(id) ON DELETE CASCADE,
+    outbox_id BIGINT NOT NULL,
+    event JSONB NOT NULL,
+    attempts INT NOT NULL,
+    last_error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX webhook_dead_letter_webhook_id ON webhook_dead_letter (webhook_id);
+
+-- Same event as before, written to the outbox instead of notified directly.
+CREATE OR REPLACE FUNCTION publish_change()
+RETURNS TRIGGER AS $$
+DECLARE
+    operation VARCHAR := TG_OP;
+    actor VARCHAR;
+    payload JSONB;
+BEGIN
+    IF (TG_OP = 'INSERT') THEN
+        actor := NEW.created_by;
+    ELSIF (TG_OP = 'UPDATE') THEN
+        actor := NEW.updated_by;
+        IF (OLD.deleted_at IS NULL AND NEW.deleted_at IS NOT NULL) THEN
+            operation := 'DELETE';
+        ELSIF (OLD.deleted_at IS NOT NULL AND NEW.deleted_at IS NULL) THEN
+            operation := 'INSERT';
+        ELSIF (NEW.deleted_at IS NOT NULL) THEN
+            RETURN NEW;
+        END IF;
+    ELSE
+        actor := history_actor(OLD.updated_by);
+    END IF;
+
+    IF (TG_TABLE_NAME = 'vertex') THEN
+        payload := jsonb_build_object(
+            'entity', 'vertex',
+            'id', COALESCE(NEW.id, OLD.id),
+            'type', COALESCE(NEW.type, OLD.type)
+        );
+    ELSE
+        payload := jsonb_build_object(
+            'entity', 'edge',
+            'id', COALESCE(NEW.id, OLD.id),
+            'label', COALESCE(NEW.label, OLD.label),
+            'from_vertex_id', COALESCE(NEW.from_vertex_id, OLD.from_vertex_id),
+            'from_vertex_type', COALESCE(NEW.from_vertex_type, OLD.from_vertex_type),
+            'to_vertex_id', COALESCE(NEW.to_vertex_id, OLD.to_vertex_id),
+            'to_vertex_type', COALESCE(NEW.to_vertex_type, OLD.to_vertex_type)
+        );
+    END IF;
+
+    payload := payload || jsonb_build_object(
+        'operation', operation,
+        'version', COALESCE(NEW.version, OLD.version),
+        'actor', actor,
+        'changed_at', NOW()::TIMESTAMP
+    );
+    INSERT INTO outbox (event) VALUES (payload);
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION notify_outbox_event()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify(
+        'broccoli_changes',
+        (NEW.event || jsonb_build_object('event_id', NEW.id))::TEXT
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER notify_vertex_change ON vertex;
+DROP TRIGGER notify_edge_change ON edge;
+DROP FUNCTION notify_change;
+
+CREATE TRIGGER publish_vertex_change
+AFTER INSERT OR UPDATE OR DELETE ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION publish_change();
+
+CREATE TRIGGER publish_edge_change
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION publish_change();
+
+CREATE TRIGGER notify_outbox_event
+AFTER INSERT ON outbox
+FOR EACH ROW
+EXECUTE FUNCTION notify_outbox_event();
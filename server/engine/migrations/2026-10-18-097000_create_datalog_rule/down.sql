@@ -0,0 +1,13 @@
+DROP TRIGGER IF EXISTS mark_datalog_dirty ON edge;
+DROP FUNCTION IF EXISTS mark_datalog_dirty;
+
+DELETE FROM edge WHERE derived;
+
+DROP INDEX IF EXISTS edge_from_to_label_derived;
+DROP INDEX IF EXISTS edge_from_to_label;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL;
+
+ALTER TABLE edge DROP COLUMN IF EXISTS derived;
+
+DROP TABLE IF EXISTS datalog_dirty_label;
+DROP TABLE IF EXISTS datalog_rule;
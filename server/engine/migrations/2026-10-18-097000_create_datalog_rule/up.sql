@@ -0,0 +1,50 @@
+-- Inference rules over edge labels. Facts derived by the rules are stored as
+-- edges marked `derived`; they never block or replace an asserted edge with
+-- the same endpoints and label.
+CREATE TABLE datalog_rule (
+    id SERIAL PRIMARY KEY,
+    rule TEXT NOT NULL,
+    head_label VARCHAR(255) NOT NULL,
+    body_labels VARCHAR(255)[] NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_by VARCHAR(255) NOT NULL
+);
+
+CREATE INDEX datalog_rule_head_label ON datalog_rule (head_label);
+
+-- Labels whose asserted edges changed since the derived edges depending on
+-- them were last brought up to date.
+CREATE TABLE datalog_dirty_label (
+    label VARCHAR(255) PRIMARY KEY
+);
+
+ALTER TABLE edge ADD COLUMN derived BOOLEAN NOT NULL DEFAULT FALSE;
+
+DROP INDEX edge_from_to_label;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL AND NOT derived;
+CREATE UNIQUE INDEX edge_from_to_label_derived ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL AND derived;
+
+CREATE OR REPLACE FUNCTION mark_datalog_dirty()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (COALESCE(NEW.derived, OLD.derived)) THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO datalog_dirty_label (label)
+    SELECT DISTINCT changed.label
+    FROM (VALUES (OLD.label), (NEW.label)) AS changed (label)
+    WHERE changed.label IS NOT NULL AND EXISTS (
+        SELECT 1 FROM datalog_rule
+        WHERE changed.label = head_label OR changed.label = ANY(body_labels)
+    )
+    ON CONFLICT DO NOTHING;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER mark_datalog_dirty
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION mark_datalog_dirty();
@@ -0,0 +1,13 @@
+DROP FUNCTION IF EXISTS refresh_edge_mirrors;
+DROP TRIGGER IF EXISTS maintain_edge_mirror ON edge;
+DROP FUNCTION IF EXISTS maintain_edge_mirror;
+DROP FUNCTION IF EXISTS edge_mirror_label;
+
+DELETE FROM edge WHERE mirror_of IS NOT NULL;
+
+DROP INDEX IF EXISTS edge_mirror_of;
+ALTER TABLE edge DROP COLUMN IF EXISTS mirror_of;
+
+ALTER TABLE edge_label_setting DROP CONSTRAINT IF EXISTS edge_label_setting_inverse_label;
+ALTER TABLE edge_label_setting DROP COLUMN IF EXISTS "symmetric";
+ALTER TABLE edge_label_setting DROP COLUMN IF EXISTS inverse_label;
@@ -0,0 +1,113 @@
+-- Labels can be symmetric (`married_to`) or come in inverse pairs
+-- (`employs`/`employed_by`). Every asserted edge of such a label is paired
+-- with a mirror edge in the other direction, which points back at it through
+-- `mirror_of` and lives and dies with it.
+ALTER TABLE edge_label_setting ADD COLUMN inverse_label VARCHAR(255);
+ALTER TABLE edge_label_setting ADD COLUMN "symmetric" BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE edge_label_setting ADD CONSTRAINT edge_label_setting_inverse_label
+    CHECK (inverse_label IS NULL OR (inverse_label <> label AND NOT "symmetric"));
+
+ALTER TABLE edge ADD COLUMN mirror_of INT REFERENCES edge (id) ON DELETE CASCADE;
+CREATE INDEX edge_mirror_of ON edge (mirror_of);
+
+-- The label of the mirror of an edge labeled `edge_label`, if any. The
+-- inverse of a pair is declared on one side or on both.
+CREATE OR REPLACE FUNCTION edge_mirror_label(edge_label VARCHAR)
+RETURNS VARCHAR AS $$
+    SELECT partner FROM (
+        SELECT CASE WHEN "symmetric" THEN label ELSE inverse_label END AS partner
+        FROM edge_label_setting WHERE label = edge_label
+        UNION ALL
+        SELECT label FROM edge_label_setting WHERE inverse_label = edge_label
+    ) AS partners
+    WHERE partner IS NOT NULL
+    LIMIT 1;
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION maintain_edge_mirror()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        -- Deleting an edge deletes its mirror through the foreign key. Deleting
+        -- a mirror deletes its edge, unless the mirror is dropped because the
+        -- edge was relabeled or its label settings changed.
+        IF (OLD.mirror_of IS NOT NULL) THEN
+            DELETE FROM edge
+            WHERE id = OLD.mirror_of AND edge_mirror_label(label) = OLD.label;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (NEW.derived) THEN
+        RETURN NULL;
+    END IF;
+
+    -- Trashing or restoring either side of a pair does the same to the other.
+    IF (NEW.mirror_of IS NOT NULL) THEN
+        IF (TG_OP = 'UPDATE' AND NEW.deleted_at IS DISTINCT FROM OLD.deleted_at) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE id = NEW.mirror_of AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (TG_OP = 'UPDATE') THEN
+        IF (NEW.label = OLD.label) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE mirror_of = NEW.id AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+            RETURN NULL;
+        END IF;
+
+        DELETE FROM edge WHERE mirror_of = NEW.id;
+    END IF;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of
+    )
+    SELECT NEW.to_vertex_id, NEW.to_vertex_type, NEW.from_vertex_id, NEW.from_vertex_type, mirror.label,
+        NEW.created_by, NEW.updated_by, NEW.deleted_at, NEW.deleted_by, NEW.id
+    FROM (SELECT edge_mirror_label(NEW.label) AS label) AS mirror
+    WHERE mirror.label IS NOT NULL
+        AND NOT (NEW.from_vertex_id = NEW.to_vertex_id AND mirror.label = NEW.label);
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER maintain_edge_mirror
+AFTER INSERT OR UPDATE OF label, deleted_at OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION maintain_edge_mirror();
+
+-- Pairs the existing edges of `labels` with mirrors as their settings now
+-- require. Edges whose mirror position is already taken by an asserted edge
+-- are left unpaired.
+CREATE OR REPLACE FUNCTION refresh_edge_mirrors(labels VARCHAR[])
+RETURNS VOID AS $$
+BEGIN
+    DELETE FROM edge AS mirror
+    USING edge AS original
+    WHERE mirror.mirror_of = original.id
+        AND original.label = ANY(labels)
+        AND edge_mirror_label(original.label) IS DISTINCT FROM mirror.label;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of
+    )
+    SELECT original.to_vertex_id, original.to_vertex_type, original.from_vertex_id, original.from_vertex_type,
+        edge_mirror_label(original.label), original.created_by, original.updated_by,
+        original.deleted_at, original.deleted_by, original.id
+    FROM edge AS original
+    WHERE original.label = ANY(labels)
+        AND original.mirror_of IS NULL
+        AND NOT original.derived
+        AND edge_mirror_label(original.label) IS NOT NULL
+        AND NOT (original.from_vertex_id = original.to_vertex_id AND edge_mirror_label(original.label) = original.label)
+        AND NOT EXISTS (SELECT 1 FROM edge AS mirror WHERE mirror.mirror_of = original.id)
+    ON CONFLICT DO NOTHING;
+END;
+$$ LANGUAGE plpgsql;
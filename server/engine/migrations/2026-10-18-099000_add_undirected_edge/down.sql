@@ -0,0 +1,90 @@
+DROP TRIGGER IF EXISTS order_undirected_edge_endpoints ON edge;
+DROP FUNCTION IF EXISTS order_undirected_edge_endpoints;
+
+CREATE OR REPLACE FUNCTION maintain_edge_mirror()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        -- Deleting an edge deletes its mirror through the foreign key. Deleting
+        -- a mirror deletes its edge, unless the mirror is dropped because the
+        -- edge was relabeled or its label settings changed.
+        IF (OLD.mirror_of IS NOT NULL) THEN
+            DELETE FROM edge
+            WHERE id = OLD.mirror_of AND edge_mirror_label(label) = OLD.label;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (NEW.derived) THEN
+        RETURN NULL;
+    END IF;
+
+    -- Trashing or restoring either side of a pair does the same to the other.
+    IF (NEW.mirror_of IS NOT NULL) THEN
+        IF (TG_OP = 'UPDATE' AND NEW.deleted_at IS DISTINCT FROM OLD.deleted_at) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE id = NEW.mirror_of AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (TG_OP = 'UPDATE') THEN
+        IF (NEW.label = OLD.label) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE mirror_of = NEW.id AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+            RETURN NULL;
+        END IF;
+
+        DELETE FROM edge WHERE mirror_of = NEW.id;
+    END IF;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of
+    )
+    SELECT NEW.to_vertex_id, NEW.to_vertex_type, NEW.from_vertex_id, NEW.from_vertex_type, mirror.label,
+        NEW.created_by, NEW.updated_by, NEW.deleted_at, NEW.deleted_by, NEW.id
+    FROM (SELECT edge_mirror_label(NEW.label) AS label) AS mirror
+    WHERE mirror.label IS NOT NULL
+        AND NOT (NEW.from_vertex_id = NEW.to_vertex_id AND mirror.label = NEW.label);
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION refresh_edge_mirrors(labels VARCHAR[])
+RETURNS VOID AS $$
+BEGIN
+    DELETE FROM edge AS mirror
+    USING edge AS original
+    WHERE mirror.mirror_of = original.id
+        AND original.label = ANY(labels)
+        AND edge_mirror_label(original.label) IS DISTINCT FROM mirror.label;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of
+    )
+    SELECT original.to_vertex_id, original.to_vertex_type, original.from_vertex_id, original.from_vertex_type,
+        edge_mirror_label(original.label), original.created_by, original.updated_by,
+        original.deleted_at, original.deleted_by, original.id
+    FROM edge AS original
+    WHERE original.label = ANY(labels)
+        AND original.mirror_of IS NULL
+        AND NOT original.derived
+        AND edge_mirror_label(original.label) IS NOT NULL
+        AND NOT (original.from_vertex_id = original.to_vertex_id AND edge_mirror_label(original.label) = original.label)
+        AND NOT EXISTS (SELECT 1 FROM edge AS mirror WHERE mirror.mirror_of = original.id)
+    ON CONFLICT DO NOTHING;
+END;
+$$ LANGUAGE plpgsql;
+
+DELETE FROM edge WHERE undirected;
+
+DROP INDEX IF EXISTS edge_from_to_label_undirected;
+DROP INDEX IF EXISTS edge_from_to_label;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL AND NOT derived;
+
+ALTER TABLE edge DROP COLUMN IF EXISTS undirected;
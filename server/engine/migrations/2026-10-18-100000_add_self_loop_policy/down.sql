@@ -0,0 +1 @@
+ALTER TABLE edge_label_setting DROP COLUMN IF EXISTS allow_self_loops;
@@ -0,0 +1,2 @@
+-- Whether edges of a label may start and end at the same vertex.
+ALTER TABLE edge_label_setting ADD COLUMN allow_self_loops BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,113 @@
+-- Labels in multigraph mode allow parallel edges between the same vertices,
+-- told apart by their key. Edges without an explicit key are keyed by their
+-- properties, so parallel edges that cannot be told apart are still rejected.
+ALTER TABLE edge_label_setting ADD COLUMN multigraph BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE edge ADD COLUMN edge_key VARCHAR(255);
+ALTER TABLE edge ADD COLUMN properties JSONB NOT NULL DEFAULT '{}';
+
+DROP INDEX edge_from_to_label;
+DROP INDEX edge_from_to_label_undirected;
+CREATE UNIQUE INDEX edge_from_to_label ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL AND NOT derived AND NOT undirected AND edge_key IS NULL;
+CREATE UNIQUE INDEX edge_from_to_label_undirected ON edge (from_vertex_id, to_vertex_id, label) WHERE deleted_at IS NULL AND NOT derived AND undirected AND edge_key IS NULL;
+CREATE UNIQUE INDEX edge_from_to_label_key ON edge (from_vertex_id, to_vertex_id, label, edge_key) WHERE deleted_at IS NULL AND NOT derived AND edge_key IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION key_multigraph_edge()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (NEW.edge_key IS NULL AND NOT NEW.derived AND EXISTS (
+        SELECT 1 FROM edge_label_setting WHERE label = NEW.label AND multigraph
+    )) THEN
+        NEW.edge_key := md5(NEW.properties::TEXT);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER key_multigraph_edge
+BEFORE INSERT ON edge
+FOR EACH ROW
+EXECUTE FUNCTION key_multigraph_edge();
+
+-- Mirrors carry the key and properties of their edge, so that parallel edges
+-- have parallel mirrors.
+CREATE OR REPLACE FUNCTION maintain_edge_mirror()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'DELETE') THEN
+        -- Deleting an edge deletes its mirror through the foreign key. Deleting
+        -- a mirror deletes its edge, unless the mirror is dropped because the
+        -- edge was relabeled or its label settings changed.
+        IF (OLD.mirror_of IS NOT NULL) THEN
+            DELETE FROM edge
+            WHERE id = OLD.mirror_of AND edge_mirror_label(label) = OLD.label;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (NEW.derived OR NEW.undirected) THEN
+        RETURN NULL;
+    END IF;
+
+    -- Trashing or restoring either side of a pair does the same to the other.
+    IF (NEW.mirror_of IS NOT NULL) THEN
+        IF (TG_OP = 'UPDATE' AND NEW.deleted_at IS DISTINCT FROM OLD.deleted_at) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE id = NEW.mirror_of AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+        END IF;
+        RETURN NULL;
+    END IF;
+
+    IF (TG_OP = 'UPDATE') THEN
+        IF (NEW.label = OLD.label) THEN
+            UPDATE edge
+            SET deleted_at = NEW.deleted_at, deleted_by = NEW.deleted_by, updated_by = NEW.updated_by
+            WHERE mirror_of = NEW.id AND deleted_at IS DISTINCT FROM NEW.deleted_at;
+            RETURN NULL;
+        END IF;
+
+        DELETE FROM edge WHERE mirror_of = NEW.id;
+    END IF;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of, edge_key, properties
+    )
+    SELECT NEW.to_vertex_id, NEW.to_vertex_type, NEW.from_vertex_id, NEW.from_vertex_type, mirror.label,
+        NEW.created_by, NEW.updated_by, NEW.deleted_at, NEW.deleted_by, NEW.id, NEW.edge_key, NEW.properties
+    FROM (SELECT edge_mirror_label(NEW.label) AS label) AS mirror
+    WHERE mirror.label IS NOT NULL
+        AND NOT (NEW.from_vertex_id = NEW.to_vertex_id AND mirror.label = NEW.label);
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION refresh_edge_mirrors(labels VARCHAR[])
+RETURNS VOID AS $$
+BEGIN
+    DELETE FROM edge AS mirror
+    USING edge AS original
+    WHERE mirror.mirror_of = original.id
+        AND original.label = ANY(labels)
+        AND edge_mirror_label(original.label) IS DISTINCT FROM mirror.label;
+
+    INSERT INTO edge (
+        from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label,
+        created_by, updated_by, deleted_at, deleted_by, mirror_of, edge_key, properties
+    )
+    SELECT original.to_vertex_id, original.to_vertex_type, original.from_vertex_id, original.from_vertex_type,
+        edge_mirror_label(original.label), original.created_by, original.updated_by,
+        original.deleted_at, original.deleted_by, original.id, original.edge_key, original.properties
+    FROM edge AS original
+    WHERE original.label = ANY(labels)
+        AND original.mirror_of IS NULL
+        AND NOT original.derived
+        AND NOT original.undirected
+        AND edge_mirror_label(original.label) IS NOT NULL
+        AND NOT (original.from_vertex_id = original.to_vertex_id AND edge_mirror_label(original.label) = original.label)
+        AND NOT EXISTS (SELECT 1 FROM edge AS mirror WHERE mirror.mirror_of = original.id)
+    ON CONFLICT DO NOTHING;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,22 @@
+-- Provenance of edges: each source asserting an edge leaves one assertion on
+-- it, with how confident it is and what it based the assertion on. Mirror
+-- edges carry no assertions of their own; they share those of their edge.
+CREATE TABLE edge_assertion (
+    id BIGSERIAL PRIMARY KEY,
+    edge_id INTEGER NOT NULL REFERENCES edge (id) ON DELETE CASCADE,
+    source VARCHAR(255) NOT NULL,
+    confidence DOUBLE PRECISION NOT NULL CHECK (confidence >= 0 AND confidence <= 1),
+    evidence JSONB NOT NULL DEFAULT '[]' CHECK (jsonb_typeof(evidence) = 'array'),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_by VARCHAR(255) NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_by VARCHAR(255) NOT NULL
+);
+
+CREATE UNIQUE INDEX edge_assertion_edge_source ON edge_assertion (edge_id, source);
+CREATE INDEX edge_assertion_source ON edge_assertion (source);
+
+CREATE TRIGGER update_edge_assertion_updated_at
+BEFORE UPDATE ON edge_assertion
+FOR EACH ROW
+EXECUTE FUNCTION update_updated_at_column();
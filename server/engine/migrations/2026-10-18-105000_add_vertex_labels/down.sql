@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS label_vertex_with_type ON vertex;
+DROP FUNCTION IF EXISTS label_vertex_with_type();
+DROP INDEX IF EXISTS vertex_labels;
+ALTER TABLE vertex DROP COLUMN IF EXISTS labels;
@@ -0,0 +1,35 @@
+-- A vertex carries a set of labels. Its type is the primary label: it is
+-- always in the set, and stays the one used for uniqueness and for the
+-- denormalized endpoint types of edges.
+ALTER TABLE vertex ADD COLUMN labels TEXT[] NOT NULL DEFAULT '{}';
+
+-- Backfilling is not a change to the vertices, so it bypasses the version,
+-- history and notification triggers.
+ALTER TABLE vertex DISABLE TRIGGER USER;
+UPDATE vertex SET labels = ARRAY[type];
+ALTER TABLE vertex ENABLE TRIGGER USER;
+
+CREATE INDEX vertex_labels ON vertex USING GIN (labels) WHERE deleted_at IS NULL;
+
+-- Keeps the labels a sorted set holding the type. Retyping a vertex without
+-- touching its labels swaps the old type for the new one.
+CREATE OR REPLACE FUNCTION label_vertex_with_type()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (TG_OP = 'UPDATE' AND NEW.type <> OLD.type AND NEW.labels = OLD.labels) THEN
+        NEW.labels := array_remove(NEW.labels, OLD.type);
+    END IF;
+    NEW.labels := ARRAY(
+        SELECT DISTINCT vertex_label
+        FROM unnest(array_append(NEW.labels, NEW.type)) AS vertex_label
+        WHERE vertex_label IS NOT NULL
+        ORDER BY vertex_label
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER label_vertex_with_type
+BEFORE INSERT OR UPDATE ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION label_vertex_with_type();
@@ -0,0 +1,4 @@
+DROP TRIGGER IF EXISTS check_vertex_name_against_aliases ON vertex;
+DROP FUNCTION IF EXISTS check_vertex_name_against_aliases();
+DROP TABLE IF EXISTS vertex_alias;
+DROP FUNCTION IF EXISTS check_vertex_alias();
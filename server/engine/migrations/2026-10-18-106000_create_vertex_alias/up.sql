@@ -0,0 +1,63 @@
+-- Alternate names of vertices: abbreviations, translations, former names.
+-- `type` copies the type of the vertex, so that aliases are looked up and
+-- kept unique within a type.
+CREATE TABLE vertex_alias (
+    id SERIAL PRIMARY KEY,
+    vertex_id INTEGER NOT NULL REFERENCES vertex (id) ON DELETE CASCADE,
+    alias VARCHAR(255) NOT NULL,
+    type VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    created_by VARCHAR(255) NOT NULL
+);
+
+CREATE UNIQUE INDEX vertex_alias_alias_type ON vertex_alias (alias, type);
+CREATE INDEX vertex_alias_vertex_id ON vertex_alias (vertex_id);
+
+-- A name resolves to one vertex per type: an alias may not take the name of
+-- another live vertex, and a live vertex may not take an alias of another
+-- vertex. Aliases of trashed vertices stay reserved, so restoring one never
+-- makes a name ambiguous. The advisory lock keeps both checks for the same
+-- name from passing concurrently.
+CREATE OR REPLACE FUNCTION check_vertex_alias()
+RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext(NEW.type), hashtext(NEW.alias));
+    IF EXISTS (
+        SELECT 1 FROM vertex
+        WHERE name = NEW.alias AND type = NEW.type AND id <> NEW.vertex_id AND deleted_at IS NULL
+    ) THEN
+        RAISE EXCEPTION 'alias % is the name of another vertex of type %', NEW.alias, NEW.type
+            USING ERRCODE = 'unique_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER check_vertex_alias
+BEFORE INSERT OR UPDATE ON vertex_alias
+FOR EACH ROW
+EXECUTE FUNCTION check_vertex_alias();
+
+CREATE OR REPLACE FUNCTION check_vertex_name_against_aliases()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (NEW.deleted_at IS NULL AND (
+        TG_OP = 'INSERT' OR NEW.name <> OLD.name OR NEW.type <> OLD.type OR OLD.deleted_at IS NOT NULL
+    )) THEN
+        PERFORM pg_advisory_xact_lock(hashtext(NEW.type), hashtext(NEW.name));
+        IF EXISTS (
+            SELECT 1 FROM vertex_alias
+            WHERE alias = NEW.name AND type = NEW.type AND vertex_id <> NEW.id
+        ) THEN
+            RAISE EXCEPTION 'name % is an alias of another vertex of type %', NEW.name, NEW.type
+                USING ERRCODE = 'unique_violation';
+        END IF;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER check_vertex_name_against_aliases
+BEFORE INSERT OR UPDATE OF name, type, deleted_at ON vertex
+FOR EACH ROW
+EXECUTE FUNCTION check_vertex_name_against_aliases();
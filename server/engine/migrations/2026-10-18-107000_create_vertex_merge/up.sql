@@ -0,0 +1,20 @@
+-- Audit trail of vertex merges. `duplicates` holds the merged vertices as
+-- they were just before they were removed; the survivor is only referenced,
+-- so that the record outlives it. Edges between the merged vertices are moved
+-- to the trash, and `dropped_edge_ids` lists them.
+CREATE TABLE vertex_merge (
+    id SERIAL PRIMARY KEY,
+    survivor_id INTEGER NOT NULL,
+    duplicates JSONB NOT NULL,
+    property_merge VARCHAR(16) NOT NULL
+        CHECK (property_merge IN ('keep_survivor', 'prefer_survivor', 'prefer_duplicate')),
+    repointed_edges INTEGER NOT NULL,
+    merged_edges INTEGER NOT NULL,
+    dropped_edges INTEGER NOT NULL,
+    dropped_edge_ids INTEGER[] NOT NULL,
+    merged_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    merged_by VARCHAR(255) NOT NULL
+);
+
+CREATE INDEX vertex_merge_survivor_id ON vertex_merge (survivor_id);
+CREATE INDEX vertex_merge_duplicates ON vertex_merge USING GIN (duplicates jsonb_path_ops);
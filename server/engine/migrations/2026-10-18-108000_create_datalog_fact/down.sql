@@ -0,0 +1,34 @@
+DROP TRIGGER IF EXISTS log_datalog_fact_change ON edge;
+DROP FUNCTION IF EXISTS log_datalog_fact_change;
+
+DROP TABLE IF EXISTS datalog_fact_change;
+DROP TABLE IF EXISTS datalog_fact;
+
+CREATE OR REPLACE FUNCTION mark_datalog_dirty()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (COALESCE(NEW.derived, OLD.derived)) THEN
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO datalog_dirty_label (label)
+    SELECT DISTINCT changed.label
+    FROM (VALUES (OLD.label), (NEW.label)) AS changed (label)
+    WHERE changed.label IS NOT NULL AND EXISTS (
+        SELECT 1 FROM datalog_rule
+        WHERE changed.label = head_label OR changed.label = ANY(body_labels)
+    )
+    ON CONFLICT DO NOTHING;
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER mark_datalog_dirty
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION mark_datalog_dirty();
+
+INSERT INTO datalog_dirty_label (label)
+SELECT DISTINCT head_label FROM datalog_rule
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,69 @@
+-- The facts of every label the rules read or derive, as of the last
+-- maintenance run, and whether a live asserted edge states them. Vertices
+-- are not referenced, so that purging one cannot change the model behind
+-- maintenance's back.
+CREATE TABLE datalog_fact (
+    label VARCHAR(255) NOT NULL,
+    from_vertex_id INTEGER NOT NULL,
+    to_vertex_id INTEGER NOT NULL,
+    asserted BOOLEAN NOT NULL,
+    PRIMARY KEY (label, from_vertex_id, to_vertex_id)
+);
+
+-- Asserted facts that may have appeared or disappeared since the last
+-- maintenance run. Rows are only ever appended, so that a change committing
+-- while a run reads the log is left for the next one.
+CREATE TABLE datalog_fact_change (
+    id BIGSERIAL PRIMARY KEY,
+    label VARCHAR(255) NOT NULL,
+    from_vertex_id INTEGER NOT NULL,
+    to_vertex_id INTEGER NOT NULL
+);
+
+-- From now on `datalog_dirty_label` only holds the heads of changed rules.
+DROP TRIGGER IF EXISTS mark_datalog_dirty ON edge;
+DROP FUNCTION IF EXISTS mark_datalog_dirty;
+
+CREATE OR REPLACE FUNCTION log_datalog_fact_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF (COALESCE(NEW.derived, OLD.derived)) THEN
+        RETURN NULL;
+    END IF;
+    IF (TG_OP = 'UPDATE' AND
+        (OLD.label, OLD.from_vertex_id, OLD.to_vertex_id, OLD.undirected, OLD.deleted_at IS NULL)
+        IS NOT DISTINCT FROM
+        (NEW.label, NEW.from_vertex_id, NEW.to_vertex_id, NEW.undirected, NEW.deleted_at IS NULL)) THEN
+        RETURN NULL;
+    END IF;
+
+    -- An undirected edge states its fact both ways.
+    INSERT INTO datalog_fact_change (label, from_vertex_id, to_vertex_id)
+    SELECT DISTINCT changed.label, fact.from_vertex_id, fact.to_vertex_id
+    FROM (VALUES
+        (OLD.label, OLD.from_vertex_id, OLD.to_vertex_id, OLD.undirected),
+        (NEW.label, NEW.from_vertex_id, NEW.to_vertex_id, NEW.undirected)
+    ) AS changed (label, from_vertex_id, to_vertex_id, undirected)
+    CROSS JOIN LATERAL (
+        SELECT changed.from_vertex_id, changed.to_vertex_id
+        UNION ALL
+        SELECT changed.to_vertex_id, changed.from_vertex_id WHERE changed.undirected
+    ) AS fact (from_vertex_id, to_vertex_id)
+    WHERE changed.label IS NOT NULL AND EXISTS (
+        SELECT 1 FROM datalog_rule
+        WHERE changed.label = head_label OR changed.label = ANY(body_labels)
+    );
+
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER log_datalog_fact_change
+AFTER INSERT OR UPDATE OR DELETE ON edge
+FOR EACH ROW
+EXECUTE FUNCTION log_datalog_fact_change();
+
+-- The next run builds the model from scratch.
+INSERT INTO datalog_dirty_label (label)
+SELECT DISTINCT head_label FROM datalog_rule
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,439 @@
+//! Weighted graph algorithms. The edges they run over are selected by a
+//! [`WeightedEdgeQuery`], loaded in one query and walked in memory, the way
+//! Datalog facts are.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{
+    api::valid_at,
+    dto::{Direction, WeightedEdgeQuery},
+    error::Error,
+    model::{VertexRank, WeightedPath},
+    schema::{edge, vertex},
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+/// PageRank stops once the ranks move by less than this in total.
+const PAGE_RANK_TOLERANCE: f64 = 1e-9;
+
+/// An edge as followed in the queried direction.
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    edge_id: i32,
+    from_vertex_id: i32,
+    to_vertex_id: i32,
+    weight: f64,
+}
+
+async fn load_steps(
+    conn: &mut AsyncPgConnection,
+    query: &WeightedEdgeQuery,
+) -> Result<Vec<Step>, Error> {
+    let bounds = [query.min_weight, query.max_weight];
+    if bounds.iter().flatten().any(|bound| !bound.is_finite()) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let mut statement = edge::table
+        .filter(edge::deleted_at.is_null())
+        .select((
+            edge::id,
+            edge::from_vertex_id,
+            edge::to_vertex_id,
+            edge::weight,
+            edge::undirected,
+        ))
+        .order(edge::id.asc())
+        .into_boxed();
+    if let Some(label) = &query.label {
+        statement = statement.filter(edge::label.eq(label.clone()));
+    }
+    if let Some(min_weight) = query.min_weight {
+        statement = statement.filter(edge::weight.ge(min_weight));
+    }
+    if let Some(max_weight) = query.max_weight {
+        statement = statement.filter(edge::weight.le(max_weight));
+    }
+    if let Some(instant) = query.valid_at {
+        statement = statement.filter(valid_at(instant));
+    }
+    let rows = statement.load::<(i32, i32, i32, f64, bool)>(conn).await?;
+
+    let mut steps = Vec::new();
+    for (edge_id, from_vertex_id, to_vertex_id, weight, undirected) in rows {
+        let forward = Step {
+            edge_id,
+            from_vertex_id,
+            to_vertex_id,
+            weight,
+        };
+        let backward = Step {
+            from_vertex_id: to_vertex_id,
+            to_vertex_id: from_vertex_id,
+            ..forward
+        };
+        let (forward_followed, backward_followed) = match query.direction {
+            Direction::Outgoing => (true, undirected),
+            Direction::Incoming => (undirected, true),
+            Direction::Both => (true, true),
+        };
+        if forward_followed {
+            steps.push(forward);
+        }
+        // A self-loop followed backward is the same step again.
+        if backward_followed && (from_vertex_id != to_vertex_id || !forward_followed) {
+            steps.push(backward);
+        }
+    }
+
+    Ok(steps)
+}
+
+/// Returns a path of least total weight from `from_vertex_id` to
+/// `to_vertex_id` over the selected edges, or `None` if there is no such
+/// path or either vertex is not live. The selected edges must not weigh less
+/// than zero.
+pub async fn shortest_path(
+    conn: &mut AsyncPgConnection,
+    from_vertex_id: i32,
+    to_vertex_id: i32,
+    query: &WeightedEdgeQuery,
+) -> Result<Option<WeightedPath>, Error> {
+    if from_vertex_id < 1 || to_vertex_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let live_ids = vertex::table
+        .filter(vertex::id.eq_any([from_vertex_id, to_vertex_id]))
+        .filter(vertex::deleted_at.is_null())
+        .select(vertex::id)
+        .load::<i32>(conn)
+        .await?;
+    if !live_ids.contains(&from_vertex_id) || !live_ids.contains(&to_vertex_id) {
+        return Ok(None);
+    }
+
+    let steps = load_steps(conn, query).await?;
+    if steps.iter().any(|step| step.weight < 0.0) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    Ok(dijkstra(&steps, from_vertex_id, to_vertex_id))
+}
+
+/// A vertex waiting to be settled, ordered so that the max-heap pops the
+/// lightest one first.
+#[derive(Debug, PartialEq)]
+struct Candidate {
+    weight: f64,
+    vertex_id: i32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .weight
+            .total_cmp(&self.weight)
+            .then_with(|| other.vertex_id.cmp(&self.vertex_id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn dijkstra(steps: &[Step], from_vertex_id: i32, to_vertex_id: i32) -> Option<WeightedPath> {
+    let mut adjacency = HashMap::<i32, Vec<&Step>>::new();
+    for step in steps {
+        adjacency.entry(step.from_vertex_id).or_default().push(step);
+    }
+
+    let mut weights = HashMap::from([(from_vertex_id, 0.0)]);
+    let mut reached_by = HashMap::<i32, &Step>::new();
+    let mut candidates = BinaryHeap::from([Candidate {
+        weight: 0.0,
+        vertex_id: from_vertex_id,
+    }]);
+    while let Some(Candidate { weight, vertex_id }) = candidates.pop() {
+        if vertex_id == to_vertex_id {
+            break;
+        }
+        if weight > weights[&vertex_id] {
+            continue;
+        }
+        for step in adjacency.get(&vertex_id).into_iter().flatten() {
+            let next_weight = weight + step.weight;
+            if weights
+                .get(&step.to_vertex_id)
+                .is_none_or(|known| next_weight < *known)
+            {
+                weights.insert(step.to_vertex_id, next_weight);
+                reached_by.insert(step.to_vertex_id, step);
+                candidates.push(Candidate {
+                    weight: next_weight,
+                    vertex_id: step.to_vertex_id,
+                });
+            }
+        }
+    }
+
+    let weight = *weights.get(&to_vertex_id)?;
+    let mut vertex_ids = vec![to_vertex_id];
+    let mut edge_ids = Vec::new();
+    let mut current = to_vertex_id;
+    while current != from_vertex_id {
+        let step = reached_by[&current];
+        edge_ids.push(step.edge_id);
+        vertex_ids.push(step.from_vertex_id);
+        current = step.from_vertex_id;
+    }
+    vertex_ids.reverse();
+    edge_ids.reverse();
+
+    Some(WeightedPath {
+        vertex_ids,
+        edge_ids,
+        weight,
+    })
+}
+
+/// Ranks the vertices of the selected edges by weighted PageRank: a vertex
+/// passes its rank on along its edges in proportion to their weight, and
+/// edges weighing zero or less pass on nothing. Vertices passing nothing on
+/// share their rank with every vertex.
+///
+/// Stops after `max_iterations`, or earlier once the ranks settle. Ranks add
+/// up to 1 and are returned highest first.
+pub async fn page_rank(
+    conn: &mut AsyncPgConnection,
+    query: &WeightedEdgeQuery,
+    damping: f64,
+    max_iterations: u32,
+) -> Result<Vec<VertexRank>, Error> {
+    if !(0.0..1.0).contains(&damping) || max_iterations < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let steps = load_steps(conn, query).await?;
+
+    let mut vertex_ids = steps
+        .iter()
+        .flat_map(|step| [step.from_vertex_id, step.to_vertex_id])
+        .collect::<Vec<_>>();
+    vertex_ids.sort_unstable();
+    vertex_ids.dedup();
+    if vertex_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let index = |vertex_id: i32| vertex_ids.binary_search(&vertex_id).unwrap();
+
+    let count = vertex_ids.len() as f64;
+    let links = steps
+        .iter()
+        .filter(|step| step.weight > 0.0)
+        .map(|step| {
+            (
+                index(step.from_vertex_id),
+                index(step.to_vertex_id),
+                step.weight,
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut out_weights = vec![0.0; vertex_ids.len()];
+    for (from, _, weight) in &links {
+        out_weights[*from] += weight;
+    }
+
+    let mut ranks = vec![1.0 / count; vertex_ids.len()];
+    for _ in 0..max_iterations {
+        let dangling = ranks
+            .iter()
+            .zip(&out_weights)
+            .filter(|(_, out_weight)| **out_weight == 0.0)
+            .map(|(rank, _)| rank)
+            .sum::<f64>();
+        let mut next = vec![(1.0 - damping + damping * dangling) / count; vertex_ids.len()];
+        for (from, to, weight) in &links {
+            next[*to] += damping * ranks[*from] * weight / out_weights[*from];
+        }
+
+        let change = ranks
+            .iter()
+            .zip(&next)
+            .map(|(rank, next)| (rank - next).abs())
+            .sum::<f64>();
+        ranks = next;
+        if change < PAGE_RANK_TOLERANCE {
+            break;
+        }
+    }
+
+    let mut result = vertex_ids
+        .into_iter()
+        .zip(ranks)
+        .map(|(vertex_id, rank)| VertexRank { vertex_id, rank })
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| {
+        b.rank
+            .total_cmp(&a.rank)
+            .then_with(|| a.vertex_id.cmp(&b.vertex_id))
+    });
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+
+    use crate::dto::{Direction, NewEdge, NewVertex, UpdateEdge, WeightedEdgeQuery};
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn test_weighted_algorithms() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for name in ["algorithm_a", "algorithm_b", "algorithm_c", "algorithm_d"] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: "algorithm".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let ids = |indexes: &[usize]| {
+            indexes
+                .iter()
+                .map(|index| vertices[*index].id)
+                .collect::<Vec<_>>()
+        };
+
+        // a reaches d through b with weight 2, or through c with weight 1.
+        let mut edges = Vec::new();
+        for (from, to, label, weight) in [
+            (0, 1, "algorithm_road", 1.0),
+            (1, 3, "algorithm_road", 1.0),
+            (0, 2, "algorithm_road", 0.5),
+            (2, 3, "algorithm_road", 0.5),
+            (0, 1, "algorithm_link", 3.0),
+            (0, 2, "algorithm_link", 1.0),
+            (1, 0, "algorithm_link", 1.0),
+            (2, 0, "algorithm_link", 1.0),
+        ] {
+            let new_edge = NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: label.to_string(),
+                created_by: "test".to_string(),
+                weight,
+                ..Default::default()
+            };
+            edges.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let roads = WeightedEdgeQuery {
+            label: Some("algorithm_road".to_string()),
+            ..Default::default()
+        };
+        let path =
+            crate::algorithm::shortest_path(&mut conn, vertices[0].id, vertices[3].id, &roads)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(path.vertex_ids, ids(&[0, 2, 3]));
+        assert_eq!(path.edge_ids, vec![edges[2].id, edges[3].id]);
+        assert_eq!(path.weight, 1.0);
+
+        let path = crate::algorithm::shortest_path(
+            &mut conn,
+            vertices[3].id,
+            vertices[0].id,
+            &WeightedEdgeQuery {
+                direction: Direction::Incoming,
+                ..roads.clone()
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(path.vertex_ids, ids(&[3, 2, 0]));
+
+        // Light edges are left out under a threshold.
+        let heavy_roads = WeightedEdgeQuery {
+            min_weight: Some(0.8),
+            ..roads.clone()
+        };
+        let path = crate::algorithm::shortest_path(
+            &mut conn,
+            vertices[0].id,
+            vertices[3].id,
+            &heavy_roads,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(path.vertex_ids, ids(&[0, 1, 3]));
+
+        let update = UpdateEdge {
+            label: None,
+            weight: Some(3.0),
+            valid_from: None,
+            valid_to: None,
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_edge(&mut conn, edges[2].id, &update, None)
+            .await
+            .unwrap();
+        assert_eq!(result.weight, 3.0);
+        let path =
+            crate::algorithm::shortest_path(&mut conn, vertices[0].id, vertices[3].id, &roads)
+                .await
+                .unwrap()
+                .unwrap();
+        assert_eq!(path.weight, 2.0);
+
+        let result =
+            crate::algorithm::shortest_path(&mut conn, vertices[3].id, vertices[0].id, &roads)
+                .await;
+        assert!(matches!(result, Ok(None)));
+
+        let update = UpdateEdge {
+            weight: Some(f64::NAN),
+            ..update
+        };
+        let result = crate::api::update_edge(&mut conn, edges[2].id, &update, None).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+
+        let links = WeightedEdgeQuery {
+            label: Some("algorithm_link".to_string()),
+            ..Default::default()
+        };
+        let ranks = crate::algorithm::page_rank(&mut conn, &links, 0.85, 100)
+            .await
+            .unwrap();
+        assert_eq!(
+            ranks.iter().map(|rank| rank.vertex_id).collect::<Vec<_>>(),
+            ids(&[0, 1, 2])
+        );
+        let total = ranks.iter().map(|rank| rank.rank).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-6);
+
+        let result = crate::algorithm::page_rank(&mut conn, &links, 1.0, 100).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+}
@@ -1,12 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::{
-    dto::{InsertableNewEdge, InsertableNewVertex, NewEdge, NewVertex},
-    error::Error,
-    model::{self, Edge, Vertex},
+    constant::HISTORY_ACTOR_SETTING,
+    dto::{
+        Direction, EdgeQuery, InsertableNewEdge, InsertableNewEdgeAssertion,
+        InsertableNewEdgeLabelSetting, InsertableNewVertex, InsertableNewVertexAlias,
+        InsertableVertexMerge, MergeVertices, NewEdge, NewEdgeAssertion, NewEdgeByKey,
+        NewEdgeLabelSetting, NewVertex, NewVertexAlias, ParallelEdgeQuery, ProvenanceQuery,
+        UpdateEdge, UpdateVertex, VertexKey,
+    },
+    error::{Error, ErrorKind},
+    model::{
+        self, DeletePolicy, DeletePreview, Edge, EdgeAssertion, EdgeHistory, EdgeLabelSetting,
+        IncidentLabel, Interval, ParallelEdges, PropertyMerge, Retraction, Vertex, VertexAlias,
+        VertexHistory, VertexMerge,
+    },
+    pattern::{SOURCE_LIKE, USERNAME_LIKE},
 };
-use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use chrono::NaiveDateTime;
+use diesel::dsl::{count_star, exists, not, now};
+use diesel::pg::Pg;
+use diesel::sql_types::{Array, Bool, Int4, Jsonb, Nullable, Text, Timestamp};
+use diesel::upsert::excluded;
+use diesel::{
+    BoolExpressionMethods, BoxableExpression, ExpressionMethods, NullableExpressionMethods,
+    OptionalExtension, PgArrayExpressionMethods, PgJsonbExpressionMethods, QueryDsl,
+    QueryableByName, SelectableHelper,
+};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+/// A condition on edges that can be added to boxed queries.
+pub(crate) type EdgeCondition =
+    Box<dyn BoxableExpression<crate::schema::edge::table, Pg, SqlType = Nullable<Bool>>>;
 
 pub async fn create_vertex(
     conn: &mut AsyncPgConnection,
@@ -19,6 +47,7 @@ pub async fn create_vertex(
         type_: new_vertex.type_.clone(),
         created_by: new_vertex.created_by.clone(),
         updated_by: new_vertex.created_by.clone(),
+        labels: new_vertex.labels.clone(),
     };
 
     let result = diesel::insert_into(vertex)
@@ -35,14 +64,20 @@ pub async fn create_edge(conn: &mut AsyncPgConnection, new_edge: &NewEdge) -> Re
     use crate::schema::vertex::dsl::*;
     use crate::schema::vertex::id as VertexId;
 
+    check_weight(new_edge.weight)?;
+    check_validity(new_edge_validity(new_edge))?;
+    check_label_settings(conn, &[new_edge_usage(new_edge)]).await?;
+
     let source_vertex_type = vertex
         .filter(VertexId.eq(new_edge.from_vertex_id))
+        .filter(crate::schema::vertex::deleted_at.is_null())
         .select(type_)
         .first::<String>(conn)
         .await?;
 
     let target_vertex_type = vertex
         .filter(VertexId.eq(new_edge.to_vertex_id))
+        .filter(crate::schema::vertex::deleted_at.is_null())
         .select(type_)
         .first::<String>(conn)
         .await?;
@@ -55,6 +90,12 @@ pub async fn create_edge(conn: &mut AsyncPgConnection, new_edge: &NewEdge) -> Re
         label: new_edge.label.clone(),
         created_by: new_edge.created_by.clone(),
         updated_by: new_edge.created_by.clone(),
+        undirected: new_edge.undirected,
+        edge_key: new_edge.edge_key.clone(),
+        properties: new_edge.properties.clone().into(),
+        weight: new_edge.weight,
+        valid_from: new_edge.valid_from,
+        valid_to: new_edge.valid_to,
     };
 
     let result = diesel::insert_into(edge)
@@ -72,15 +113,22 @@ pub async fn create_edges(
 ) -> Result<Vec<Edge>, Error> {
     use crate::schema::edge::dsl::*;
 
+    for new_edge in new_edges {
+        check_weight(new_edge.weight)?;
+        check_validity(new_edge_validity(new_edge))?;
+    }
+    let usages = new_edges.iter().map(new_edge_usage).collect::<Vec<_>>();
+    check_label_settings(conn, &usages).await?;
+
     let mut id_type_map = HashMap::new();
     for new_edge in new_edges {
-        let source_vertex = match get_vertex_by_id(conn, new_edge.from_vertex_id).await {
+        let source_vertex = match get_vertex_by_id(conn, new_edge.from_vertex_id, None).await {
             Ok(source_vertex) => source_vertex,
             Err(_) => {
                 return Err(Error::Validation(validator::ValidationErrors::new()));
             }
         };
-        let target_vertex = match get_vertex_by_id(conn, new_edge.to_vertex_id).await {
+        let target_vertex = match get_vertex_by_id(conn, new_edge.to_vertex_id, None).await {
             Ok(target_vertex) => target_vertex,
             Err(_) => {
                 return Err(Error::Validation(validator::ValidationErrors::new()));
@@ -100,6 +148,12 @@ pub async fn create_edges(
             label: new_edge.label.clone(),
             created_by: new_edge.created_by.clone(),
             updated_by: new_edge.created_by.clone(),
+            undirected: new_edge.undirected,
+            edge_key: new_edge.edge_key.clone(),
+            properties: new_edge.properties.clone().into(),
+            weight: new_edge.weight,
+            valid_from: new_edge.valid_from,
+            valid_to: new_edge.valid_to,
         })
         .collect::<Vec<_>>();
 
@@ -112,7 +166,89 @@ pub async fn create_edges(
     Ok(result)
 }
 
-async fn create_vertices(
+/// Creates an edge between the vertices named by `new_edge.from` and
+/// `new_edge.to`, aliases included.
+pub async fn create_edge_by_key(
+    conn: &mut AsyncPgConnection,
+    new_edge: &NewEdgeByKey,
+) -> Result<Edge, Error> {
+    let source_vertex = get_vertex_by_key(conn, &new_edge.from).await?;
+    let target_vertex = get_vertex_by_key(conn, &new_edge.to).await?;
+
+    create_edge(
+        conn,
+        &new_edge.to_new_edge(source_vertex.id, target_vertex.id),
+    )
+    .await
+}
+
+/// Records a source's claim that an edge holds. A live edge with the same
+/// endpoints, label and key takes the assertion in; otherwise the edge is
+/// created. Returns the edge the assertion was merged into.
+pub async fn assert_edge(
+    conn: &mut AsyncPgConnection,
+    assertion: &NewEdgeAssertion,
+) -> Result<Edge, Error> {
+    use crate::schema::edge_assertion;
+
+    if !SOURCE_LIKE.is_match(&assertion.source) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+    check_confidence(assertion.confidence)?;
+
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                let asserted = match find_asserted_edge(conn, &assertion.edge).await? {
+                    Some(asserted) => asserted,
+                    None => {
+                        // A concurrent assertion may create the edge between
+                        // the lookup and the insert; the unique indexes catch
+                        // it, and the edge it created takes this one in.
+                        let created = conn
+                            .transaction::<_, Error, _>(|conn| {
+                                create_edge(conn, &assertion.edge).scope_boxed()
+                            })
+                            .await;
+                        match created {
+                            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                                find_asserted_edge(conn, &assertion.edge)
+                                    .await?
+                                    .ok_or(error)?
+                            }
+                            created => created?,
+                        }
+                    }
+                };
+                let new_assertion = InsertableNewEdgeAssertion {
+                    edge_id: asserted.mirror_of.unwrap_or(asserted.id),
+                    source: assertion.source.clone(),
+                    confidence: assertion.confidence,
+                    evidence: assertion.evidence.clone().into(),
+                    created_by: assertion.edge.created_by.clone(),
+                    updated_by: assertion.edge.created_by.clone(),
+                };
+                diesel::insert_into(edge_assertion::table)
+                    .values(&new_assertion)
+                    .on_conflict((edge_assertion::edge_id, edge_assertion::source))
+                    .do_update()
+                    .set((
+                        edge_assertion::confidence.eq(excluded(edge_assertion::confidence)),
+                        edge_assertion::evidence.eq(excluded(edge_assertion::evidence)),
+                        edge_assertion::updated_by.eq(excluded(edge_assertion::updated_by)),
+                    ))
+                    .execute(conn)
+                    .await?;
+                Ok(asserted)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn create_vertices(
     conn: &mut AsyncPgConnection,
     new_vertices: &[NewVertex],
 ) -> Result<Vec<Vertex>, Error> {
@@ -125,6 +261,7 @@ async fn create_vertices(
             type_: new_vertex.type_.clone(),
             created_by: new_vertex.created_by.clone(),
             updated_by: new_vertex.created_by.clone(),
+            labels: new_vertex.labels.clone(),
         })
         .collect::<Vec<_>>();
 
@@ -137,15 +274,96 @@ async fn create_vertices(
     Ok(result)
 }
 
-async fn get_vertex_by_id(conn: &mut AsyncPgConnection, vertext_id: i32) -> Result<Vertex, Error> {
+/// Gives a live vertex an alternate name, scoped by the vertex type.
+pub async fn create_vertex_alias(
+    conn: &mut AsyncPgConnection,
+    new_alias: &NewVertexAlias,
+) -> Result<VertexAlias, Error> {
+    use crate::schema::vertex;
+    use crate::schema::vertex_alias;
+
+    if new_alias.vertex_id < 1 || new_alias.alias.is_empty() {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let vertex_type = vertex::table
+        .filter(vertex::id.eq(new_alias.vertex_id))
+        .filter(vertex::deleted_at.is_null())
+        .select(vertex::type_)
+        .first::<String>(conn)
+        .await?;
+
+    let new_alias = InsertableNewVertexAlias {
+        vertex_id: new_alias.vertex_id,
+        alias: new_alias.alias.clone(),
+        type_: vertex_type,
+        created_by: new_alias.created_by.clone(),
+    };
+
+    let result = diesel::insert_into(vertex_alias::table)
+        .values(&new_alias)
+        .returning(VertexAlias::as_returning())
+        .get_result(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_vertex_aliases(
+    conn: &mut AsyncPgConnection,
+    aliased_vertex_id: i32,
+) -> Result<Vec<VertexAlias>, Error> {
+    use crate::schema::vertex_alias::dsl::*;
+
+    let result = vertex_alias
+        .filter(vertex_id.eq(aliased_vertex_id))
+        .order(alias.asc())
+        .select(VertexAlias::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_vertex_alias(
+    conn: &mut AsyncPgConnection,
+    aliased_vertex_id: i32,
+    vertex_alias_name: &str,
+) -> Result<usize, Error> {
+    use crate::schema::vertex_alias::dsl::*;
+
+    let result = diesel::delete(
+        vertex_alias
+            .filter(vertex_id.eq(aliased_vertex_id))
+            .filter(alias.eq(vertex_alias_name)),
+    )
+    .execute(conn)
+    .await?;
+
+    Ok(result)
+}
+
+pub async fn get_vertex_by_id(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    as_of: Option<NaiveDateTime>,
+) -> Result<Vertex, Error> {
     use crate::schema::vertex::dsl::*;
 
     if vertext_id < 1 {
         return Err(Error::Validation(validator::ValidationErrors::new()));
     }
 
+    if let Some(as_of) = as_of {
+        return get_vertices_as_of(conn, &[vertext_id], as_of)
+            .await?
+            .pop()
+            .ok_or(Error::Database(diesel::result::Error::NotFound));
+    }
+
     let result = vertex
         .filter(crate::schema::vertex::id.eq(vertext_id))
+        .filter(deleted_at.is_null())
         .select(model::Vertex::as_select())
         .first::<Vertex>(conn)
         .await?;
@@ -153,285 +371,4258 @@ async fn get_vertex_by_id(conn: &mut AsyncPgConnection, vertext_id: i32) -> Resu
     Ok(result)
 }
 
-async fn delete_vertex_by_id(
+pub async fn get_vertices_by_ids(
     conn: &mut AsyncPgConnection,
-    vertext_id: i32,
-) -> Result<usize, Error> {
+    vertex_ids: &[i32],
+    as_of: Option<NaiveDateTime>,
+) -> Result<Vec<Vertex>, Error> {
     use crate::schema::vertex::dsl::*;
 
-    if vertext_id < 1 {
-        return Err(Error::Validation(validator::ValidationErrors::new()));
+    if let Some(as_of) = as_of {
+        return get_vertices_as_of(conn, vertex_ids, as_of).await;
     }
 
-    let result = diesel::delete(vertex.filter(crate::schema::vertex::id.eq(vertext_id)))
-        .execute(conn)
+    let result = vertex
+        .filter(id.eq_any(vertex_ids))
+        .filter(deleted_at.is_null())
+        .order(id.asc())
+        .select(Vertex::as_select())
+        .load(conn)
         .await?;
 
     Ok(result)
 }
 
-#[cfg(test)]
-mod tests {
+/// Looks up the live vertices going by `vertex_name`, as their name or as an
+/// alias, optionally of one type only. Within a type a name resolves to at
+/// most one vertex.
+pub async fn find_vertices_by_name(
+    conn: &mut AsyncPgConnection,
+    vertex_name: &str,
+    vertex_type: Option<&str>,
+) -> Result<Vec<Vertex>, Error> {
+    use crate::schema::vertex;
+    use crate::schema::vertex_alias;
 
-    use crate::dto::NewVertex;
-    use crate::schema::edge;
-    use crate::schema::edge::dsl::*;
-    use diesel::{ExpressionMethods, QueryDsl};
-    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    let mut aliased = vertex_alias::table
+        .filter(vertex_alias::alias.eq(vertex_name))
+        .select(vertex_alias::vertex_id)
+        .into_boxed();
+    let mut vertices = vertex::table
+        .filter(vertex::deleted_at.is_null())
+        .into_boxed();
+    if let Some(vertex_type) = vertex_type {
+        aliased = aliased.filter(vertex_alias::type_.eq(vertex_type));
+        vertices = vertices.filter(vertex::type_.eq(vertex_type));
+    }
 
-    #[tokio::test]
-    async fn test_create_vertex() {
-        dotenvy::from_path(".env").ok();
+    let result = vertices
+        .filter(vertex::name.eq(vertex_name).or(vertex::id.eq_any(aliased)))
+        .order(vertex::id.asc())
+        .select(Vertex::as_select())
+        .load(conn)
+        .await?;
 
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    Ok(result)
+}
 
-        let new_vertex = NewVertex {
-            name: "create_vertex".to_string(),
-            type_: "create_vertex".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Resolves a key to the live vertex it names.
+pub async fn get_vertex_by_key(
+    conn: &mut AsyncPgConnection,
+    key: &VertexKey,
+) -> Result<Vertex, Error> {
+    find_vertices_by_name(conn, &key.name, Some(&key.type_))
+        .await?
+        .into_iter()
+        .next()
+        .ok_or(Error::Database(diesel::result::Error::NotFound))
+}
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let result = crate::api::create_vertex(&mut conn, &new_vertex)
-            .await
-            .unwrap();
-        assert!(result.id > 0);
-        assert_eq!(result.name, "create_vertex");
-        assert_eq!(result.type_, "create_vertex");
-        assert_eq!(result.created_by, "test");
+/// Returns up to `limit` live vertices with an id greater than `after_id`, in
+/// id order. Passing the last id of a page fetches the next one.
+pub async fn get_vertices_page(
+    conn: &mut AsyncPgConnection,
+    after_id: i32,
+    limit: i64,
+) -> Result<Vec<Vertex>, Error> {
+    use crate::schema::vertex::dsl::*;
+
+    if limit < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
     }
 
-    #[tokio::test]
-    async fn test_create_edge() {
-        dotenvy::from_path(".env").ok();
+    let result = vertex
+        .filter(id.gt(after_id))
+        .filter(deleted_at.is_null())
+        .order(id.asc())
+        .limit(limit)
+        .select(Vertex::as_select())
+        .load(conn)
+        .await?;
 
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    Ok(result)
+}
 
-        let source_vertex = NewVertex {
-            name: "create_edge_source_vertex".to_string(),
-            type_: "create_edge_source_vertex".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Pages through the live vertices carrying `vertex_label`, primary or not,
+/// the way `get_vertices_page` does.
+pub async fn get_vertices_with_label(
+    conn: &mut AsyncPgConnection,
+    vertex_label: &str,
+    after_id: i32,
+    limit: i64,
+) -> Result<Vec<Vertex>, Error> {
+    use crate::schema::vertex::dsl::*;
 
-        let target_vertex = NewVertex {
-            name: "create_edge_target_vertex".to_string(),
-            type_: "create_edge_target_vertex".to_string(),
-            created_by: "test".to_string(),
-        };
+    if limit < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
-            .await
-            .unwrap();
+    let result = vertex
+        .filter(labels.contains(vec![vertex_label]))
+        .filter(id.gt(after_id))
+        .filter(deleted_at.is_null())
+        .order(id.asc())
+        .limit(limit)
+        .select(Vertex::as_select())
+        .load(conn)
+        .await?;
 
-        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
-            .await
-            .unwrap();
+    Ok(result)
+}
 
-        let new_edge = crate::dto::NewEdge {
-            from_vertex_id: source_vertex.id,
-            to_vertex_id: target_vertex.id,
-            label: "create_edge".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Returns up to `limit` live edges with an id greater than `after_id`, in id
+/// order.
+pub async fn get_edges_page(
+    conn: &mut AsyncPgConnection,
+    after_id: i32,
+    limit: i64,
+) -> Result<Vec<Edge>, Error> {
+    use crate::schema::edge::dsl::*;
 
-        let result = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
-        assert!(result.id > 0);
-        assert_eq!(result.from_vertex_id, source_vertex.id);
-        assert_eq!(result.from_vertex_type, source_vertex.type_);
-        assert_eq!(result.to_vertex_id, target_vertex.id);
-        assert_eq!(result.to_vertex_type, target_vertex.type_);
-        assert_eq!(result.label, "create_edge");
-        assert_eq!(result.created_by, "test");
+    if limit < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
     }
 
-    #[tokio::test]
-    async fn test_get_vertex_by_id() {
-        dotenvy::from_path(".env").ok();
+    let result = edge
+        .filter(id.gt(after_id))
+        .filter(deleted_at.is_null())
+        .order(id.asc())
+        .limit(limit)
+        .select(Edge::as_select())
+        .load(conn)
+        .await?;
 
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    Ok(result)
+}
 
-        let new_vertex = NewVertex {
-            name: "get_vertex_by_id".to_string(),
-            type_: "get_vertex_by_id".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Returns the edges attached to `vertex_id` that match `query`.
+pub async fn get_incident_edges(
+    conn: &mut AsyncPgConnection,
+    vertex_id: i32,
+    query: &EdgeQuery,
+) -> Result<Vec<Edge>, Error> {
+    if vertex_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
-            .await
-            .unwrap();
+    load_incident_edges(conn, &[vertex_id], query).await
+}
 
-        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id)
-            .await
-            .unwrap();
-        assert_eq!(result.id, new_vertex.id);
-        assert_eq!(result.name, "get_vertex_by_id");
-        assert_eq!(result.type_, "get_vertex_by_id");
-        assert_eq!(result.created_by, "test");
+/// Batched [`get_incident_edges`]: the edges that match `query`, keyed by
+/// which of `vertex_ids` they are attached to. Vertices without such edges
+/// are left out.
+pub async fn get_incident_edges_by_vertex_ids(
+    conn: &mut AsyncPgConnection,
+    vertex_ids: &[i32],
+    query: &EdgeQuery,
+) -> Result<HashMap<i32, Vec<Edge>>, Error> {
+    if vertex_ids.iter().any(|vertex_id| *vertex_id < 1) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
     }
 
-    #[tokio::test]
-    async fn test_delete_vertex_by_id_without_relationship() {
-        dotenvy::from_path(".env").ok();
-
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let mut result = HashMap::<i32, Vec<Edge>>::new();
+    for incident in load_incident_edges(conn, vertex_ids, query).await? {
+        let (outgoing, incoming) = followed_ways(&incident, query.direction);
+        if outgoing && vertex_ids.contains(&incident.from_vertex_id) {
+            result
+                .entry(incident.from_vertex_id)
+                .or_default()
+                .push(incident.clone());
+        }
+        if incoming && vertex_ids.contains(&incident.to_vertex_id) {
+            result
+                .entry(incident.to_vertex_id)
+                .or_default()
+                .push(incident);
+        }
+    }
 
-        let new_vertex = NewVertex {
-            name: "delete_vertex_by_id".to_string(),
-            type_: "delete_vertex_by_id".to_string(),
-            created_by: "test".to_string(),
-        };
+    Ok(result)
+}
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
-            .await
-            .unwrap();
+/// Returns the vertices one hop away from `vertex_id` along the edges that
+/// match `query`.
+pub async fn get_neighbors(
+    conn: &mut AsyncPgConnection,
+    vertex_id: i32,
+    query: &EdgeQuery,
+) -> Result<Vec<Vertex>, Error> {
+    let neighbor_ids = get_incident_edges(conn, vertex_id, query)
+        .await?
+        .iter()
+        .flat_map(|edge| neighbor_ids_of(edge, &[vertex_id], query.direction))
+        .collect::<Vec<_>>();
 
-        let result = crate::api::delete_vertex_by_id(&mut conn, new_vertex.id)
-            .await
-            .unwrap();
-        assert_eq!(result, 1);
+    get_vertices_by_ids(conn, &neighbor_ids, query.as_of).await
+}
 
-        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id).await;
-        assert!(result.is_err());
+/// Counts the live edges of each label from one vertex to another, and adds
+/// up the numeric property named by `query.sum` over them. Undirected edges
+/// count for either orientation.
+pub async fn aggregate_parallel_edges(
+    conn: &mut AsyncPgConnection,
+    query: &ParallelEdgeQuery,
+) -> Result<Vec<ParallelEdges>, Error> {
+    if [query.from_vertex_id, query.to_vertex_id]
+        .into_iter()
+        .flatten()
+        .any(|vertex_id| vertex_id < 1)
+    {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
     }
 
-    #[tokio::test]
-    async fn test_delete_vertex_by_id_with_relationship() {
-        dotenvy::from_path(".env").ok();
+    let result = diesel::sql_query(
+        "SELECT from_vertex_id, to_vertex_id, label, COUNT(*) AS edge_count, \
+            SUM(CASE WHEN jsonb_typeof(properties -> $4) = 'number' \
+                THEN (properties ->> $4)::FLOAT8 END) AS sum \
+         FROM edge \
+         WHERE deleted_at IS NULL \
+            AND ($1::INT IS NULL OR from_vertex_id = $1 OR (undirected AND to_vertex_id = $1)) \
+            AND ($2::INT IS NULL OR to_vertex_id = $2 OR (undirected AND from_vertex_id = $2)) \
+            AND ($3::TEXT IS NULL OR label = $3) \
+         GROUP BY from_vertex_id, to_vertex_id, label \
+         ORDER BY from_vertex_id, to_vertex_id, label",
+    )
+    .bind::<Nullable<Int4>, _>(query.from_vertex_id)
+    .bind::<Nullable<Int4>, _>(query.to_vertex_id)
+    .bind::<Nullable<Text>, _>(&query.label)
+    .bind::<Nullable<Text>, _>(&query.sum)
+    .load::<ParallelEdges>(conn)
+    .await?;
 
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    Ok(result)
+}
 
-        let source_vertex = NewVertex {
-            name: "delete_vertex_by_id_with_relationship_source_vertex".to_string(),
-            type_: "delete_vertex_by_id_with_relationship_source_vertex".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Lists who asserted an edge, most confident first. A mirror edge shares
+/// the assertions of its edge.
+pub async fn get_edge_assertions(
+    conn: &mut AsyncPgConnection,
+    asserted_edge_id: i32,
+) -> Result<Vec<EdgeAssertion>, Error> {
+    use crate::schema::edge;
+    use crate::schema::edge_assertion;
 
-        let target_vertex = NewVertex {
-            name: "delete_vertex_by_id_with_relationship_target_vertex".to_string(),
-            type_: "delete_vertex_by_id_with_relationship_target_vertex".to_string(),
-            created_by: "test".to_string(),
-        };
+    let mirror_of = edge::table
+        .filter(edge::id.eq(asserted_edge_id))
+        .select(edge::mirror_of)
+        .first::<Option<i32>>(conn)
+        .await?;
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
-            .await
-            .unwrap();
+    let result = edge_assertion::table
+        .filter(edge_assertion::edge_id.eq(mirror_of.unwrap_or(asserted_edge_id)))
+        .order((
+            edge_assertion::confidence.desc(),
+            edge_assertion::source.asc(),
+        ))
+        .select(EdgeAssertion::as_select())
+        .load(conn)
+        .await?;
 
-        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
-            .await
-            .unwrap();
+    Ok(result)
+}
 
-        let new_edge = crate::dto::NewEdge {
-            from_vertex_id: source_vertex.id,
-            to_vertex_id: target_vertex.id,
-            label: "delete_vertex_by_id_with_relationship".to_string(),
-            created_by: "test".to_string(),
-        };
+/// Loads the live edges matching `query` by their provenance. Edges nobody
+/// asserted, e.g. those created directly, never match.
+pub async fn get_edges_by_provenance(
+    conn: &mut AsyncPgConnection,
+    query: &ProvenanceQuery,
+) -> Result<Vec<Edge>, Error> {
+    use crate::schema::edge;
+    use crate::schema::edge_assertion;
 
-        let _ = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+    if let Some(min_confidence) = query.min_confidence {
+        check_confidence(min_confidence)?;
+    }
 
-        let result = crate::api::delete_vertex_by_id(&mut conn, source_vertex.id).await;
-        assert!(result.is_ok());
+    let mut assertions = edge_assertion::table
+        .select(edge_assertion::edge_id)
+        .distinct()
+        .into_boxed();
+    if let Some(source) = &query.source {
+        assertions = assertions.filter(edge_assertion::source.eq(source));
+    }
+    if let Some(min_confidence) = query.min_confidence {
+        assertions = assertions.filter(edge_assertion::confidence.ge(min_confidence));
+    }
+    let asserted_ids = assertions.load::<i32>(conn).await?;
 
-        edge.filter(from_vertex_id.eq(source_vertex.id))
-            .select(edge::id)
-            .first::<i32>(&mut conn)
+    let mut edges = edge::table
+        .filter(edge::deleted_at.is_null())
+        .filter(
+            edge::id
+                .eq_any(&asserted_ids)
+                .or(edge::mirror_of.eq_any(&asserted_ids)),
+        )
+        .into_boxed();
+    if let Some(label) = &query.label {
+        edges = edges.filter(edge::label.eq(label));
+    }
+
+    let result = edges
+        .order(edge::id.asc())
+        .select(Edge::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Breadth-first traversal from `start_vertex_id` along the edges that match
+/// `query`, up to `max_depth` hops. The start vertex is not part of the
+/// result; the other vertices are returned in the order they were reached.
+pub async fn traverse(
+    conn: &mut AsyncPgConnection,
+    start_vertex_id: i32,
+    max_depth: usize,
+    query: &EdgeQuery,
+) -> Result<Vec<Vertex>, Error> {
+    if start_vertex_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let mut visited = HashSet::from([start_vertex_id]);
+    let mut reached = Vec::new();
+    let mut frontier = vec![start_vertex_id];
+
+    for _ in 0..max_depth {
+        if frontier.is_empty() {
+            break;
+        }
+
+        let next = traverse_hop(conn, &frontier, &mut visited, query).await?;
+        reached.extend_from_slice(&next);
+        frontier = next;
+    }
+
+    let mut vertices = get_vertices_by_ids(conn, &reached, query.as_of)
+        .await?
+        .into_iter()
+        .map(|vertex| (vertex.id, vertex))
+        .collect::<HashMap<_, _>>();
+
+    Ok(reached
+        .iter()
+        .filter_map(|vertex_id| vertices.remove(vertex_id))
+        .collect())
+}
+
+/// One hop of [`traverse`]: the ids of the vertices first reached from
+/// `frontier`, in the order they were reached. They are added to `visited`,
+/// which lets callers stream a traversal a hop at a time.
+pub async fn traverse_hop(
+    conn: &mut AsyncPgConnection,
+    frontier: &[i32],
+    visited: &mut HashSet<i32>,
+    query: &EdgeQuery,
+) -> Result<Vec<i32>, Error> {
+    let edges = load_incident_edges(conn, frontier, query).await?;
+    let mut next = Vec::new();
+    for edge in &edges {
+        for neighbor_id in neighbor_ids_of(edge, frontier, query.direction) {
+            if visited.insert(neighbor_id) {
+                next.push(neighbor_id);
+            }
+        }
+    }
+
+    Ok(next)
+}
+
+/// Updates the name and/or type of a live vertex. A type change is carried
+/// over to the denormalized endpoint types of its edges.
+///
+/// When `expected_version` is given the update fails with
+/// [`Error::Conflict`] unless the vertex is still at that version.
+pub async fn update_vertex(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    update: &UpdateVertex,
+    expected_version: Option<i32>,
+) -> Result<Vertex, Error> {
+    use crate::schema::{edge, vertex, vertex_alias};
+
+    if vertext_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            check_vertex_version(conn, vertext_id, expected_version).await?;
+
+            let result = diesel::update(
+                vertex::table
+                    .filter(vertex::id.eq(vertext_id))
+                    .filter(vertex::deleted_at.is_null()),
+            )
+            .set(update)
+            .returning(Vertex::as_returning())
+            .get_result(conn)
+            .await?;
+
+            if update.type_.is_some() {
+                diesel::update(vertex_alias::table.filter(vertex_alias::vertex_id.eq(vertext_id)))
+                    .set(vertex_alias::type_.eq(&result.type_))
+                    .execute(conn)
+                    .await?;
+                diesel::update(edge::table.filter(edge::from_vertex_id.eq(vertext_id)))
+                    .set((
+                        edge::from_vertex_type.eq(&result.type_),
+                        edge::updated_by.eq(&update.updated_by),
+                    ))
+                    .execute(conn)
+                    .await?;
+                diesel::update(edge::table.filter(edge::to_vertex_id.eq(vertext_id)))
+                    .set((
+                        edge::to_vertex_type.eq(&result.type_),
+                        edge::updated_by.eq(&update.updated_by),
+                    ))
+                    .execute(conn)
+                    .await?;
+            }
+
+            Ok(result)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Updates the label, weight or validity of a live edge, with the same version check as
+/// [`update_vertex`]. Mirror edges follow the edge they mirror and cannot be
+/// relabeled themselves, and self-loops and keyed edges only take labels
+/// allowing them.
+pub async fn update_edge(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+    update: &UpdateEdge,
+    expected_version: Option<i32>,
+) -> Result<Edge, Error> {
+    use crate::schema::edge;
+
+    if edge_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+    if let Some(weight) = update.weight {
+        check_weight(weight)?;
+    }
+
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            check_edge_version(conn, edge_id, expected_version).await?;
+
+            let (from_vertex_id, to_vertex_id, mirror_of, edge_key, valid_from, valid_to) =
+                edge::table
+                    .filter(edge::id.eq(edge_id))
+                    .select((
+                        edge::from_vertex_id,
+                        edge::to_vertex_id,
+                        edge::mirror_of,
+                        edge::edge_key,
+                        edge::valid_from,
+                        edge::valid_to,
+                    ))
+                    .first::<(
+                        i32,
+                        i32,
+                        Option<i32>,
+                        Option<String>,
+                        Option<NaiveDateTime>,
+                        Option<NaiveDateTime>,
+                    )>(conn)
+                    .await?;
+            if mirror_of.is_some() {
+                return Err(Error::Validation(validator::ValidationErrors::new()));
+            }
+            check_validity(Interval {
+                from: update.valid_from.unwrap_or(valid_from),
+                to: update.valid_to.unwrap_or(valid_to),
+            })?;
+            if let Some(label) = &update.label {
+                let usage = LabelUsage {
+                    label,
+                    self_loop: from_vertex_id == to_vertex_id,
+                    keyed: edge_key.is_some(),
+                };
+                check_label_settings(conn, &[usage]).await?;
+            }
+
+            let result = diesel::update(
+                edge::table
+                    .filter(edge::id.eq(edge_id))
+                    .filter(edge::deleted_at.is_null()),
+            )
+            .set(update)
+            .returning(Edge::as_returning())
+            .get_result(conn)
+            .await?;
+
+            Ok(result)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+pub async fn delete_vertex_by_id(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    expected_version: Option<i32>,
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    if vertext_id < 1 || !USERNAME_LIKE.is_match(deleted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted_by = deleted_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                check_vertex_version(conn, vertext_id, expected_version).await?;
+                let incident_labels =
+                    get_incident_labels(conn, vertext_id, DeletePolicy::default()).await?;
+                remove_vertex(conn, vertext_id, &incident_labels, &deleted_by).await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_edge_by_id(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+    expected_version: Option<i32>,
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge::dsl::{edge, id};
+
+    if edge_id < 1 || !USERNAME_LIKE.is_match(deleted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted_by = deleted_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                check_edge_version(conn, edge_id, expected_version).await?;
+                set_history_actor(conn, &deleted_by).await?;
+                let result = diesel::delete(edge.filter(id.eq(edge_id)))
+                    .execute(conn)
+                    .await?;
+                Ok(result)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+/// Creates the settings of an edge label, or replaces them if the label is
+/// already configured.
+///
+/// Making a label symmetric or giving it an inverse pairs its existing edges
+/// with mirror edges, and changing that declaration pairs them anew. A label
+/// belongs to at most one pair, so declarations contradicting the settings of
+/// another label are rejected. Neither can a label leave multigraph mode
+/// while it has live keyed edges, nor forbid self-loops while it has live
+/// ones. Derived self-loops follow a changed policy at the next
+/// [`crate::datalog::maintain`].
+pub async fn save_edge_label_setting(
+    conn: &mut AsyncPgConnection,
+    new_setting: &NewEdgeLabelSetting,
+) -> Result<EdgeLabelSetting, Error> {
+    use crate::schema::{datalog_dirty_label, datalog_rule, edge, edge_label_setting};
+
+    new_setting.validate()?;
+
+    let new_setting = InsertableNewEdgeLabelSetting {
+        label: new_setting.label.clone(),
+        delete_policy: new_setting.delete_policy,
+        inverse_label: new_setting.inverse_label.clone(),
+        symmetric: new_setting.symmetric,
+        allow_self_loops: new_setting.allow_self_loops,
+        multigraph: new_setting.multigraph,
+        created_by: new_setting.created_by.clone(),
+        updated_by: new_setting.created_by.clone(),
+    };
+
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            let pair = |label: &str, inverse_label: Option<&str>, symmetric: bool| {
+                let partner = if symmetric {
+                    Some(label)
+                } else {
+                    inverse_label
+                };
+                partner.map(|partner| HashSet::from([label.to_string(), partner.to_string()]))
+            };
+            let new_pair = pair(
+                &new_setting.label,
+                new_setting.inverse_label.as_deref(),
+                new_setting.symmetric,
+            );
+
+            // The labels whose edges may need other mirrors afterwards.
+            let mut changed_labels = HashSet::from([new_setting.label.clone()]);
+            changed_labels.extend(new_pair.iter().flatten().cloned());
+
+            let settings = edge_label_setting::table
+                .select(EdgeLabelSetting::as_select())
+                .load(conn)
+                .await?;
+            for setting in &settings {
+                let old_pair = pair(
+                    &setting.label,
+                    setting.inverse_label.as_deref(),
+                    setting.symmetric,
+                );
+                if setting.label == new_setting.label {
+                    changed_labels.extend(old_pair.into_iter().flatten());
+                } else if let (Some(new_pair), Some(old_pair)) = (&new_pair, old_pair) {
+                    if !new_pair.is_disjoint(&old_pair) && *new_pair != old_pair {
+                        return Err(Error::Validation(validator::ValidationErrors::new()));
+                    }
+                }
+            }
+
+            if !new_setting.multigraph {
+                let keyed = diesel::select(exists(
+                    edge::table
+                        .filter(edge::label.eq(&new_setting.label))
+                        .filter(edge::edge_key.is_not_null())
+                        .filter(edge::mirror_of.is_null())
+                        .filter(edge::deleted_at.is_null()),
+                ))
+                .get_result::<bool>(conn)
+                .await?;
+                if keyed {
+                    return Err(Error::Validation(validator::ValidationErrors::new()));
+                }
+            }
+
+            if !new_setting.allow_self_loops {
+                let looped = diesel::select(exists(
+                    edge::table
+                        .filter(edge::label.eq(&new_setting.label))
+                        .filter(edge::from_vertex_id.eq(edge::to_vertex_id))
+                        .filter(edge::derived.eq(false))
+                        .filter(edge::mirror_of.is_null())
+                        .filter(edge::deleted_at.is_null()),
+                ))
+                .get_result::<bool>(conn)
+                .await?;
+                if looped {
+                    return Err(Error::Validation(validator::ValidationErrors::new()));
+                }
+            }
+
+            let allowed_self_loops = settings
+                .iter()
+                .find(|setting| setting.label == new_setting.label)
+                .is_some_and(|setting| setting.allow_self_loops);
+            if allowed_self_loops != new_setting.allow_self_loops {
+                diesel::insert_into(datalog_dirty_label::table)
+                    .values(
+                        datalog_rule::table
+                            .filter(datalog_rule::head_label.eq(&new_setting.label))
+                            .select(datalog_rule::head_label)
+                            .distinct(),
+                    )
+                    .into_columns(datalog_dirty_label::label)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+            }
+
+            let result = diesel::insert_into(edge_label_setting::table)
+                .values(&new_setting)
+                .on_conflict(edge_label_setting::label)
+                .do_update()
+                .set((
+                    edge_label_setting::delete_policy.eq(new_setting.delete_policy),
+                    edge_label_setting::inverse_label.eq(&new_setting.inverse_label),
+                    edge_label_setting::symmetric.eq(new_setting.symmetric),
+                    edge_label_setting::allow_self_loops.eq(new_setting.allow_self_loops),
+                    edge_label_setting::multigraph.eq(new_setting.multigraph),
+                    edge_label_setting::updated_by.eq(&new_setting.updated_by),
+                ))
+                .returning(EdgeLabelSetting::as_returning())
+                .get_result(conn)
+                .await?;
+
+            diesel::sql_query("SELECT refresh_edge_mirrors($1)")
+                .bind::<Array<Text>, _>(changed_labels.into_iter().collect::<Vec<_>>())
+                .execute(conn)
+                .await?;
+
+            Ok(result)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+pub async fn get_edge_label_settings(
+    conn: &mut AsyncPgConnection,
+) -> Result<Vec<EdgeLabelSetting>, Error> {
+    use crate::schema::edge_label_setting::dsl::*;
+
+    let result = edge_label_setting
+        .order(label.asc())
+        .select(EdgeLabelSetting::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Deletes a vertex according to the delete policies of its incident edge
+/// labels. `default_policy` applies to labels without configured settings.
+/// The vertex is moved to the trash unless `permanent` is set.
+///
+/// The returned preview lists the incident edges per label and the affected
+/// neighbors. With `dry_run` nothing is changed; otherwise the delete fails
+/// with [`Error::DeleteRestricted`] while edges of a restricting label exist,
+/// or with [`Error::Conflict`] when `expected_version` is no longer current.
+pub async fn delete_vertex(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    default_policy: DeletePolicy,
+    dry_run: bool,
+    permanent: bool,
+    expected_version: Option<i32>,
+    deleted_by: &str,
+) -> Result<DeletePreview, Error> {
+    if vertext_id < 1 || !USERNAME_LIKE.is_match(deleted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted_by = deleted_by.to_string();
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            check_vertex_version(conn, vertext_id, expected_version).await?;
+            let target = get_vertex_by_id(conn, vertext_id, None).await?;
+            let incident_labels = get_incident_labels(conn, vertext_id, default_policy).await?;
+
+            let query = EdgeQuery {
+                direction: Direction::Both,
+                ..Default::default()
+            };
+            let neighbors = get_neighbors(conn, vertext_id, &query).await?;
+
+            let mut preview = DeletePreview {
+                vertex: target,
+                incident_labels,
+                neighbors,
+                deleted: false,
+            };
+            if dry_run {
+                return Ok(preview);
+            }
+
+            if permanent {
+                remove_vertex(conn, vertext_id, &preview.incident_labels, &deleted_by).await?;
+            } else {
+                trash_vertex(conn, vertext_id, &preview.incident_labels, &deleted_by).await?;
+            }
+
+            preview.deleted = true;
+            Ok(preview)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// The live incident edges of a vertex counted per label, with the delete
+/// policy of each label.
+async fn get_incident_labels(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    default_policy: DeletePolicy,
+) -> Result<Vec<IncidentLabel>, Error> {
+    use crate::schema::{edge, edge_label_setting};
+
+    let edge_counts = edge::table
+        .filter(
+            edge::from_vertex_id
+                .eq(vertext_id)
+                .or(edge::to_vertex_id.eq(vertext_id)),
+        )
+        .filter(edge::deleted_at.is_null())
+        .group_by(edge::label)
+        .select((edge::label, count_star()))
+        .order(edge::label.asc())
+        .load::<(String, i64)>(conn)
+        .await?;
+
+    let policies = edge_label_setting::table
+        .filter(edge_label_setting::label.eq_any(edge_counts.iter().map(|(l, _)| l)))
+        .select((edge_label_setting::label, edge_label_setting::delete_policy))
+        .load::<(String, DeletePolicy)>(conn)
+        .await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+
+    let incident_labels = edge_counts
+        .into_iter()
+        .map(|(label, edge_count)| IncidentLabel {
+            delete_policy: policies.get(&label).copied().unwrap_or(default_policy),
+            label,
+            edge_count,
+        })
+        .collect();
+
+    Ok(incident_labels)
+}
+
+/// Fails with [`Error::DeleteRestricted`] while edges of a restricting label
+/// exist. Every vertex delete goes through here.
+fn check_delete_policies(incident_labels: &[IncidentLabel]) -> Result<(), Error> {
+    let restricted = incident_labels
+        .iter()
+        .filter(|incident| incident.delete_policy == DeletePolicy::Restrict)
+        .map(|incident| incident.label.clone())
+        .collect::<Vec<_>>();
+    if !restricted.is_empty() {
+        return Err(Error::DeleteRestricted(restricted));
+    }
+
+    Ok(())
+}
+
+/// Removes a vertex for good; the database cascades to all of its edges.
+async fn remove_vertex(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    incident_labels: &[IncidentLabel],
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::vertex;
+
+    check_delete_policies(incident_labels)?;
+    set_history_actor(conn, deleted_by).await?;
+    let result = diesel::delete(vertex::table.filter(vertex::id.eq(vertext_id)))
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Moves a live vertex to the trash. Edges of detaching labels are removed
+/// for good, the other live incident edges are trashed along with it.
+async fn trash_vertex(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    incident_labels: &[IncidentLabel],
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge;
+    use crate::schema::vertex;
+
+    check_delete_policies(incident_labels)?;
+    let trashed_at = diesel::update(
+        vertex::table
+            .filter(vertex::id.eq(vertext_id))
+            .filter(vertex::deleted_at.is_null()),
+    )
+    .set((
+        vertex::deleted_at.eq(now.nullable()),
+        vertex::deleted_by.eq(deleted_by),
+        vertex::updated_by.eq(deleted_by),
+    ))
+    .returning(vertex::deleted_at)
+    .get_result::<Option<NaiveDateTime>>(conn)
+    .await
+    .optional()?;
+
+    let Some(trashed_at) = trashed_at else {
+        return Ok(0);
+    };
+
+    let incident_edges = edge::table
+        .filter(
+            edge::from_vertex_id
+                .eq(vertext_id)
+                .or(edge::to_vertex_id.eq(vertext_id)),
+        )
+        .filter(edge::deleted_at.is_null());
+
+    let detached = incident_labels
+        .iter()
+        .filter(|incident| incident.delete_policy == DeletePolicy::Detach)
+        .map(|incident| &incident.label)
+        .collect::<Vec<_>>();
+    if !detached.is_empty() {
+        set_history_actor(conn, deleted_by).await?;
+        diesel::delete(incident_edges.filter(edge::label.eq_any(detached)))
+            .execute(conn)
+            .await?;
+    }
+
+    // The cascaded edges share the vertex's `deleted_at`, which is how
+    // `restore_vertex_by_id` finds them again.
+    diesel::update(incident_edges)
+        .set((
+            edge::deleted_at.eq(trashed_at),
+            edge::deleted_by.eq(deleted_by),
+            edge::updated_by.eq(deleted_by),
+        ))
+        .execute(conn)
+        .await?;
+
+    Ok(1)
+}
+
+/// Moves a vertex to the trash together with its live incident edges. Trashed
+/// rows are hidden from every read until restored or purged. Labels without
+/// configured settings cascade.
+pub async fn soft_delete_vertex_by_id(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    expected_version: Option<i32>,
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    if vertext_id < 1 || !USERNAME_LIKE.is_match(deleted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted_by = deleted_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                check_vertex_version(conn, vertext_id, expected_version).await?;
+                let incident_labels =
+                    get_incident_labels(conn, vertext_id, DeletePolicy::default()).await?;
+                trash_vertex(conn, vertext_id, &incident_labels, &deleted_by).await
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn soft_delete_edge_by_id(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+    expected_version: Option<i32>,
+    deleted_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge;
+
+    if edge_id < 1 || !USERNAME_LIKE.is_match(deleted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted_by = deleted_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                check_edge_version(conn, edge_id, expected_version).await?;
+                let result = diesel::update(
+                    edge::table
+                        .filter(edge::id.eq(edge_id))
+                        .filter(edge::deleted_at.is_null()),
+                )
+                .set((
+                    edge::deleted_at.eq(now.nullable()),
+                    edge::deleted_by.eq(&deleted_by),
+                    edge::updated_by.eq(&deleted_by),
+                ))
+                .execute(conn)
+                .await?;
+                Ok(result)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+/// Withdraws everything `source` asserted. Edges no other source asserts
+/// anymore are moved to the trash along with their mirrors; edges that were
+/// never asserted are left alone.
+pub async fn retract_source(
+    conn: &mut AsyncPgConnection,
+    source: &str,
+    retracted_by: &str,
+) -> Result<Retraction, Error> {
+    use crate::schema::edge;
+    use crate::schema::edge_assertion;
+
+    if !SOURCE_LIKE.is_match(source) || !USERNAME_LIKE.is_match(retracted_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                set_history_actor(conn, retracted_by).await?;
+                let retracted_ids =
+                    diesel::delete(edge_assertion::table.filter(edge_assertion::source.eq(source)))
+                        .returning(edge_assertion::edge_id)
+                        .get_results::<i32>(conn)
+                        .await?;
+                let mut trashed_edge_ids = diesel::update(
+                    edge::table
+                        .filter(edge::id.eq_any(&retracted_ids))
+                        .filter(edge::deleted_at.is_null())
+                        .filter(not(exists(
+                            edge_assertion::table.filter(edge_assertion::edge_id.eq(edge::id)),
+                        ))),
+                )
+                .set((
+                    edge::deleted_at.eq(now.nullable()),
+                    edge::deleted_by.eq(retracted_by),
+                    edge::updated_by.eq(retracted_by),
+                ))
+                .returning(edge::id)
+                .get_results::<i32>(conn)
+                .await?;
+                trashed_edge_ids.sort_unstable();
+                Ok(Retraction {
+                    assertions: retracted_ids.len(),
+                    trashed_edge_ids,
+                })
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+/// Folds duplicate vertices of the survivor's type into the survivor, in one
+/// transaction. Their edges are re-pointed to the survivor. An edge that would
+/// then collide with another edge of the same endpoints, label and key is
+/// merged into that edge instead: its properties are combined following
+/// `merge.property_merge` and its assertions are moved over, keeping the more
+/// confident one per source. Edges between the merged vertices are moved to
+/// the trash rather than left as self-loops, and their ids are recorded; they
+/// follow the survivor like the other trashed edges, so one can only be
+/// restored as a self-loop of the survivor, where its label allows
+/// self-loops. The duplicates are then removed, their
+/// names and aliases becoming aliases of the survivor, and the merge is
+/// recorded. Derived edges are not moved; Datalog re-derives them.
+pub async fn merge_vertices(
+    conn: &mut AsyncPgConnection,
+    merge: &MergeVertices,
+) -> Result<VertexMerge, Error> {
+    use crate::schema::{edge, vertex, vertex_alias, vertex_merge};
+
+    let mut duplicate_ids = merge.duplicate_ids.clone();
+    duplicate_ids.sort_unstable();
+    duplicate_ids.dedup();
+    if merge.survivor_id < 1
+        || duplicate_ids.is_empty()
+        || duplicate_ids
+            .iter()
+            .any(|&duplicate_id| duplicate_id < 1 || duplicate_id == merge.survivor_id)
+        || !USERNAME_LIKE.is_match(&merge.merged_by)
+    {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            let mut merged_ids = duplicate_ids.clone();
+            merged_ids.push(merge.survivor_id);
+            let vertices = vertex::table
+                .filter(vertex::id.eq_any(&merged_ids))
+                .filter(vertex::deleted_at.is_null())
+                .order(vertex::id.asc())
+                .select(Vertex::as_select())
+                .for_update()
+                .load::<Vertex>(conn)
+                .await?;
+            if vertices.len() != merged_ids.len() {
+                return Err(Error::Database(diesel::result::Error::NotFound));
+            }
+            let (survivors, duplicates): (Vec<_>, Vec<_>) = vertices
+                .into_iter()
+                .partition(|merged| merged.id == merge.survivor_id);
+            let survivor = &survivors[0];
+            if duplicates
+                .iter()
+                .any(|duplicate| duplicate.type_ != survivor.type_)
+            {
+                return Err(Error::Validation(validator::ValidationErrors::new()));
+            }
+
+            set_history_actor(conn, &merge.merged_by).await?;
+
+            let dropped = diesel::update(
+                edge::table
+                    .filter(edge::from_vertex_id.eq_any(&merged_ids))
+                    .filter(edge::to_vertex_id.eq_any(&merged_ids))
+                    .filter(edge::from_vertex_id.ne(edge::to_vertex_id))
+                    .filter(edge::derived.eq(false))
+                    .filter(edge::deleted_at.is_null()),
+            )
+            .set((
+                edge::deleted_at.eq(now.nullable()),
+                edge::deleted_by.eq(&merge.merged_by),
+                edge::updated_by.eq(&merge.merged_by),
+            ))
+            .returning((edge::id, edge::mirror_of))
+            .get_results::<(i32, Option<i32>)>(conn)
+            .await?;
+            let dropped_ids = dropped.iter().map(|(id, _)| *id).collect::<HashSet<_>>();
+            let dropped_edge_ids = dropped
+                .iter()
+                .filter(|(_, mirror)| mirror.is_none())
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+
+            let incident_edges = edge::table
+                .filter(
+                    edge::from_vertex_id
+                        .eq_any(&merged_ids)
+                        .or(edge::to_vertex_id.eq_any(&merged_ids)),
+                )
+                .filter(edge::derived.eq(false))
+                .filter(edge::deleted_at.is_null())
+                .order(edge::id.asc())
+                .select(Edge::as_select())
+                .load::<Edge>(conn)
+                .await?;
+            let survivor_id = merge.survivor_id;
+            let merged_to = |merged_id: i32| {
+                if duplicate_ids.binary_search(&merged_id).is_ok() {
+                    survivor_id
+                } else {
+                    merged_id
+                }
+            };
+
+            // Edges of the survivor stay as they are; edges of the duplicates
+            // are taken over one by one, together with their mirrors.
+            let (moving, staying): (Vec<_>, Vec<_>) =
+                incident_edges.into_iter().partition(|incident| {
+                    duplicate_ids.contains(&incident.from_vertex_id)
+                        || duplicate_ids.contains(&incident.to_vertex_id)
+                });
+            let mut owners = HashMap::new();
+            let mut properties = HashMap::new();
+            for incident in staying {
+                let owner_id = incident.mirror_of.unwrap_or(incident.id);
+                owners.insert(EdgeMergeKey::of(&incident, merged_to), owner_id);
+                properties.entry(owner_id).or_insert(incident.properties);
+            }
+            let mut moving_pairs = BTreeMap::<i32, Vec<Edge>>::new();
+            for incident in moving {
+                moving_pairs
+                    .entry(incident.mirror_of.unwrap_or(incident.id))
+                    .or_default()
+                    .push(incident);
+            }
+
+            let mut merged_edges = 0;
+            let mut merged_into = HashSet::new();
+            for (moving_id, pair) in moving_pairs {
+                let keys = pair
+                    .iter()
+                    .map(|incident| EdgeMergeKey::of(incident, merged_to))
+                    .collect::<Vec<_>>();
+                let moving_properties = pair[0].properties.clone();
+                let Some(owner_id) = keys.iter().find_map(|key| owners.get(key).copied()) else {
+                    owners.extend(keys.into_iter().map(|key| (key, moving_id)));
+                    properties.insert(moving_id, moving_properties);
+                    continue;
+                };
+
+                if let Some(owner_properties) = properties.get_mut(&owner_id) {
+                    merge_properties(owner_properties, &moving_properties, merge.property_merge);
+                }
+                diesel::sql_query(
+                    "INSERT INTO edge_assertion \
+                        (edge_id, source, confidence, evidence, created_by, updated_by) \
+                     SELECT $2, source, confidence, evidence, created_by, $3 \
+                     FROM edge_assertion \
+                     WHERE edge_id = $1 \
+                     ON CONFLICT (edge_id, source) DO UPDATE \
+                     SET confidence = excluded.confidence, evidence = excluded.evidence, \
+                        updated_by = excluded.updated_by \
+                     WHERE excluded.confidence > edge_assertion.confidence",
+                )
+                .bind::<Int4, _>(moving_id)
+                .bind::<Int4, _>(owner_id)
+                .bind::<Text, _>(&merge.merged_by)
+                .execute(conn)
+                .await?;
+                diesel::delete(edge::table.filter(edge::id.eq(moving_id)))
+                    .execute(conn)
+                    .await?;
+                merged_into.insert(owner_id);
+                merged_edges += 1;
+            }
+            if merge.property_merge != PropertyMerge::KeepSurvivor {
+                for owner_id in merged_into {
+                    diesel::update(
+                        edge::table.filter(edge::id.eq(owner_id).or(edge::mirror_of.eq(owner_id))),
+                    )
+                    .set((
+                        edge::properties.eq(&properties[&owner_id]),
+                        edge::updated_by.eq(&merge.merged_by),
+                    ))
+                    .execute(conn)
+                    .await?;
+                }
+            }
+
+            // Trashed edges move along too, so that they are not purged with
+            // the duplicates.
+            let repointed_from = diesel::update(
+                edge::table
+                    .filter(edge::from_vertex_id.eq_any(&duplicate_ids))
+                    .filter(edge::derived.eq(false)),
+            )
+            .set((
+                edge::from_vertex_id.eq(survivor_id),
+                edge::from_vertex_type.eq(&survivor.type_),
+                edge::updated_by.eq(&merge.merged_by),
+            ))
+            .returning((edge::id, edge::mirror_of))
+            .get_results::<(i32, Option<i32>)>(conn)
+            .await?;
+            let repointed_to = diesel::update(
+                edge::table
+                    .filter(edge::to_vertex_id.eq_any(&duplicate_ids))
+                    .filter(edge::derived.eq(false)),
+            )
+            .set((
+                edge::to_vertex_id.eq(survivor_id),
+                edge::to_vertex_type.eq(&survivor.type_),
+                edge::updated_by.eq(&merge.merged_by),
+            ))
+            .returning((edge::id, edge::mirror_of))
+            .get_results::<(i32, Option<i32>)>(conn)
+            .await?;
+            // The dropped edges move along as well, but were not repointed
+            // as edges of the survivor.
+            let repointed_edges = repointed_from
+                .iter()
+                .chain(&repointed_to)
+                .filter(|(id, mirror)| mirror.is_none() && !dropped_ids.contains(id))
+                .count();
+
+            let mut labels = survivor.labels.clone();
+            labels.extend(
+                duplicates
+                    .iter()
+                    .flat_map(|duplicate| duplicate.labels.iter().cloned()),
+            );
+            diesel::update(vertex::table.filter(vertex::id.eq(survivor_id)))
+                .set((
+                    vertex::labels.eq(labels),
+                    vertex::updated_by.eq(&merge.merged_by),
+                ))
+                .execute(conn)
+                .await?;
+
+            let mut aliases = vertex_alias::table
+                .filter(vertex_alias::vertex_id.eq_any(&duplicate_ids))
+                .select(vertex_alias::alias)
+                .load::<String>(conn)
+                .await?;
+            aliases.extend(duplicates.iter().map(|duplicate| duplicate.name.clone()));
+            aliases.retain(|alias| *alias != survivor.name);
+
+            diesel::delete(vertex::table.filter(vertex::id.eq_any(&duplicate_ids)))
+                .execute(conn)
+                .await?;
+
+            let new_aliases = aliases
+                .into_iter()
+                .map(|alias| InsertableNewVertexAlias {
+                    vertex_id: survivor_id,
+                    alias,
+                    type_: survivor.type_.clone(),
+                    created_by: merge.merged_by.clone(),
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(vertex_alias::table)
+                .values(&new_aliases)
+                .on_conflict_do_nothing()
+                .execute(conn)
+                .await?;
+
+            let record = InsertableVertexMerge {
+                survivor_id,
+                duplicates: serde_json::to_value(&duplicates)
+                    .map_err(|e| Error::Other(e.into()))?,
+                property_merge: merge.property_merge,
+                repointed_edges: repointed_edges as i32,
+                merged_edges,
+                dropped_edges: dropped_edge_ids.len() as i32,
+                dropped_edge_ids,
+                merged_by: merge.merged_by.clone(),
+            };
+            let result = diesel::insert_into(vertex_merge::table)
+                .values(&record)
+                .returning(VertexMerge::as_returning())
+                .get_result(conn)
+                .await?;
+
+            Ok(result)
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Lists the merges a vertex took part in, as survivor or as duplicate,
+/// oldest first.
+pub async fn get_vertex_merges(
+    conn: &mut AsyncPgConnection,
+    merged_vertex_id: i32,
+) -> Result<Vec<VertexMerge>, Error> {
+    use crate::schema::vertex_merge::dsl::*;
+
+    let result = vertex_merge
+        .filter(
+            survivor_id
+                .eq(merged_vertex_id)
+                .or(duplicates.contains(serde_json::json!([{ "id": merged_vertex_id }]))),
+        )
+        .order(id.asc())
+        .select(VertexMerge::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Brings a trashed vertex back together with the edges that were trashed
+/// along with it. Edges whose other endpoint is still in the trash stay there.
+pub async fn restore_vertex_by_id(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    restored_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge;
+    use crate::schema::vertex;
+
+    if vertext_id < 1 || !USERNAME_LIKE.is_match(restored_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let restored_by = restored_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                let trashed_at = vertex::table
+                    .filter(vertex::id.eq(vertext_id))
+                    .select(vertex::deleted_at)
+                    .first::<Option<NaiveDateTime>>(conn)
+                    .await?;
+
+                let Some(trashed_at) = trashed_at else {
+                    return Ok(0);
+                };
+
+                diesel::update(vertex::table.filter(vertex::id.eq(vertext_id)))
+                    .set((
+                        vertex::deleted_at.eq(None::<NaiveDateTime>),
+                        vertex::deleted_by.eq(None::<String>),
+                        vertex::updated_by.eq(&restored_by),
+                    ))
+                    .execute(conn)
+                    .await?;
+
+                let trashed_vertices = vertex::table
+                    .filter(vertex::deleted_at.is_not_null())
+                    .select(vertex::id);
+
+                diesel::update(
+                    edge::table
+                        .filter(
+                            edge::from_vertex_id
+                                .eq(vertext_id)
+                                .or(edge::to_vertex_id.eq(vertext_id)),
+                        )
+                        .filter(edge::deleted_at.eq(trashed_at))
+                        .filter(edge::from_vertex_id.ne_all(trashed_vertices))
+                        .filter(edge::to_vertex_id.ne_all(trashed_vertices)),
+                )
+                .set((
+                    edge::deleted_at.eq(None::<NaiveDateTime>),
+                    edge::deleted_by.eq(None::<String>),
+                    edge::updated_by.eq(&restored_by),
+                ))
+                .execute(conn)
+                .await?;
+
+                Ok(1)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+/// Brings a trashed edge back, provided both of its endpoints are live. Fails
+/// when the settings of its label no longer allow it as a self-loop or a keyed
+/// edge, as for an edge dropped by a vertex merge.
+pub async fn restore_edge_by_id(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+    restored_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge;
+    use crate::schema::vertex;
+
+    if edge_id < 1 || !USERNAME_LIKE.is_match(restored_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let restored_by = restored_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                let trashed = edge::table
+                    .filter(edge::id.eq(edge_id))
+                    .filter(edge::deleted_at.is_not_null())
+                    .select((
+                        edge::label,
+                        edge::from_vertex_id,
+                        edge::to_vertex_id,
+                        edge::edge_key,
+                    ))
+                    .for_update()
+                    .first::<(String, i32, i32, Option<String>)>(conn)
+                    .await
+                    .optional()?;
+
+                let Some((label, from_vertex_id, to_vertex_id, edge_key)) = trashed else {
+                    return Ok(0);
+                };
+                let usage = LabelUsage {
+                    label: &label,
+                    self_loop: from_vertex_id == to_vertex_id,
+                    keyed: edge_key.is_some(),
+                };
+                check_label_settings(conn, &[usage]).await?;
+
+                let trashed_vertices = vertex::table
+                    .filter(vertex::deleted_at.is_not_null())
+                    .select(vertex::id);
+
+                let result = diesel::update(
+                    edge::table
+                        .filter(edge::id.eq(edge_id))
+                        .filter(edge::from_vertex_id.ne_all(trashed_vertices))
+                        .filter(edge::to_vertex_id.ne_all(trashed_vertices)),
+                )
+                .set((
+                    edge::deleted_at.eq(None::<NaiveDateTime>),
+                    edge::deleted_by.eq(None::<String>),
+                    edge::updated_by.eq(&restored_by),
+                ))
+                .execute(conn)
+                .await?;
+                Ok(result)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_deleted_vertices(conn: &mut AsyncPgConnection) -> Result<Vec<Vertex>, Error> {
+    use crate::schema::vertex::dsl::*;
+
+    let result = vertex
+        .filter(deleted_at.is_not_null())
+        .order((deleted_at.desc(), id.asc()))
+        .select(Vertex::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_deleted_edges(conn: &mut AsyncPgConnection) -> Result<Vec<Edge>, Error> {
+    use crate::schema::edge::dsl::*;
+
+    let result = edge
+        .filter(deleted_at.is_not_null())
+        .order((deleted_at.desc(), id.asc()))
+        .select(Edge::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Permanently removes the vertices and edges that were moved to the trash
+/// before `deleted_before`. Meant to be run periodically; returns the number
+/// of vertices and edges removed.
+pub async fn purge_deleted(
+    conn: &mut AsyncPgConnection,
+    deleted_before: NaiveDateTime,
+    purged_by: &str,
+) -> Result<usize, Error> {
+    use crate::schema::edge;
+    use crate::schema::vertex;
+
+    if !USERNAME_LIKE.is_match(purged_by) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let purged_by = purged_by.to_string();
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                set_history_actor(conn, &purged_by).await?;
+                let edges = diesel::delete(edge::table.filter(edge::deleted_at.lt(deleted_before)))
+                    .execute(conn)
+                    .await?;
+                let vertices =
+                    diesel::delete(vertex::table.filter(vertex::deleted_at.lt(deleted_before)))
+                        .execute(conn)
+                        .await?;
+                Ok(edges + vertices)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_vertex_history(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+) -> Result<Vec<VertexHistory>, Error> {
+    use crate::schema::vertex_history::dsl::*;
+
+    if vertext_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let result = vertex_history
+        .filter(vertex_id.eq(vertext_id))
+        .order(id.asc())
+        .select(VertexHistory::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_edge_history(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+) -> Result<Vec<EdgeHistory>, Error> {
+    use crate::schema::edge_history::dsl::{self, edge_history};
+
+    if edge_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let result = edge_history
+        .filter(dsl::edge_id.eq(edge_id))
+        .order(dsl::id.asc())
+        .select(EdgeHistory::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+/// Records `actor` as the author of the changes made by the rest of the
+/// current transaction. Only needed for deletes, where the row itself carries
+/// no information about who removed it.
+pub(crate) async fn set_history_actor(
+    conn: &mut AsyncPgConnection,
+    actor: &str,
+) -> Result<(), Error> {
+    diesel::sql_query("SELECT set_config($1, $2, true)")
+        .bind::<Text, _>(HISTORY_ACTOR_SETTING)
+        .bind::<Text, _>(actor)
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Locks a live vertex for the rest of the transaction and makes sure it is
+/// still at `expected_version`. Does nothing without an expected version.
+async fn check_vertex_version(
+    conn: &mut AsyncPgConnection,
+    vertext_id: i32,
+    expected_version: Option<i32>,
+) -> Result<(), Error> {
+    use crate::schema::vertex::dsl::*;
+
+    let Some(expected) = expected_version else {
+        return Ok(());
+    };
+
+    let actual = vertex
+        .filter(id.eq(vertext_id))
+        .filter(deleted_at.is_null())
+        .select(version)
+        .for_update()
+        .first::<i32>(conn)
+        .await?;
+
+    if actual != expected {
+        return Err(Error::Conflict { expected, actual });
+    }
+
+    Ok(())
+}
+
+/// Weights are checked by the database as well, but a non-finite one is a
+/// client error.
+fn check_weight(weight: f64) -> Result<(), Error> {
+    if weight.is_finite() {
+        Ok(())
+    } else {
+        Err(Error::Validation(validator::ValidationErrors::new()))
+    }
+}
+
+fn check_confidence(confidence: f64) -> Result<(), Error> {
+    if (0.0..=1.0).contains(&confidence) {
+        Ok(())
+    } else {
+        Err(Error::Validation(validator::ValidationErrors::new()))
+    }
+}
+
+fn check_validity(validity: Interval) -> Result<(), Error> {
+    if validity.is_empty() {
+        Err(Error::Validation(validator::ValidationErrors::new()))
+    } else {
+        Ok(())
+    }
+}
+
+fn new_edge_validity(new_edge: &NewEdge) -> Interval {
+    Interval {
+        from: new_edge.valid_from,
+        to: new_edge.valid_to,
+    }
+}
+
+/// Finds the live edge `new_edge` would duplicate: same endpoints, label and
+/// key. Without a key, an edge of a multigraph label is keyed by its
+/// properties, so the properties have to match instead.
+async fn find_asserted_edge(
+    conn: &mut AsyncPgConnection,
+    new_edge: &NewEdge,
+) -> Result<Option<Edge>, Error> {
+    use crate::schema::edge::dsl::*;
+
+    let (source_vertex_id, target_vertex_id) = if new_edge.undirected {
+        (
+            new_edge.from_vertex_id.min(new_edge.to_vertex_id),
+            new_edge.from_vertex_id.max(new_edge.to_vertex_id),
+        )
+    } else {
+        (new_edge.from_vertex_id, new_edge.to_vertex_id)
+    };
+
+    let candidates = edge
+        .filter(from_vertex_id.eq(source_vertex_id))
+        .filter(to_vertex_id.eq(target_vertex_id))
+        .filter(label.eq(&new_edge.label))
+        .filter(undirected.eq(new_edge.undirected))
+        .filter(derived.eq(false))
+        .filter(deleted_at.is_null())
+        .select(Edge::as_select())
+        .load::<Edge>(conn)
+        .await?;
+
+    let new_properties = serde_json::Value::from(new_edge.properties.clone());
+    let result = candidates
+        .into_iter()
+        .find(|candidate| match &new_edge.edge_key {
+            Some(key) => candidate.edge_key.as_ref() == Some(key),
+            None => candidate.edge_key.is_none() || candidate.properties == new_properties,
+        });
+
+    Ok(result)
+}
+
+/// What tells a live edge apart from the others once its endpoints have been
+/// merged, as far as the unique indexes on edges go.
+#[derive(PartialEq, Eq, Hash)]
+struct EdgeMergeKey {
+    from_vertex_id: i32,
+    to_vertex_id: i32,
+    label: String,
+    edge_key: Option<String>,
+    undirected: bool,
+}
+
+impl EdgeMergeKey {
+    fn of(merged_edge: &Edge, merged_to: impl Fn(i32) -> i32) -> Self {
+        let from_vertex_id = merged_to(merged_edge.from_vertex_id);
+        let to_vertex_id = merged_to(merged_edge.to_vertex_id);
+        let (from_vertex_id, to_vertex_id) = if merged_edge.undirected {
+            (
+                from_vertex_id.min(to_vertex_id),
+                from_vertex_id.max(to_vertex_id),
+            )
+        } else {
+            (from_vertex_id, to_vertex_id)
+        };
+
+        // Keyed edges are unique whether directed or not.
+        EdgeMergeKey {
+            from_vertex_id,
+            to_vertex_id,
+            label: merged_edge.label.clone(),
+            edge_key: merged_edge.edge_key.clone(),
+            undirected: merged_edge.undirected && merged_edge.edge_key.is_none(),
+        }
+    }
+}
+
+/// Combines the properties of an edge merged away into those of the edge kept.
+fn merge_properties(
+    kept: &mut serde_json::Value,
+    merged: &serde_json::Value,
+    property_merge: PropertyMerge,
+) {
+    let (serde_json::Value::Object(kept), serde_json::Value::Object(merged)) = (kept, merged)
+    else {
+        return;
+    };
+
+    for (key, value) in merged {
+        match property_merge {
+            PropertyMerge::KeepSurvivor => return,
+            PropertyMerge::PreferSurvivor => {
+                kept.entry(key).or_insert_with(|| value.clone());
+            }
+            PropertyMerge::PreferDuplicate => {
+                kept.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Keeps the edges holding in the domain at `instant`.
+pub(crate) fn valid_at(instant: NaiveDateTime) -> EdgeCondition {
+    use crate::schema::edge::dsl::*;
+
+    Box::new(
+        valid_from
+            .le(instant)
+            .or(valid_from.is_null())
+            .and(valid_to.gt(instant).or(valid_to.is_null())),
+    )
+}
+
+/// How an edge uses its label, as far as label settings are concerned.
+struct LabelUsage<'a> {
+    label: &'a str,
+    self_loop: bool,
+    keyed: bool,
+}
+
+fn new_edge_usage(new_edge: &NewEdge) -> LabelUsage<'_> {
+    LabelUsage {
+        label: &new_edge.label,
+        self_loop: new_edge.from_vertex_id == new_edge.to_vertex_id,
+        keyed: new_edge.edge_key.is_some(),
+    }
+}
+
+/// Fails unless the settings of their labels allow the self-loops and the
+/// edge keys among `usages`.
+async fn check_label_settings(
+    conn: &mut AsyncPgConnection,
+    usages: &[LabelUsage<'_>],
+) -> Result<(), Error> {
+    use crate::schema::edge_label_setting;
+
+    let labels = usages
+        .iter()
+        .filter(|usage| usage.self_loop || usage.keyed)
+        .map(|usage| usage.label)
+        .collect::<Vec<_>>();
+    if labels.is_empty() {
+        return Ok(());
+    }
+
+    let settings = edge_label_setting::table
+        .filter(edge_label_setting::label.eq_any(&labels))
+        .select((
+            edge_label_setting::label,
+            edge_label_setting::allow_self_loops,
+            edge_label_setting::multigraph,
+        ))
+        .load::<(String, bool, bool)>(conn)
+        .await?
+        .into_iter()
+        .map(|(label, allow_self_loops, multigraph)| (label, (allow_self_loops, multigraph)))
+        .collect::<HashMap<_, _>>();
+
+    let allowed = usages.iter().all(|usage| {
+        let (allow_self_loops, multigraph) = settings.get(usage.label).copied().unwrap_or_default();
+        (!usage.self_loop || allow_self_loops) && (!usage.keyed || multigraph)
+    });
+    if allowed {
+        Ok(())
+    } else {
+        Err(Error::Validation(validator::ValidationErrors::new()))
+    }
+}
+
+async fn check_edge_version(
+    conn: &mut AsyncPgConnection,
+    edge_id: i32,
+    expected_version: Option<i32>,
+) -> Result<(), Error> {
+    use crate::schema::edge::dsl::{self, edge};
+
+    let Some(expected) = expected_version else {
+        return Ok(());
+    };
+
+    let actual = edge
+        .filter(dsl::id.eq(edge_id))
+        .filter(dsl::deleted_at.is_null())
+        .select(dsl::version)
+        .for_update()
+        .first::<i32>(conn)
+        .await?;
+
+    if actual != expected {
+        return Err(Error::Conflict { expected, actual });
+    }
+
+    Ok(())
+}
+
+#[derive(QueryableByName)]
+struct HistoryImage {
+    #[diesel(sql_type = Jsonb)]
+    row: serde_json::Value,
+}
+
+async fn get_vertices_as_of(
+    conn: &mut AsyncPgConnection,
+    vertex_ids: &[i32],
+    as_of: NaiveDateTime,
+) -> Result<Vec<Vertex>, Error> {
+    let images = diesel::sql_query(
+        "SELECT new_row AS row FROM ( \
+            SELECT DISTINCT ON (vertex_id) operation, new_row FROM vertex_history \
+            WHERE vertex_id = ANY($1) AND changed_at <= $2 \
+            ORDER BY vertex_id, id DESC \
+         ) AS snapshot \
+         WHERE operation <> 'DELETE' AND new_row ->> 'deleted_at' IS NULL \
+         ORDER BY (new_row ->> 'id')::INT",
+    )
+    .bind::<Array<Int4>, _>(vertex_ids)
+    .bind::<Timestamp, _>(as_of)
+    .load::<HistoryImage>(conn)
+    .await?;
+
+    from_history_images(images)
+}
+
+async fn get_edges_as_of(
+    conn: &mut AsyncPgConnection,
+    vertex_ids: &[i32],
+    as_of: NaiveDateTime,
+) -> Result<Vec<Edge>, Error> {
+    // Endpoints are matched against every image of an edge first, so that the
+    // latest image decides even when it no longer touches `vertex_ids`.
+    let images = diesel::sql_query(
+        "SELECT new_row AS row FROM ( \
+            SELECT DISTINCT ON (edge_id) operation, new_row FROM edge_history \
+            WHERE changed_at <= $2 AND edge_id IN ( \
+                SELECT edge_id FROM edge_history \
+                WHERE (COALESCE(new_row, old_row) ->> 'from_vertex_id')::INT = ANY($1) \
+                   OR (COALESCE(new_row, old_row) ->> 'to_vertex_id')::INT = ANY($1) \
+            ) \
+            ORDER BY edge_id, id DESC \
+         ) AS snapshot \
+         WHERE operation <> 'DELETE' AND new_row ->> 'deleted_at' IS NULL \
+         ORDER BY (new_row ->> 'id')::INT",
+    )
+    .bind::<Array<Int4>, _>(vertex_ids)
+    .bind::<Timestamp, _>(as_of)
+    .load::<HistoryImage>(conn)
+    .await?;
+
+    from_history_images(images)
+}
+
+fn from_history_images<T: DeserializeOwned>(images: Vec<HistoryImage>) -> Result<Vec<T>, Error> {
+    images
+        .into_iter()
+        .map(|image| serde_json::from_value(image.row).map_err(|e| Error::Other(e.into())))
+        .collect()
+}
+
+async fn load_incident_edges(
+    conn: &mut AsyncPgConnection,
+    vertex_ids: &[i32],
+    query: &EdgeQuery,
+) -> Result<Vec<Edge>, Error> {
+    use crate::schema::edge::dsl::*;
+
+    let mut result = if let Some(as_of) = query.as_of {
+        get_edges_as_of(conn, vertex_ids, as_of).await?
+    } else {
+        let mut statement = edge
+            .filter(deleted_at.is_null())
+            .select(Edge::as_select())
+            .into_boxed();
+        statement = match query.direction {
+            Direction::Outgoing => statement.filter(
+                from_vertex_id
+                    .eq_any(vertex_ids)
+                    .or(undirected.and(to_vertex_id.eq_any(vertex_ids))),
+            ),
+            Direction::Incoming => statement.filter(
+                to_vertex_id
+                    .eq_any(vertex_ids)
+                    .or(undirected.and(from_vertex_id.eq_any(vertex_ids))),
+            ),
+            Direction::Both => statement.filter(
+                from_vertex_id
+                    .eq_any(vertex_ids)
+                    .or(to_vertex_id.eq_any(vertex_ids)),
+            ),
+        };
+        if let Some(edge_label) = &query.label {
+            statement = statement.filter(label.eq(edge_label));
+        }
+        if let Some(min_weight) = query.min_weight {
+            statement = statement.filter(weight.ge(min_weight));
+        }
+        if let Some(instant) = query.valid_at {
+            statement = statement.filter(valid_at(instant));
+        }
+        if let Some(Interval { from, to }) = query.valid_during {
+            if let Some(to) = to {
+                statement = statement.filter(valid_from.lt(to).or(valid_from.is_null()));
+            }
+            if let Some(from) = from {
+                statement = statement.filter(valid_to.gt(from).or(valid_to.is_null()));
+            }
+        }
+        statement.order(id.asc()).load(conn).await?
+    };
+
+    result.retain(|incident| {
+        query.label.as_ref().is_none_or(|l| &incident.label == l)
+            && query.min_weight.is_none_or(|min| incident.weight >= min)
+            && query
+                .valid_at
+                .is_none_or(|instant| incident.validity().contains(instant))
+            && query
+                .valid_during
+                .is_none_or(|interval| incident.validity().overlaps(&interval))
+            && !neighbor_ids_of(incident, vertex_ids, query.direction).is_empty()
+    });
+
+    Ok(result)
+}
+
+/// The endpoints of `edge` reached when stepping off one of `vertex_ids` in
+/// `direction`.
+fn neighbor_ids_of(edge: &Edge, vertex_ids: &[i32], direction: Direction) -> Vec<i32> {
+    let (outgoing, incoming) = followed_ways(edge, direction);
+    let mut result = Vec::new();
+    if outgoing && vertex_ids.contains(&edge.from_vertex_id) {
+        result.push(edge.to_vertex_id);
+    }
+    if incoming && vertex_ids.contains(&edge.to_vertex_id) {
+        result.push(edge.from_vertex_id);
+    }
+    result
+}
+
+/// Whether `edge` is followed from its source and from its target when
+/// stepping in `direction`. Undirected edges are followed both ways.
+fn followed_ways(edge: &Edge, direction: Direction) -> (bool, bool) {
+    (
+        edge.undirected || direction != Direction::Incoming,
+        edge.undirected || direction != Direction::Outgoing,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::constant::MAX_TYPE_LENGTH;
+    use crate::dto::{Direction, EdgeQuery, NewVertex};
+    use crate::model::{DeletePolicy, Interval};
+    use crate::schema::edge;
+    use crate::schema::edge::dsl::*;
+    use diesel::{ExpressionMethods, QueryDsl};
+    use diesel_async::scoped_futures::ScopedFutureExt;
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+    use validator::Validate;
+
+    #[tokio::test]
+    async fn test_create_vertex() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertex = NewVertex {
+            name: "create_vertex".to_string(),
+            type_: "create_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let result = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+        assert!(result.id > 0);
+        assert_eq!(result.name, "create_vertex");
+        assert_eq!(result.type_, "create_vertex");
+        assert_eq!(result.created_by, "test");
+    }
+
+    #[tokio::test]
+    async fn test_create_edge() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "create_edge_source_vertex".to_string(),
+            type_: "create_edge_source_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "create_edge_target_vertex".to_string(),
+            type_: "create_edge_target_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: source_vertex.id,
+            to_vertex_id: target_vertex.id,
+            label: "create_edge".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        assert!(result.id > 0);
+        assert_eq!(result.from_vertex_id, source_vertex.id);
+        assert_eq!(result.from_vertex_type, source_vertex.type_);
+        assert_eq!(result.to_vertex_id, target_vertex.id);
+        assert_eq!(result.to_vertex_type, target_vertex.type_);
+        assert_eq!(result.label, "create_edge");
+        assert_eq!(result.created_by, "test");
+    }
+
+    #[tokio::test]
+    async fn test_get_vertex_by_id() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertex = NewVertex {
+            name: "get_vertex_by_id".to_string(),
+            type_: "get_vertex_by_id".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+
+        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id, None)
+            .await
+            .unwrap();
+        assert_eq!(result.id, new_vertex.id);
+        assert_eq!(result.name, "get_vertex_by_id");
+        assert_eq!(result.type_, "get_vertex_by_id");
+        assert_eq!(result.created_by, "test");
+    }
+
+    #[tokio::test]
+    async fn test_delete_vertex_by_id_without_relationship() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertex = NewVertex {
+            name: "delete_vertex_by_id".to_string(),
+            type_: "delete_vertex_by_id".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+
+        let result = crate::api::delete_vertex_by_id(&mut conn, new_vertex.id, None, "test")
+            .await
+            .unwrap();
+        assert_eq!(result, 1);
+
+        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id, None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_vertex_by_id_with_relationship() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "delete_vertex_by_id_with_relationship_source_vertex".to_string(),
+            type_: "delete_vertex_by_id_with_relationship_source_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "delete_vertex_by_id_with_relationship_target_vertex".to_string(),
+            type_: "delete_vertex_by_id_with_relationship_target_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: source_vertex.id,
+            to_vertex_id: target_vertex.id,
+            label: "delete_vertex_by_id_with_relationship".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let _ = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+
+        let result =
+            crate::api::delete_vertex_by_id(&mut conn, source_vertex.id, None, "test").await;
+        assert!(result.is_ok());
+
+        edge.filter(from_vertex_id.eq(source_vertex.id))
+            .select(edge::id)
+            .first::<i32>(&mut conn)
+            .await
+            .expect_err("edge should be deleted");
+    }
+
+    #[tokio::test]
+    async fn test_create_vertices() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertices = vec![
+            NewVertex {
+                name: "create_vertices_1".to_string(),
+                type_: "create_vertices_1".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            },
+            NewVertex {
+                name: "create_vertices_2".to_string(),
+                type_: "create_vertices_2".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            },
+        ];
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let result = crate::api::create_vertices(&mut conn, &new_vertices)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].name, "create_vertices_1");
+        assert_eq!(result[0].type_, "create_vertices_1");
+        assert_eq!(result[0].created_by, "test");
+        assert_eq!(result[1].name, "create_vertices_2");
+        assert_eq!(result[1].type_, "create_vertices_2");
+        assert_eq!(result[1].created_by, "test");
+    }
+
+    #[tokio::test]
+    async fn test_get_vertices_page() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertices = ["vertices_page_1", "vertices_page_2", "vertices_page_3"]
+            .into_iter()
+            .map(|vertex_name| NewVertex {
+                name: vertex_name.to_string(),
+                type_: "get_vertices_page".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let vertices = crate::api::create_vertices(&mut conn, &new_vertices)
+            .await
+            .unwrap();
+
+        let result = crate::api::get_vertices_page(&mut conn, vertices[0].id - 1, 1)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, vertices[0].id);
+
+        crate::api::soft_delete_vertex_by_id(&mut conn, vertices[1].id, None, "test")
+            .await
+            .unwrap();
+
+        let result = crate::api::get_vertices_page(&mut conn, vertices[0].id, 1000)
+            .await
+            .unwrap();
+        assert!(result.windows(2).all(|page| page[0].id < page[1].id));
+        assert!(!result.iter().any(|vertex| vertex.id == vertices[1].id));
+        assert!(result.iter().any(|vertex| vertex.id == vertices[2].id));
+
+        let result = crate::api::get_vertices_page(&mut conn, 0, 0).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_edges() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "create_edges_source_vertex".to_string(),
+            type_: "create_edges_source_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "create_edges_target_vertex".to_string(),
+            type_: "create_edges_target_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edges = vec![
+            crate::dto::NewEdge {
+                from_vertex_id: source_vertex.id,
+                to_vertex_id: target_vertex.id,
+                label: "create_edges_1".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            },
+            crate::dto::NewEdge {
+                from_vertex_id: target_vertex.id,
+                to_vertex_id: source_vertex.id,
+                label: "create_edges_2".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let result = crate::api::create_edges(&mut conn, &new_edges)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].from_vertex_id, source_vertex.id);
+        assert_eq!(result[0].from_vertex_type, source_vertex.type_);
+        assert_eq!(result[0].to_vertex_id, target_vertex.id);
+        assert_eq!(result[0].to_vertex_type, target_vertex.type_);
+        assert_eq!(result[0].label, "create_edges_1");
+        assert_eq!(result[0].created_by, "test");
+        assert_eq!(result[1].from_vertex_id, target_vertex.id);
+        assert_eq!(result[1].from_vertex_type, target_vertex.type_);
+        assert_eq!(result[1].to_vertex_id, source_vertex.id);
+        assert_eq!(result[1].to_vertex_type, source_vertex.type_);
+        assert_eq!(result[1].label, "create_edges_2");
+        assert_eq!(result[1].created_by, "test");
+    }
+
+    #[tokio::test]
+    async fn test_get_vertex_history() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertex = NewVertex {
+            name: "get_vertex_history".to_string(),
+            type_: "get_vertex_history".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+
+        crate::api::delete_vertex_by_id(&mut conn, new_vertex.id, None, "auditor")
+            .await
+            .unwrap();
+
+        let result = crate::api::get_vertex_history(&mut conn, new_vertex.id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].operation, "INSERT");
+        assert_eq!(result[0].changed_by, "test");
+        assert!(result[0].old_row.is_none());
+        assert_eq!(
+            result[0].new_row.as_ref().unwrap()["name"],
+            "get_vertex_history"
+        );
+        assert_eq!(result[1].operation, "DELETE");
+        assert_eq!(result[1].changed_by, "auditor");
+        assert!(result[1].new_row.is_none());
+        assert_eq!(result[1].old_row.as_ref().unwrap()["id"], new_vertex.id);
+
+        // The log is append-only.
+        use crate::schema::vertex_history;
+        let result = diesel::update(
+            vertex_history::table.filter(vertex_history::vertex_id.eq(new_vertex.id)),
+        )
+        .set(vertex_history::changed_by.eq("forger"))
+        .execute(&mut conn)
+        .await;
+        assert!(result.is_err());
+        let result = diesel::delete(
+            vertex_history::table.filter(vertex_history::vertex_id.eq(new_vertex.id)),
+        )
+        .execute(&mut conn)
+        .await;
+        assert!(result.is_err());
+        let result = crate::api::get_vertex_history(&mut conn, new_vertex.id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[1].changed_by, "auditor");
+    }
+
+    #[tokio::test]
+    async fn test_get_edge_history_with_cascaded_delete() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "get_edge_history_source_vertex".to_string(),
+            type_: "get_edge_history_source_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "get_edge_history_target_vertex".to_string(),
+            type_: "get_edge_history_target_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: source_vertex.id,
+            to_vertex_id: target_vertex.id,
+            label: "get_edge_history".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let new_edge = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+
+        crate::api::delete_vertex_by_id(&mut conn, target_vertex.id, None, "auditor")
+            .await
+            .unwrap();
+
+        let result = crate::api::get_edge_history(&mut conn, new_edge.id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].operation, "INSERT");
+        assert_eq!(result[1].operation, "DELETE");
+        assert_eq!(result[1].changed_by, "auditor");
+        assert_eq!(
+            result[1].old_row.as_ref().unwrap()["label"],
+            "get_edge_history"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_vertex_by_id_as_of() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let new_vertex = NewVertex {
+            name: "get_vertex_by_id_as_of".to_string(),
+            type_: "get_vertex_by_id_as_of".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+
+        crate::api::delete_vertex_by_id(&mut conn, new_vertex.id, None, "test")
+            .await
+            .unwrap();
+
+        let result =
+            crate::api::get_vertex_by_id(&mut conn, new_vertex.id, Some(new_vertex.created_at))
+                .await
+                .unwrap();
+        assert_eq!(result.id, new_vertex.id);
+        assert_eq!(result.name, "get_vertex_by_id_as_of");
+        assert_eq!(result.created_at, new_vertex.created_at);
+
+        let before = new_vertex.created_at - chrono::Duration::seconds(1);
+        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id, Some(before)).await;
+        assert!(result.is_err());
+
+        let result = crate::api::get_vertex_by_id(&mut conn, new_vertex.id, None).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_neighbors_and_traverse() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in ["neighbors_a", "neighbors_b", "neighbors_c", "neighbors_d"] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "get_neighbors".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let mut edges = Vec::new();
+        for (from, to) in [(0, 1), (0, 2), (2, 3)] {
+            let new_edge = crate::dto::NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: "get_neighbors".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            edges.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let query = EdgeQuery::default();
+        let result = crate::api::get_neighbors(&mut conn, vertices[0].id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, vertices[1].id);
+        assert_eq!(result[1].id, vertices[2].id);
+
+        let query = EdgeQuery {
+            direction: Direction::Incoming,
+            ..Default::default()
+        };
+        let result = crate::api::get_neighbors(&mut conn, vertices[3].id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, vertices[2].id);
+
+        let query = EdgeQuery::default();
+        let result = crate::api::traverse(&mut conn, vertices[0].id, 2, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[2].id, vertices[3].id);
+
+        let query = EdgeQuery {
+            direction: Direction::Both,
+            ..Default::default()
+        };
+        let result = crate::api::get_incident_edges_by_vertex_ids(
+            &mut conn,
+            &[vertices[0].id, vertices[2].id],
+            &query,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[&vertices[0].id].len(), 2);
+        assert_eq!(result[&vertices[2].id].len(), 2);
+        assert_eq!(result[&vertices[2].id][0].id, edges[1].id);
+        assert_eq!(result[&vertices[2].id][1].id, edges[2].id);
+
+        let query = EdgeQuery::default();
+        let as_of = edges[2].created_at;
+        crate::api::delete_edge_by_id(&mut conn, edges[0].id, None, "test")
+            .await
+            .unwrap();
+
+        let result = crate::api::get_neighbors(&mut conn, vertices[0].id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, vertices[2].id);
+
+        let query = EdgeQuery {
+            as_of: Some(as_of),
+            ..Default::default()
+        };
+        let result = crate::api::get_neighbors(&mut conn, vertices[0].id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].id, vertices[1].id);
+
+        let result = crate::api::traverse(&mut conn, vertices[0].id, 2, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete_restore_and_purge_vertex() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "soft_delete_source_vertex".to_string(),
+            type_: "soft_delete_source_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "soft_delete_target_vertex".to_string(),
+            type_: "soft_delete_target_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: source_vertex.id,
+            to_vertex_id: target_vertex.id,
+            label: "soft_delete".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let new_edge = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+
+        let result =
+            crate::api::soft_delete_vertex_by_id(&mut conn, source_vertex.id, None, "trasher")
+                .await
+                .unwrap();
+        assert_eq!(result, 1);
+
+        let result = crate::api::get_vertex_by_id(&mut conn, source_vertex.id, None).await;
+        assert!(result.is_err());
+
+        let query = EdgeQuery {
+            direction: Direction::Incoming,
+            ..Default::default()
+        };
+        let result = crate::api::get_incident_edges(&mut conn, target_vertex.id, &query)
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+
+        let trashed = crate::api::get_deleted_edges(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|trashed| trashed.id == new_edge.id)
+            .unwrap();
+        assert_eq!(trashed.deleted_by.as_deref(), Some("trasher"));
+
+        let result = crate::api::restore_vertex_by_id(&mut conn, source_vertex.id, "test")
+            .await
+            .unwrap();
+        assert_eq!(result, 1);
+
+        let result = crate::api::get_incident_edges(&mut conn, target_vertex.id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, new_edge.id);
+        assert!(result[0].deleted_at.is_none());
+
+        crate::api::soft_delete_vertex_by_id(&mut conn, source_vertex.id, None, "test")
+            .await
+            .unwrap();
+        let trashed = crate::api::get_deleted_vertices(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|trashed| trashed.id == source_vertex.id)
+            .unwrap();
+
+        let deleted_before = trashed.deleted_at.unwrap() + chrono::Duration::microseconds(1);
+        let result = crate::api::purge_deleted(&mut conn, deleted_before, "test")
+            .await
+            .unwrap();
+        assert!(result >= 2);
+
+        let result = crate::api::restore_vertex_by_id(&mut conn, source_vertex.id, "test").await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_delete_vertex_with_policies() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in [
+            "delete_policy_hub",
+            "delete_policy_a",
+            "delete_policy_b",
+            "delete_policy_c",
+        ] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "delete_vertex".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let mut edges = Vec::new();
+        for (to, edge_label) in [
+            (1, "deletepolicyrestrict"),
+            (2, "deletepolicycascade"),
+            (3, "deletepolicydetach"),
+        ] {
+            let new_edge = crate::dto::NewEdge {
+                from_vertex_id: vertices[0].id,
+                to_vertex_id: vertices[to].id,
+                label: edge_label.to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            edges.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        for (edge_label, delete_policy) in [
+            ("deletepolicyrestrict", DeletePolicy::Restrict),
+            ("deletepolicycascade", DeletePolicy::Cascade),
+            ("deletepolicydetach", DeletePolicy::Detach),
+        ] {
+            let new_setting = crate::dto::NewEdgeLabelSetting {
+                label: edge_label.to_string(),
+                delete_policy,
+                inverse_label: None,
+                symmetric: false,
+                allow_self_loops: false,
+                multigraph: false,
+                created_by: "test".to_string(),
+            };
+            crate::api::save_edge_label_setting(&mut conn, &new_setting)
+                .await
+                .unwrap();
+        }
+
+        let new_setting = crate::dto::NewEdgeLabelSetting {
+            label: "delete policy".to_string(),
+            delete_policy: DeletePolicy::Restrict,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops: false,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        let result = crate::api::save_edge_label_setting(&mut conn, &new_setting).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let result = crate::api::delete_vertex(
+            &mut conn,
+            vertices[0].id,
+            DeletePolicy::Cascade,
+            true,
+            false,
+            None,
+            "test",
+        )
+        .await
+        .unwrap();
+        assert!(!result.deleted);
+        assert_eq!(result.vertex.id, vertices[0].id);
+        assert_eq!(result.neighbors.len(), 3);
+        let policies = result
+            .incident_labels
+            .iter()
+            .map(|incident| {
+                (
+                    incident.label.as_str(),
+                    incident.delete_policy,
+                    incident.edge_count,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            policies,
+            [
+                ("deletepolicycascade", DeletePolicy::Cascade, 1),
+                ("deletepolicydetach", DeletePolicy::Detach, 1),
+                ("deletepolicyrestrict", DeletePolicy::Restrict, 1),
+            ]
+        );
+
+        // Every way of deleting the vertex honours the restricting label.
+        let result = crate::api::delete_vertex(
+            &mut conn,
+            vertices[0].id,
+            DeletePolicy::Cascade,
+            false,
+            false,
+            None,
+            "test",
+        )
+        .await;
+        match result {
+            Err(crate::error::Error::DeleteRestricted(labels)) => {
+                assert_eq!(labels, vec!["deletepolicyrestrict".to_string()]);
+            }
+            other => panic!("expected a restricted delete, got {other:?}"),
+        }
+        let result =
+            crate::api::soft_delete_vertex_by_id(&mut conn, vertices[0].id, None, "test").await;
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::DeleteRestricted(_))
+        ));
+        let result = crate::api::delete_vertex_by_id(&mut conn, vertices[0].id, None, "test").await;
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::DeleteRestricted(_))
+        ));
+        assert!(
+            crate::api::get_vertex_by_id(&mut conn, vertices[0].id, None)
+                .await
+                .is_ok()
+        );
+
+        let new_setting = crate::dto::NewEdgeLabelSetting {
+            label: "deletepolicyrestrict".to_string(),
+            delete_policy: DeletePolicy::Cascade,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops: false,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        crate::api::save_edge_label_setting(&mut conn, &new_setting)
+            .await
+            .unwrap();
+
+        let result = crate::api::delete_vertex(
+            &mut conn,
+            vertices[0].id,
+            DeletePolicy::Cascade,
+            false,
+            false,
+            None,
+            "test",
+        )
+        .await
+        .unwrap();
+        assert!(result.deleted);
+        assert!(
+            crate::api::get_vertex_by_id(&mut conn, vertices[0].id, None)
+                .await
+                .is_err()
+        );
+        assert!(
+            crate::api::get_vertex_by_id(&mut conn, vertices[1].id, None)
+                .await
+                .is_ok()
+        );
+
+        // The vertex was trashed: restoring it brings back the cascaded
+        // edges, but not the detached one.
+        let result = crate::api::restore_vertex_by_id(&mut conn, vertices[0].id, "test")
+            .await
+            .unwrap();
+        assert_eq!(result, 1);
+        let query = EdgeQuery {
+            direction: Direction::Both,
+            ..Default::default()
+        };
+        let mut restored = crate::api::get_incident_edges(&mut conn, vertices[0].id, &query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|incident| incident.id)
+            .collect::<Vec<_>>();
+        restored.sort_unstable();
+        assert_eq!(restored, [edges[0].id, edges[1].id]);
+        let result = crate::api::get_edge_history(&mut conn, edges[2].id)
+            .await
+            .unwrap();
+        assert_eq!(result.last().unwrap().operation, "DELETE");
+
+        let result = crate::api::delete_vertex(
+            &mut conn,
+            vertices[0].id,
+            DeletePolicy::Cascade,
+            false,
+            true,
+            None,
+            "test",
+        )
+        .await
+        .unwrap();
+        assert!(result.deleted);
+        let result = crate::api::restore_vertex_by_id(&mut conn, vertices[0].id, "test").await;
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::Database(
+                diesel::result::Error::NotFound
+            ))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_update_vertex_with_version() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let source_vertex = NewVertex {
+            name: "update_vertex_source_vertex".to_string(),
+            type_: "update_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let target_vertex = NewVertex {
+            name: "update_vertex_target_vertex".to_string(),
+            type_: "update_vertex".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+            .await
+            .unwrap();
+        assert_eq!(source_vertex.version, 1);
+
+        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+            .await
+            .unwrap();
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: source_vertex.id,
+            to_vertex_id: target_vertex.id,
+            label: "update_vertex".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let new_edge = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+
+        let update = crate::dto::UpdateVertex {
+            name: None,
+            type_: Some("update_vertex_renamed".to_string()),
+            labels: None,
+            updated_by: "editor".to_string(),
+        };
+        let expected_version = crate::model::version_from_etag(&source_vertex.etag());
+        let result =
+            crate::api::update_vertex(&mut conn, source_vertex.id, &update, expected_version)
+                .await
+                .unwrap();
+        assert_eq!(result.version, 2);
+        assert_eq!(result.type_, "update_vertex_renamed");
+        assert_eq!(result.name, "update_vertex_source_vertex");
+        assert_eq!(result.updated_by, "editor");
+
+        let query = EdgeQuery::default();
+        let result = crate::api::get_incident_edges(&mut conn, source_vertex.id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result[0].id, new_edge.id);
+        assert_eq!(result[0].from_vertex_type, "update_vertex_renamed");
+        assert_eq!(result[0].version, 2);
+
+        let result = crate::api::update_vertex(&mut conn, source_vertex.id, &update, Some(1)).await;
+        match result {
+            Err(crate::error::Error::Conflict { expected, actual }) => {
+                assert_eq!(expected, 1);
+                assert_eq!(actual, 2);
+            }
+            other => panic!("expected a version conflict, got {other:?}"),
+        }
+
+        let result =
+            crate::api::delete_vertex_by_id(&mut conn, source_vertex.id, Some(1), "test").await;
+        assert!(matches!(result, Err(crate::error::Error::Conflict { .. })));
+
+        let result = crate::api::delete_vertex_by_id(&mut conn, source_vertex.id, Some(2), "test")
+            .await
+            .unwrap();
+        assert_eq!(result, 1);
+    }
+
+    #[tokio::test]
+    async fn test_inverse_and_symmetric_labels() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in [
+            "label_semantics_a",
+            "label_semantics_b",
+            "label_semantics_c",
+        ] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "label_semantics".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        for (edge_label, inverse_label, symmetric) in [
+            ("semanticsmarried", None, true),
+            ("semanticsemploys", Some("semanticsemployedby"), false),
+        ] {
+            let new_setting = crate::dto::NewEdgeLabelSetting {
+                label: edge_label.to_string(),
+                delete_policy: DeletePolicy::Cascade,
+                inverse_label: inverse_label.map(str::to_string),
+                symmetric,
+                allow_self_loops: false,
+                multigraph: false,
+                created_by: "test".to_string(),
+            };
+            crate::api::save_edge_label_setting(&mut conn, &new_setting)
+                .await
+                .unwrap();
+        }
+
+        // The inverse is already paired with another label.
+        let new_setting = crate::dto::NewEdgeLabelSetting {
+            label: "semanticsemployedby".to_string(),
+            delete_policy: DeletePolicy::Cascade,
+            inverse_label: None,
+            symmetric: true,
+            allow_self_loops: false,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        let result = crate::api::save_edge_label_setting(&mut conn, &new_setting).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        // A label is either symmetric or has an inverse other than itself.
+        for (inverse_label, symmetric) in [("semanticssibling", false), ("semanticsother", true)] {
+            let new_setting = crate::dto::NewEdgeLabelSetting {
+                label: "semanticssibling".to_string(),
+                delete_policy: DeletePolicy::Cascade,
+                inverse_label: Some(inverse_label.to_string()),
+                symmetric,
+                allow_self_loops: false,
+                multigraph: false,
+                created_by: "test".to_string(),
+            };
+            let result = crate::api::save_edge_label_setting(&mut conn, &new_setting).await;
+            let Err(crate::error::Error::Validation(errors)) = result else {
+                panic!("expected a validation error, got {result:?}");
+            };
+            assert!(errors.errors().contains_key("__all__"));
+        }
+
+        let outgoing = |edge_label: &str| EdgeQuery {
+            direction: Direction::Outgoing,
+            label: Some(edge_label.to_string()),
+            as_of: None,
+            min_weight: None,
+            valid_at: None,
+            valid_during: None,
+        };
+
+        let mut pairs = Vec::new();
+        for (to, edge_label) in [(1, "semanticsmarried"), (2, "semanticsemploys")] {
+            let new_edge = crate::dto::NewEdge {
+                from_vertex_id: vertices[0].id,
+                to_vertex_id: vertices[to].id,
+                label: edge_label.to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            pairs.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let married = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[1].id,
+            &outgoing("semanticsmarried"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(married.len(), 1);
+        assert_eq!(married[0].to_vertex_id, vertices[0].id);
+        assert_eq!(married[0].mirror_of, Some(pairs[0].id));
+
+        let employed_by = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[2].id,
+            &outgoing("semanticsemployedby"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(employed_by.len(), 1);
+        assert_eq!(employed_by[0].to_vertex_id, vertices[0].id);
+        assert_eq!(employed_by[0].mirror_of, Some(pairs[1].id));
+
+        // The other side exists already.
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: vertices[2].id,
+            to_vertex_id: vertices[0].id,
+            label: "semanticsemployedby".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        let result = crate::api::create_edge(&mut conn, &new_edge).await;
+        assert_eq!(
+            result.unwrap_err().kind(),
+            crate::error::ErrorKind::AlreadyExists
+        );
+
+        // Trashing either side trashes both, and restoring brings both back.
+        crate::api::soft_delete_edge_by_id(&mut conn, employed_by[0].id, None, "test")
+            .await
+            .unwrap();
+        let employs = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[0].id,
+            &outgoing("semanticsemploys"),
+        )
+        .await
+        .unwrap();
+        assert!(employs.is_empty());
+        crate::api::restore_edge_by_id(&mut conn, pairs[1].id, "test")
+            .await
+            .unwrap();
+        let result = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[2].id,
+            &outgoing("semanticsemployedby"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert!(result[0].deleted_at.is_none());
+
+        // Mirrors follow their edge.
+        let update = crate::dto::UpdateEdge {
+            label: Some("semanticsunpaired".to_string()),
+            weight: None,
+            valid_from: None,
+            valid_to: None,
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_edge(&mut conn, employed_by[0].id, &update, None).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+        crate::api::update_edge(&mut conn, pairs[1].id, &update, None)
+            .await
+            .unwrap();
+        let result = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[2].id,
+            &outgoing("semanticsemployedby"),
+        )
+        .await
+        .unwrap();
+        assert!(result.is_empty());
+
+        // Deleting the mirror deletes the edge.
+        crate::api::delete_edge_by_id(&mut conn, married[0].id, None, "test")
+            .await
+            .unwrap();
+        let result = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[0].id,
+            &outgoing("semanticsmarried"),
+        )
+        .await
+        .unwrap();
+        assert!(result.is_empty());
+
+        // Existing edges are paired when the label is declared symmetric, and
+        // unpaired when it no longer is.
+        for symmetric in [true, false] {
+            let new_setting = crate::dto::NewEdgeLabelSetting {
+                label: "semanticsunpaired".to_string(),
+                delete_policy: DeletePolicy::Cascade,
+                inverse_label: None,
+                symmetric,
+                allow_self_loops: false,
+                multigraph: false,
+                created_by: "test".to_string(),
+            };
+            crate::api::save_edge_label_setting(&mut conn, &new_setting)
+                .await
+                .unwrap();
+            let result = crate::api::get_incident_edges(
+                &mut conn,
+                vertices[2].id,
+                &outgoing("semanticsunpaired"),
+            )
+            .await
+            .unwrap();
+            assert_eq!(result.len(), usize::from(symmetric));
+        }
+        let result = crate::api::get_incident_edges(
+            &mut conn,
+            vertices[0].id,
+            &outgoing("semanticsunpaired"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, pairs[1].id);
+    }
+
+    #[tokio::test]
+    async fn test_undirected_edges() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in ["undirected_a", "undirected_b", "undirected_c"] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "undirected".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // b - a is stored as a - b.
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: vertices[1].id,
+            to_vertex_id: vertices[0].id,
+            label: "undirectedsimilar".to_string(),
+            created_by: "test".to_string(),
+            undirected: true,
+            ..Default::default()
+        };
+        let result = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        assert!(result.undirected);
+        assert_eq!(result.from_vertex_id, vertices[0].id);
+        assert_eq!(result.to_vertex_id, vertices[1].id);
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: vertices[0].id,
+            to_vertex_id: vertices[1].id,
+            ..new_edge
+        };
+        let result = crate::api::create_edge(&mut conn, &new_edge).await;
+        assert_eq!(
+            result.unwrap_err().kind(),
+            crate::error::ErrorKind::AlreadyExists
+        );
+
+        let new_edge = crate::dto::NewEdge {
+            from_vertex_id: vertices[2].id,
+            to_vertex_id: vertices[1].id,
+            ..new_edge
+        };
+        crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+
+        for direction in [Direction::Outgoing, Direction::Incoming, Direction::Both] {
+            let query = EdgeQuery {
+                direction,
+                label: Some("undirectedsimilar".to_string()),
+                as_of: None,
+                min_weight: None,
+                valid_at: None,
+                valid_during: None,
+            };
+            let result = crate::api::get_neighbors(&mut conn, vertices[1].id, &query)
+                .await
+                .unwrap();
+            assert_eq!(
+                result.iter().map(|vertex| vertex.id).collect::<Vec<_>>(),
+                vec![vertices[0].id, vertices[2].id]
+            );
+
+            let result = crate::api::traverse(&mut conn, vertices[0].id, 2, &query)
+                .await
+                .unwrap();
+            assert_eq!(
+                result.iter().map(|vertex| vertex.id).collect::<Vec<_>>(),
+                vec![vertices[1].id, vertices[2].id]
+            );
+        }
+
+        let query = EdgeQuery {
+            direction: Direction::Incoming,
+            ..Default::default()
+        };
+        let result =
+            crate::api::get_incident_edges_by_vertex_ids(&mut conn, &[vertices[0].id], &query)
+                .await
+                .unwrap();
+        assert_eq!(result[&vertices[0].id].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_self_loop_policy() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = NewVertex {
+            name: "self_loop_document".to_string(),
+            type_: "self_loop".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+        let document = crate::api::create_vertex(&mut conn, &new_vertex)
             .await
-            .expect_err("edge should be deleted");
+            .unwrap();
+
+        let self_loop = |edge_label: &str| crate::dto::NewEdge {
+            from_vertex_id: document.id,
+            to_vertex_id: document.id,
+            label: edge_label.to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = crate::api::create_edge(&mut conn, &self_loop("selfloopreferences")).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let new_setting = |allow_self_loops| crate::dto::NewEdgeLabelSetting {
+            label: "selfloopreferences".to_string(),
+            delete_policy: DeletePolicy::Cascade,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        crate::api::save_edge_label_setting(&mut conn, &new_setting(true))
+            .await
+            .unwrap();
+        let references = crate::api::create_edge(&mut conn, &self_loop("selfloopreferences"))
+            .await
+            .unwrap();
+        assert_eq!(references.from_vertex_id, references.to_vertex_id);
+
+        // One forbidden self-loop fails the whole batch.
+        let result = crate::api::create_edges(
+            &mut conn,
+            &[self_loop("selfloopcites"), self_loop("selfloopforbidden")],
+        )
+        .await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+        let query = EdgeQuery::default();
+        let result = crate::api::get_incident_edges(&mut conn, document.id, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+
+        let update = crate::dto::UpdateEdge {
+            label: Some("selfloopforbidden".to_string()),
+            weight: None,
+            valid_from: None,
+            valid_to: None,
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_edge(&mut conn, references.id, &update, None).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        // Self-loops cannot be forbidden while the label has live ones.
+        let result = crate::api::save_edge_label_setting(&mut conn, &new_setting(false)).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+        crate::api::soft_delete_edge_by_id(&mut conn, references.id, None, "test")
+            .await
+            .unwrap();
+        let result = crate::api::save_edge_label_setting(&mut conn, &new_setting(false))
+            .await
+            .unwrap();
+        assert!(!result.allow_self_loops);
     }
 
     #[tokio::test]
-    async fn test_create_vertices() {
+    async fn test_multigraph_edges() {
         dotenvy::from_path(".env").ok();
 
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
-        let new_vertices = vec![
-            NewVertex {
-                name: "create_vertices_1".to_string(),
-                type_: "create_vertices_1".to_string(),
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in ["multigraph_a", "multigraph_b"] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "multigraph".to_string(),
                 created_by: "test".to_string(),
-            },
-            NewVertex {
-                name: "create_vertices_2".to_string(),
-                type_: "create_vertices_2".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        let new_setting = |multigraph| crate::dto::NewEdgeLabelSetting {
+            label: "multigraphtransferred".to_string(),
+            delete_policy: DeletePolicy::Cascade,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops: false,
+            multigraph,
+            created_by: "test".to_string(),
+        };
+        crate::api::save_edge_label_setting(&mut conn, &new_setting(true))
+            .await
+            .unwrap();
+
+        let transfer = |key: Option<&str>, amount: f64| crate::dto::NewEdge {
+            from_vertex_id: vertices[0].id,
+            to_vertex_id: vertices[1].id,
+            label: "multigraphtransferred".to_string(),
+            created_by: "test".to_string(),
+            edge_key: key.map(str::to_string),
+            properties: serde_json::json!({ "amount": amount })
+                .as_object()
+                .unwrap()
+                .clone(),
+            ..Default::default()
+        };
+
+        // Parallel edges without a key are told apart by their properties.
+        let result =
+            crate::api::create_edges(&mut conn, &[transfer(None, 10.0), transfer(None, 25.5)])
+                .await
+                .unwrap();
+        assert_ne!(result[0].edge_key, result[1].edge_key);
+        let result = crate::api::create_edge(&mut conn, &transfer(None, 10.0)).await;
+        assert_eq!(
+            result.unwrap_err().kind(),
+            crate::error::ErrorKind::AlreadyExists
+        );
+        let result = crate::api::create_edge(&mut conn, &transfer(Some("tx3"), 10.0))
+            .await
+            .unwrap();
+        assert_eq!(result.edge_key.as_deref(), Some("tx3"));
+        assert_eq!(result.properties["amount"], 10.0);
+
+        let keyed = crate::dto::NewEdge {
+            label: "multigraphother".to_string(),
+            ..transfer(Some("tx4"), 1.0)
+        };
+        let result = crate::api::create_edge(&mut conn, &keyed).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let query = crate::dto::ParallelEdgeQuery {
+            from_vertex_id: Some(vertices[0].id),
+            sum: Some("amount".to_string()),
+            ..Default::default()
+        };
+        let result = crate::api::aggregate_parallel_edges(&mut conn, &query)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].to_vertex_id, vertices[1].id);
+        assert_eq!(result[0].edge_count, 3);
+        assert_eq!(result[0].sum, Some(45.5));
+
+        let result = crate::api::save_edge_label_setting(&mut conn, &new_setting(false)).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_edge_validity() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in ["validity_alice", "validity_acme", "validity_globex"] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "validity".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let date = |year| {
+            chrono::NaiveDate::from_ymd_opt(year, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+
+        // alice worked at acme from 2019 to 2022, and at globex since.
+        let employed = |to, from_date, to_date| crate::dto::NewEdge {
+            from_vertex_id: vertices[0].id,
+            to_vertex_id: to,
+            label: "validityemployed".to_string(),
+            created_by: "test".to_string(),
+            valid_from: from_date,
+            valid_to: to_date,
+            ..Default::default()
+        };
+        let acme = crate::api::create_edge(
+            &mut conn,
+            &employed(vertices[1].id, Some(date(2019)), Some(date(2022))),
+        )
+        .await
+        .unwrap();
+        let globex =
+            crate::api::create_edge(&mut conn, &employed(vertices[2].id, Some(date(2022)), None))
+                .await
+                .unwrap();
+
+        let valid_at = |instant| EdgeQuery {
+            valid_at: Some(instant),
+            ..Default::default()
+        };
+        let valid_during = |from, to| EdgeQuery {
+            valid_during: Some(Interval { from, to }),
+            ..Default::default()
+        };
+        for (query, expected) in [
+            (valid_at(date(2020)), vec![acme.id]),
+            (valid_at(date(2022)), vec![globex.id]),
+            (valid_at(date(2018)), vec![]),
+            (
+                valid_during(Some(date(2021)), Some(date(2023))),
+                vec![acme.id, globex.id],
+            ),
+            (valid_during(None, Some(date(2019))), vec![]),
+            (valid_during(Some(date(2030)), None), vec![globex.id]),
+        ] {
+            let result = crate::api::get_incident_edges(&mut conn, vertices[0].id, &query)
+                .await
+                .unwrap();
+            assert_eq!(
+                result
+                    .iter()
+                    .map(|incident| incident.id)
+                    .collect::<Vec<_>>(),
+                expected,
+                "{query:?}"
+            );
+        }
+
+        let result = crate::api::create_edge(
+            &mut conn,
+            &employed(vertices[1].id, Some(date(2022)), Some(date(2022))),
+        )
+        .await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let update = crate::dto::UpdateEdge {
+            label: None,
+            weight: None,
+            valid_from: None,
+            valid_to: Some(Some(date(2024))),
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_edge(&mut conn, globex.id, &update, None)
+            .await
+            .unwrap();
+        assert_eq!(result.valid_from, Some(date(2022)));
+        assert_eq!(result.valid_to, Some(date(2024)));
+
+        let update = crate::dto::UpdateEdge {
+            valid_from: Some(Some(date(2025))),
+            valid_to: None,
+            ..update
+        };
+        let result = crate::api::update_edge(&mut conn, globex.id, &update, None).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let update: crate::dto::UpdateEdge =
+            serde_json::from_value(serde_json::json!({ "valid_to": null, "updated_by": "test" }))
+                .unwrap();
+        let result = crate::api::update_edge(&mut conn, globex.id, &update, None)
+            .await
+            .unwrap();
+        assert_eq!(result.valid_from, Some(date(2022)));
+        assert_eq!(result.valid_to, None);
+    }
+
+    #[tokio::test]
+    async fn test_edge_provenance() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in [
+            "provenance_aspirin",
+            "provenance_headache",
+            "provenance_fever",
+        ] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "provenance".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let treats = |to| crate::dto::NewEdge {
+            from_vertex_id: vertices[0].id,
+            to_vertex_id: to,
+            label: "provenancetreats".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        let assertion = |to, asserted_by: &str, sure| crate::dto::NewEdgeAssertion {
+            edge: treats(to),
+            source: asserted_by.to_string(),
+            confidence: sure,
+            evidence: vec![serde_json::json!(format!("{asserted_by}/{to}"))],
+        };
+
+        // Both sources assert aspirin treats headache; only the extractor
+        // asserts it treats fever.
+        let headache =
+            crate::api::assert_edge(&mut conn, &assertion(vertices[1].id, "provenance/ner", 0.6))
+                .await
+                .unwrap();
+        let merged =
+            crate::api::assert_edge(&mut conn, &assertion(vertices[1].id, "provenance.kb", 0.9))
+                .await
+                .unwrap();
+        assert_eq!(merged.id, headache.id);
+        let fever =
+            crate::api::assert_edge(&mut conn, &assertion(vertices[2].id, "provenance/ner", 0.4))
+                .await
+                .unwrap();
+        assert_ne!(fever.id, headache.id);
+
+        // Asserting again replaces the source's earlier assertion.
+        crate::api::assert_edge(&mut conn, &assertion(vertices[1].id, "provenance/ner", 0.7))
+            .await
+            .unwrap();
+        let assertions = crate::api::get_edge_assertions(&mut conn, headache.id)
+            .await
+            .unwrap();
+        let sources = assertions
+            .iter()
+            .map(|claim| (claim.source.as_str(), claim.confidence))
+            .collect::<Vec<_>>();
+        assert_eq!(sources, [("provenance.kb", 0.9), ("provenance/ner", 0.7)]);
+
+        let result =
+            crate::api::assert_edge(&mut conn, &assertion(vertices[2].id, "provenance/ner", 1.5))
+                .await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let by_provenance = |asserted_by: Option<&str>, sure| crate::dto::ProvenanceQuery {
+            label: Some("provenancetreats".to_string()),
+            source: asserted_by.map(str::to_string),
+            min_confidence: sure,
+        };
+        for (query, expected) in [
+            (by_provenance(None, Some(0.8)), vec![headache.id]),
+            (
+                by_provenance(Some("provenance/ner"), None),
+                vec![headache.id, fever.id],
+            ),
+            (
+                by_provenance(Some("provenance/ner"), Some(0.5)),
+                vec![headache.id],
+            ),
+            (by_provenance(Some("provenance/none"), None), vec![]),
+        ] {
+            let result = crate::api::get_edges_by_provenance(&mut conn, &query)
+                .await
+                .unwrap();
+            let result_ids = result.iter().map(|found| found.id).collect::<Vec<_>>();
+            assert_eq!(result_ids, expected, "{query:?}");
+        }
+
+        // Retracting the extractor leaves the edge the knowledge base backs.
+        let retraction = crate::api::retract_source(&mut conn, "provenance/ner", "test")
+            .await
+            .unwrap();
+        assert_eq!(retraction.assertions, 2);
+        assert_eq!(retraction.trashed_edge_ids, vec![fever.id]);
+        let live_ids = edge
+            .filter(label.eq("provenancetreats"))
+            .filter(deleted_at.is_null())
+            .select(id)
+            .load::<i32>(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(live_ids, vec![headache.id]);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_edge_assertions() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in ["provenance_race_aspirin", "provenance_race_pain"] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "provenance".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let assertion = |asserted_by: &str| crate::dto::NewEdgeAssertion {
+            edge: crate::dto::NewEdge {
+                from_vertex_id: vertices[0].id,
+                to_vertex_id: vertices[1].id,
+                label: "provenancerelieves".to_string(),
                 created_by: "test".to_string(),
+                ..Default::default()
             },
-        ];
+            source: asserted_by.to_string(),
+            confidence: 0.5,
+            evidence: Vec::new(),
+        };
+
+        // The first assertion creates the edge but commits only once the
+        // second has missed it and is blocked inserting it as well.
+        let mut first_conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut second_conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let second_pid = diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>(
+            "pg_backend_pid()",
+        ))
+        .get_result::<i32>(&mut second_conn)
+        .await
+        .unwrap();
+
+        let (inserted, insert) = tokio::sync::oneshot::channel::<()>();
+        let (commit, committed) = tokio::sync::oneshot::channel::<()>();
+        let first_assertion = assertion("provenance/first");
+        let first = tokio::spawn(async move {
+            first_conn
+                .transaction::<_, crate::error::Error, _>(|conn| {
+                    async move {
+                        let asserted = crate::api::assert_edge(conn, &first_assertion).await?;
+                        inserted.send(()).unwrap();
+                        committed.await.unwrap();
+                        Ok(asserted)
+                    }
+                    .scope_boxed()
+                })
+                .await
+        });
+        insert.await.unwrap();
+        let second_assertion = assertion("provenance/second");
+        let second = tokio::spawn(async move {
+            crate::api::assert_edge(&mut second_conn, &second_assertion).await
+        });
+
+        let blocked = diesel::dsl::sql::<diesel::sql_types::Bool>(
+            "EXISTS (SELECT 1 FROM pg_stat_activity WHERE wait_event_type = 'Lock' AND pid = ",
+        )
+        .bind::<diesel::sql_types::Integer, _>(second_pid)
+        .sql(")");
+        while !second.is_finished()
+            && !diesel::select(blocked.clone())
+                .get_result::<bool>(&mut conn)
+                .await
+                .unwrap()
+        {}
+        commit.send(()).unwrap();
+
+        let first = first.await.unwrap().unwrap();
+        let second = second.await.unwrap().unwrap();
+        assert_eq!(first.id, second.id);
+        let assertions = crate::api::get_edge_assertions(&mut conn, first.id)
+            .await
+            .unwrap();
+        assert_eq!(assertions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_vertex_labels() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
         let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let result = crate::api::create_vertices(&mut conn, &new_vertices)
+        let new_vertex = NewVertex {
+            name: "labels_ursula".to_string(),
+            type_: "labels_person".to_string(),
+            created_by: "test".to_string(),
+            labels: vec![
+                "labels_author".to_string(),
+                "labels_person".to_string(),
+                "labels_author".to_string(),
+            ],
+        };
+        let ursula = crate::api::create_vertex(&mut conn, &new_vertex)
             .await
             .unwrap();
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].name, "create_vertices_1");
-        assert_eq!(result[0].type_, "create_vertices_1");
-        assert_eq!(result[0].created_by, "test");
-        assert_eq!(result[1].name, "create_vertices_2");
-        assert_eq!(result[1].type_, "create_vertices_2");
-        assert_eq!(result[1].created_by, "test");
+        assert_eq!(ursula.labels, vec!["labels_author", "labels_person"]);
+        assert!(ursula.has_label("labels_author"));
+
+        let with_label = |labeled: Vec<crate::model::Vertex>| {
+            labeled.iter().map(|labeled| labeled.id).collect::<Vec<_>>()
+        };
+        let result = crate::api::get_vertices_with_label(&mut conn, "labels_author", 0, 10)
+            .await
+            .unwrap();
+        assert_eq!(with_label(result), vec![ursula.id]);
+
+        // Retyping swaps the primary label; replacing the labels keeps it.
+        let retype = crate::dto::UpdateVertex {
+            name: None,
+            type_: Some("labels_editor".to_string()),
+            labels: None,
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_vertex(&mut conn, ursula.id, &retype, None)
+            .await
+            .unwrap();
+        assert_eq!(result.labels, vec!["labels_author", "labels_editor"]);
+        let relabel = crate::dto::UpdateVertex {
+            name: None,
+            type_: None,
+            labels: Some(vec!["labels_critic".to_string()]),
+            updated_by: "test".to_string(),
+        };
+        let result = crate::api::update_vertex(&mut conn, ursula.id, &relabel, None)
+            .await
+            .unwrap();
+        assert_eq!(result.labels, vec!["labels_critic", "labels_editor"]);
+
+        for invalid in ["", "  ", &"l".repeat(MAX_TYPE_LENGTH + 1)] {
+            let new_vertex = NewVertex {
+                name: "labels_blank".to_string(),
+                type_: "labels_person".to_string(),
+                created_by: "test".to_string(),
+                labels: vec![invalid.to_string()],
+            };
+            assert!(new_vertex.validate().is_err(), "{invalid:?}");
+            let relabel = crate::dto::UpdateVertex {
+                name: None,
+                type_: None,
+                labels: Some(vec!["labels_critic".to_string(), invalid.to_string()]),
+                updated_by: "test".to_string(),
+            };
+            assert!(relabel.validate().is_err(), "{invalid:?}");
+        }
+
+        let result = crate::api::get_vertices_with_label(&mut conn, "labels_author", 0, 10)
+            .await
+            .unwrap();
+        assert!(result.is_empty());
     }
 
     #[tokio::test]
-    async fn test_create_edges() {
+    async fn test_vertex_aliases() {
         dotenvy::from_path(".env").ok();
 
         let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
-        let source_vertex = NewVertex {
-            name: "create_edges_source_vertex".to_string(),
-            type_: "create_edges_source_vertex".to_string(),
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let new_vertex = |vertex_name: &str, vertex_type: &str| NewVertex {
+            name: vertex_name.to_string(),
+            type_: vertex_type.to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+        let ibm = crate::api::create_vertex(&mut conn, &new_vertex("aliases_ibm", "aliases_org"))
+            .await
+            .unwrap();
+        let acme = crate::api::create_vertex(&mut conn, &new_vertex("aliases_acme", "aliases_org"))
+            .await
+            .unwrap();
+        let new_alias = |aliased: i32, alternate: &str| crate::dto::NewVertexAlias {
+            vertex_id: aliased,
+            alias: alternate.to_string(),
             created_by: "test".to_string(),
         };
+        crate::api::create_vertex_alias(&mut conn, &new_alias(ibm.id, "aliases_big_blue"))
+            .await
+            .unwrap();
 
-        let target_vertex = NewVertex {
-            name: "create_edges_target_vertex".to_string(),
-            type_: "create_edges_target_vertex".to_string(),
+        // An alias names a single vertex of its type.
+        for result in [
+            crate::api::create_vertex_alias(&mut conn, &new_alias(ibm.id, "aliases_acme"))
+                .await
+                .map(|_| ()),
+            crate::api::create_vertex_alias(&mut conn, &new_alias(acme.id, "aliases_big_blue"))
+                .await
+                .map(|_| ()),
+            crate::api::create_vertex(&mut conn, &new_vertex("aliases_big_blue", "aliases_org"))
+                .await
+                .map(|_| ()),
+        ] {
+            assert_eq!(
+                result.unwrap_err().kind(),
+                crate::error::ErrorKind::AlreadyExists
+            );
+        }
+        let band =
+            crate::api::create_vertex(&mut conn, &new_vertex("aliases_big_blue", "aliases_band"))
+                .await
+                .unwrap();
+
+        for (vertex_type, expected) in [
+            (None, vec![ibm.id, band.id]),
+            (Some("aliases_org"), vec![ibm.id]),
+        ] {
+            let result =
+                crate::api::find_vertices_by_name(&mut conn, "aliases_big_blue", vertex_type)
+                    .await
+                    .unwrap();
+            let found_ids = result.iter().map(|found| found.id).collect::<Vec<_>>();
+            assert_eq!(found_ids, expected, "{vertex_type:?}");
+        }
+        let result = crate::traversal::g()
+            .v([ibm.id, acme.id])
+            .has_name("aliases_big_blue")
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, ibm.id);
+
+        let key = |vertex_name: &str| crate::dto::VertexKey {
+            name: vertex_name.to_string(),
+            type_: "aliases_org".to_string(),
+        };
+        let new_edge = |from_name: &str| crate::dto::NewEdgeByKey {
+            from: key(from_name),
+            to: key("aliases_acme"),
+            label: "aliasesacquired".to_string(),
             created_by: "test".to_string(),
+            undirected: false,
+            edge_key: None,
+            properties: Default::default(),
+            weight: 1.0,
+            valid_from: None,
+            valid_to: None,
         };
+        let result = crate::api::create_edge_by_key(&mut conn, &new_edge("aliases_big_blue"))
+            .await
+            .unwrap();
+        assert_eq!(result.from_vertex_id, ibm.id);
+        assert_eq!(result.to_vertex_id, acme.id);
+        let result = crate::api::create_edge_by_key(&mut conn, &new_edge("aliases_none")).await;
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::Database(
+                diesel::result::Error::NotFound
+            ))
+        ));
 
-        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
-        let source_vertex = crate::api::create_vertex(&mut conn, &source_vertex)
+        // Aliases follow their vertex when it is retyped.
+        let retype = crate::dto::UpdateVertex {
+            name: None,
+            type_: Some("aliases_company".to_string()),
+            labels: None,
+            updated_by: "test".to_string(),
+        };
+        crate::api::update_vertex(&mut conn, ibm.id, &retype, None)
             .await
             .unwrap();
+        let result = crate::api::find_vertices_by_name(
+            &mut conn,
+            "aliases_big_blue",
+            Some("aliases_company"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, ibm.id);
 
-        let target_vertex = crate::api::create_vertex(&mut conn, &target_vertex)
+        let deleted = crate::api::delete_vertex_alias(&mut conn, ibm.id, "aliases_big_blue")
             .await
             .unwrap();
+        assert_eq!(deleted, 1);
+        let result = crate::api::get_vertex_aliases(&mut conn, ibm.id)
+            .await
+            .unwrap();
+        assert!(result.is_empty());
+    }
 
-        let new_edges = vec![
-            crate::dto::NewEdge {
-                from_vertex_id: source_vertex.id,
-                to_vertex_id: target_vertex.id,
-                label: "create_edges_1".to_string(),
+    #[tokio::test]
+    async fn test_merge_vertices() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for vertex_name in [
+            "merge_ibm",
+            "merge_i.b.m.",
+            "merge_ibm_corp",
+            "merge_acme",
+            "merge_globex",
+        ] {
+            let new_vertex = NewVertex {
+                name: vertex_name.to_string(),
+                type_: "merge_org".to_string(),
                 created_by: "test".to_string(),
-            },
-            crate::dto::NewEdge {
-                from_vertex_id: target_vertex.id,
-                to_vertex_id: source_vertex.id,
-                label: "create_edges_2".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let (ibm, dotted, corp, acme, globex) = (
+            &vertices[0],
+            &vertices[1],
+            &vertices[2],
+            &vertices[3],
+            &vertices[4],
+        );
+        crate::api::create_vertex_alias(
+            &mut conn,
+            &crate::dto::NewVertexAlias {
+                vertex_id: dotted.id,
+                alias: "merge_big_blue".to_string(),
                 created_by: "test".to_string(),
             },
-        ];
+        )
+        .await
+        .unwrap();
+        let relabel = crate::dto::UpdateVertex {
+            name: None,
+            type_: None,
+            labels: Some(vec!["merge_listed".to_string()]),
+            updated_by: "test".to_string(),
+        };
+        crate::api::update_vertex(&mut conn, dotted.id, &relabel, None)
+            .await
+            .unwrap();
 
-        let result = crate::api::create_edges(&mut conn, &new_edges)
+        let supplies = |from, to, supplied: serde_json::Value| crate::dto::NewEdge {
+            from_vertex_id: from,
+            to_vertex_id: to,
+            label: "mergesupplies".to_string(),
+            created_by: "test".to_string(),
+            properties: serde_json::from_value(supplied).unwrap(),
+            ..Default::default()
+        };
+        let assertion = |supplied, asserted_by: &str, sure| crate::dto::NewEdgeAssertion {
+            edge: supplied,
+            source: asserted_by.to_string(),
+            confidence: sure,
+            evidence: Vec::new(),
+        };
+        let kept = crate::api::assert_edge(
+            &mut conn,
+            &assertion(
+                supplies(
+                    ibm.id,
+                    acme.id,
+                    serde_json::json!({ "since": 1990, "volume": 1 }),
+                ),
+                "merge/a",
+                0.5,
+            ),
+        )
+        .await
+        .unwrap();
+        for (asserted_by, sure) in [("merge/a", 0.8), ("merge/b", 0.3)] {
+            crate::api::assert_edge(
+                &mut conn,
+                &assertion(
+                    supplies(
+                        dotted.id,
+                        acme.id,
+                        serde_json::json!({ "since": 1985, "region": "eu" }),
+                    ),
+                    asserted_by,
+                    sure,
+                ),
+            )
             .await
             .unwrap();
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].from_vertex_id, source_vertex.id);
-        assert_eq!(result[0].from_vertex_type, source_vertex.type_);
-        assert_eq!(result[0].to_vertex_id, target_vertex.id);
-        assert_eq!(result[0].to_vertex_type, target_vertex.type_);
-        assert_eq!(result[0].label, "create_edges_1");
-        assert_eq!(result[0].created_by, "test");
-        assert_eq!(result[1].from_vertex_id, target_vertex.id);
-        assert_eq!(result[1].from_vertex_type, target_vertex.type_);
-        assert_eq!(result[1].to_vertex_id, source_vertex.id);
-        assert_eq!(result[1].to_vertex_type, source_vertex.type_);
-        assert_eq!(result[1].label, "create_edges_2");
-        assert_eq!(result[1].created_by, "test");
+        }
+        let moved = crate::api::create_edge(
+            &mut conn,
+            &supplies(globex.id, corp.id, serde_json::json!({})),
+        )
+        .await
+        .unwrap();
+        let dropped =
+            crate::api::create_edge(&mut conn, &supplies(corp.id, ibm.id, serde_json::json!({})))
+                .await
+                .unwrap();
+
+        let merge = |duplicates| crate::dto::MergeVertices {
+            survivor_id: ibm.id,
+            duplicate_ids: duplicates,
+            property_merge: Default::default(),
+            merged_by: "test".to_string(),
+        };
+        let result = crate::api::merge_vertices(&mut conn, &merge(vec![ibm.id])).await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+
+        let record = crate::api::merge_vertices(&mut conn, &merge(vec![corp.id, dotted.id]))
+            .await
+            .unwrap();
+        assert_eq!(
+            (
+                record.repointed_edges,
+                record.merged_edges,
+                record.dropped_edges
+            ),
+            (1, 1, 1)
+        );
+        assert_eq!(record.dropped_edge_ids, [dropped.id]);
+
+        // The edge between the merged vertices is in the trash, as a
+        // self-loop of the survivor.
+        let trashed = {
+            use crate::schema::edge;
+            edge::table
+                .filter(edge::id.eq(dropped.id))
+                .filter(edge::deleted_at.is_not_null())
+                .select((edge::from_vertex_id, edge::to_vertex_id, edge::deleted_by))
+                .get_result::<(i32, i32, Option<String>)>(&mut conn)
+                .await
+                .unwrap()
+        };
+        assert_eq!(trashed, (ibm.id, ibm.id, Some("test".to_string())));
+
+        // The colliding edge was folded into the survivor's, which keeps its
+        // own values and gains the more confident assertion.
+        let query = EdgeQuery {
+            direction: Direction::Both,
+            label: Some("mergesupplies".to_string()),
+            ..Default::default()
+        };
+        let mut merged_edges = crate::api::get_incident_edges(&mut conn, ibm.id, &query)
+            .await
+            .unwrap();
+        merged_edges.sort_by_key(|incident| incident.id);
+        assert_eq!(merged_edges.len(), 2);
+        let repointed = merged_edges.pop().unwrap();
+        let merged = merged_edges.pop().unwrap();
+        assert_eq!(merged.id, kept.id);
+        assert_eq!(
+            merged.properties,
+            serde_json::json!({ "since": 1990, "volume": 1, "region": "eu" })
+        );
+        assert_eq!(repointed.id, moved.id);
+        assert_eq!(
+            (repointed.from_vertex_id, repointed.to_vertex_id),
+            (globex.id, ibm.id)
+        );
+        assert_eq!(repointed.to_vertex_type, ibm.type_);
+        let assertions = crate::api::get_edge_assertions(&mut conn, kept.id)
+            .await
+            .unwrap();
+        let sources = assertions
+            .iter()
+            .map(|claim| (claim.source.as_str(), claim.confidence))
+            .collect::<Vec<_>>();
+        assert_eq!(sources, [("merge/a", 0.8), ("merge/b", 0.3)]);
+
+        // The duplicates live on as names of the survivor.
+        for alternate in ["merge_i.b.m.", "merge_ibm_corp", "merge_big_blue"] {
+            let result = crate::api::find_vertices_by_name(&mut conn, alternate, Some("merge_org"))
+                .await
+                .unwrap();
+            let found_ids = result.iter().map(|found| found.id).collect::<Vec<_>>();
+            assert_eq!(found_ids, vec![ibm.id], "{alternate}");
+        }
+        let survivor = crate::api::get_vertex_by_id(&mut conn, ibm.id, None)
+            .await
+            .unwrap();
+        assert!(survivor.has_label("merge_listed"));
+        let result = crate::api::get_vertex_by_id(&mut conn, dotted.id, None).await;
+        assert!(matches!(
+            result,
+            Err(crate::error::Error::Database(
+                diesel::result::Error::NotFound
+            ))
+        ));
+
+        let result = crate::api::get_vertex_merges(&mut conn, dotted.id)
+            .await
+            .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, record.id);
+        let duplicates =
+            serde_json::from_value::<Vec<crate::model::Vertex>>(result[0].duplicates.clone())
+                .unwrap();
+        let duplicate_names = duplicates
+            .iter()
+            .map(|duplicate| duplicate.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(duplicate_names, ["merge_i.b.m.", "merge_ibm_corp"]);
+
+        // The dropped edge can only come back where its label allows
+        // self-loops.
+        let result = crate::api::restore_edge_by_id(&mut conn, dropped.id, "test").await;
+        assert!(matches!(result, Err(crate::error::Error::Validation(_))));
+        let new_setting = crate::dto::NewEdgeLabelSetting {
+            label: "mergesupplies".to_string(),
+            delete_policy: Default::default(),
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops: true,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        crate::api::save_edge_label_setting(&mut conn, &new_setting)
+            .await
+            .unwrap();
+        let result = crate::api::restore_edge_by_id(&mut conn, dropped.id, "test")
+            .await
+            .unwrap();
+        assert_eq!(result, 1);
+        let restored = {
+            use crate::schema::edge;
+            edge::table
+                .filter(edge::id.eq(dropped.id))
+                .filter(edge::deleted_at.is_null())
+                .select((edge::from_vertex_id, edge::to_vertex_id))
+                .get_result::<(i32, i32)>(&mut conn)
+                .await
+                .unwrap()
+        };
+        assert_eq!(restored, (ibm.id, ibm.id));
     }
 }
@@ -0,0 +1,399 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use futures_util::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_postgres::AsyncMessage;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+use crate::constant::CHANGE_CHANNEL;
+use crate::error::Error;
+
+const SUBSCRIPTION_BUFFER: usize = 1024;
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeEntity {
+    Vertex,
+    Edge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A change to a vertex or an edge, as recorded in the outbox by the
+/// `publish_change` trigger. Vertex events carry `type`; edge events carry
+/// the label and both endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Id of the outbox row the event was recorded in.
+    pub event_id: i64,
+    pub entity: ChangeEntity,
+    pub operation: ChangeOperation,
+    pub id: i32,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    pub label: Option<String>,
+    pub from_vertex_id: Option<i32>,
+    pub from_vertex_type: Option<String>,
+    pub to_vertex_id: Option<i32>,
+    pub to_vertex_type: Option<String>,
+    pub version: i32,
+    pub actor: String,
+    pub changed_at: NaiveDateTime,
+}
+
+/// Narrows a subscription down to the events of interest. Empty fields match
+/// everything.
+///
+/// `vertex_ids` and `types` match a vertex itself and either endpoint of an
+/// edge, so filtering on a vertex id follows its neighborhood; `labels` only
+/// constrains edge events.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeFilter {
+    pub entity: Option<ChangeEntity>,
+    #[serde(default)]
+    pub vertex_ids: Vec<i32>,
+    #[serde(default)]
+    pub types: Vec<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+impl ChangeFilter {
+    pub fn matches(&self, event: &ChangeEvent) -> bool {
+        if self.entity.is_some_and(|entity| entity != event.entity) {
+            return false;
+        }
+
+        let id_matches = match event.entity {
+            ChangeEntity::Vertex => self.vertex_ids.contains(&event.id),
+            ChangeEntity::Edge => [event.from_vertex_id, event.to_vertex_id]
+                .iter()
+                .flatten()
+                .any(|id| self.vertex_ids.contains(id)),
+        };
+        if !self.vertex_ids.is_empty() && !id_matches {
+            return false;
+        }
+
+        let type_matches = |type_: &Option<String>| {
+            type_
+                .as_ref()
+                .is_some_and(|type_| self.types.contains(type_))
+        };
+        if !self.types.is_empty()
+            && !type_matches(&event.type_)
+            && !type_matches(&event.from_vertex_type)
+            && !type_matches(&event.to_vertex_type)
+        {
+            return false;
+        }
+
+        match (&event.entity, &event.label) {
+            (ChangeEntity::Edge, Some(label)) => {
+                self.labels.is_empty() || self.labels.contains(label)
+            }
+            _ => true,
+        }
+    }
+}
+
+/// A live stream of [`ChangeEvent`]s. Dropping it stops listening.
+///
+/// When the connection is lost the stream yields the error, reconnects with
+/// exponential backoff and carries on. Changes made while disconnected are
+/// not replayed.
+pub struct Subscription {
+    receiver: mpsc::Receiver<Result<ChangeEvent, Error>>,
+}
+
+impl Stream for Subscription {
+    type Item = Result<ChangeEvent, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+/// Subscribes to the changes made to vertices and edges that match `filter`.
+///
+/// The `sslmode` of `database_url` decides whether the connection uses TLS:
+/// never with `disable`, when the server offers it with `prefer`, the
+/// default, and always with `require`. Server certificates are verified
+/// against the system's root certificates.
+pub fn subscribe(database_url: &str, filter: ChangeFilter) -> Subscription {
+    let (sender, receiver) = mpsc::channel(SUBSCRIPTION_BUFFER);
+    tokio::spawn(run_subscription(database_url.to_string(), filter, sender));
+
+    Subscription { receiver }
+}
+
+async fn run_subscription(
+    database_url: String,
+    filter: ChangeFilter,
+    sender: mpsc::Sender<Result<ChangeEvent, Error>>,
+) {
+    let tls = match tls_connector() {
+        Ok(tls) => tls,
+        Err(e) => {
+            let _ = sender.send(Err(e)).await;
+            return;
+        }
+    };
+
+    let mut reconnect_delay = INITIAL_RECONNECT_DELAY;
+    loop {
+        match listen(&database_url, &tls, &filter, &sender, &mut reconnect_delay).await {
+            Ok(()) => return,
+            Err(e) => {
+                if sender.send(Err(e)).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        tokio::time::sleep(reconnect_delay).await;
+        reconnect_delay = (reconnect_delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+fn tls_connector() -> Result<MakeRustlsConnect, Error> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(std::io::Error::other)?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+
+    Ok(MakeRustlsConnect::new(config))
+}
+
+/// Forwards notifications until the connection fails (an error) or the
+/// subscriber goes away (`Ok`).
+async fn listen(
+    database_url: &str,
+    tls: &MakeRustlsConnect,
+    filter: &ChangeFilter,
+    sender: &mpsc::Sender<Result<ChangeEvent, Error>>,
+    reconnect_delay: &mut Duration,
+) -> Result<(), Error> {
+    let (client, mut connection) = tokio_postgres::connect(database_url, tls.clone()).await?;
+
+    // The connection has to be polled for `LISTEN` to complete, so it is
+    // driven by its own task that hands the notifications over.
+    let (notifications, mut received) = mpsc::unbounded_channel();
+    let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+    let connection = tokio::spawn(async move {
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if notifications.send(Ok(notification)).is_err() {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let _ = notifications.send(Err(e));
+                    return;
+                }
+            }
+        }
+    });
+
+    let result = async {
+        client
+            .batch_execute(&format!("LISTEN {CHANGE_CHANNEL}"))
+            .await?;
+        *reconnect_delay = INITIAL_RECONNECT_DELAY;
+
+        loop {
+            let notification = tokio::select! {
+                notification = received.recv() => notification,
+                _ = sender.closed() => return Ok(()),
+            };
+            let Some(notification) = notification else {
+                break;
+            };
+
+            let event = match serde_json::from_str::<ChangeEvent>(notification?.payload()) {
+                Ok(event) => event,
+                Err(e) => {
+                    if sender.send(Err(Error::Other(e.into()))).await.is_err() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            };
+            if filter.matches(&event) && sender.send(Ok(event)).await.is_err() {
+                return Ok(());
+            }
+        }
+
+        Err(Error::Other(std::io::Error::new(
+            std::io::ErrorKind::ConnectionAborted,
+            "change notification connection closed",
+        )))
+    }
+    .await;
+
+    connection.abort();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use crate::change::{ChangeEntity, ChangeEvent, ChangeFilter, ChangeOperation, Subscription};
+    use crate::dto::NewVertex;
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+    use futures_util::StreamExt;
+
+    /// The next event other than a probe's.
+    async fn next_event(subscription: &mut Subscription) -> ChangeEvent {
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            if event.type_.as_deref() != Some("subscribe_probe") {
+                return event;
+            }
+        }
+    }
+
+    fn event(entity: ChangeEntity, id: i32, endpoints: Option<(i32, i32)>) -> ChangeEvent {
+        ChangeEvent {
+            event_id: 1,
+            entity,
+            operation: ChangeOperation::Insert,
+            id,
+            type_: None,
+            label: None,
+            from_vertex_id: endpoints.map(|(from, _)| from),
+            from_vertex_type: None,
+            to_vertex_id: endpoints.map(|(_, to)| to),
+            to_vertex_type: None,
+            version: 1,
+            actor: "test".to_string(),
+            changed_at: chrono::DateTime::UNIX_EPOCH.naive_utc(),
+        }
+    }
+
+    #[test]
+    fn test_filter_vertex_ids() {
+        let filter = ChangeFilter {
+            vertex_ids: vec![1, 2],
+            ..Default::default()
+        };
+
+        assert!(filter.matches(&event(ChangeEntity::Vertex, 1, None)));
+        assert!(filter.matches(&event(ChangeEntity::Vertex, 2, None)));
+        assert!(!filter.matches(&event(ChangeEntity::Vertex, 3, None)));
+
+        // An edge matches through either endpoint, never through its own id.
+        assert!(filter.matches(&event(ChangeEntity::Edge, 3, Some((1, 3)))));
+        assert!(filter.matches(&event(ChangeEntity::Edge, 3, Some((3, 2)))));
+        assert!(!filter.matches(&event(ChangeEntity::Edge, 1, Some((3, 4)))));
+
+        let filter = ChangeFilter {
+            entity: Some(ChangeEntity::Edge),
+            ..filter
+        };
+        assert!(!filter.matches(&event(ChangeEntity::Vertex, 1, None)));
+        assert!(filter.matches(&event(ChangeEntity::Edge, 3, Some((1, 3)))));
+
+        assert!(ChangeFilter::default().matches(&event(ChangeEntity::Vertex, 3, None)));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let filter = ChangeFilter {
+            types: vec!["subscribe".to_string(), "subscribe_probe".to_string()],
+            ..Default::default()
+        };
+        let mut subscription = crate::change::subscribe(&database_url, filter);
+
+        // Changes made before the subscription listens are not seen, so probe
+        // until one comes through.
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let listening = async {
+            for probe in 0.. {
+                let probe_vertex = NewVertex {
+                    name: format!("subscribe_probe_{probe}"),
+                    type_: "subscribe_probe".to_string(),
+                    created_by: "test".to_string(),
+                    labels: Vec::new(),
+                };
+                crate::api::create_vertex(&mut conn, &probe_vertex)
+                    .await
+                    .unwrap();
+                let probed =
+                    tokio::time::timeout(Duration::from_millis(100), subscription.next()).await;
+                if probed.is_ok() {
+                    break;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), listening)
+            .await
+            .unwrap();
+
+        let ignored_vertex = NewVertex {
+            name: "subscribe_ignored".to_string(),
+            type_: "subscribe_ignored".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+        crate::api::create_vertex(&mut conn, &ignored_vertex)
+            .await
+            .unwrap();
+
+        let new_vertex = NewVertex {
+            name: "subscribe".to_string(),
+            type_: "subscribe".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+        crate::api::soft_delete_vertex_by_id(&mut conn, new_vertex.id, None, "remover")
+            .await
+            .unwrap();
+
+        let event = next_event(&mut subscription).await;
+        assert_eq!(event.entity, ChangeEntity::Vertex);
+        assert_eq!(event.operation, ChangeOperation::Insert);
+        assert_eq!(event.id, new_vertex.id);
+        assert_eq!(event.type_.as_deref(), Some("subscribe"));
+        assert_eq!(event.actor, "test");
+
+        let event = next_event(&mut subscription).await;
+        assert_eq!(event.operation, ChangeOperation::Delete);
+        assert_eq!(event.id, new_vertex.id);
+        assert_eq!(event.actor, "remover");
+        assert_eq!(event.version, 2);
+    }
+}
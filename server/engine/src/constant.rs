@@ -1,4 +1,10 @@
+pub const MIN_IDENTIFIER_LENGTH: usize = 3;
 pub const MAX_NAME_LENGTH: usize = 255;
 pub const MAX_TYPE_LENGTH: usize = 255;
 pub const MAX_USERNAME_LENGTH: usize = 255;
-pub const MAX_EDGE_LABEL_LENGTH: usize = 255;
\ No newline at end of file
+pub const MAX_EDGE_LABEL_LENGTH: usize = 255;
+pub const MAX_SOURCE_LENGTH: usize = 255;
+pub const HISTORY_ACTOR_SETTING: &str = "broccoli.actor";
+pub const CHANGE_CHANNEL: &str = "broccoli_changes";
+pub const MAX_PATH_LENGTH: u32 = 10;
+pub const DATALOG_ACTOR: &str = "datalog";
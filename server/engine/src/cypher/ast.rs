@@ -0,0 +1,131 @@
+use crate::dto::Direction;
+
+/// `MATCH ... [WHERE ...] RETURN ... [ORDER BY ...] [SKIP n] [LIMIT n]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    pub patterns: Vec<Pattern>,
+    pub predicate: Option<Expr>,
+    pub distinct: bool,
+    pub items: Vec<ReturnItem>,
+    pub order_by: Vec<OrderItem>,
+    pub skip: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+/// A chain of nodes connected by relationships, `(a)-[:knows]->(b)<-[]-(c)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub start: NodePattern,
+    pub steps: Vec<(RelationshipPattern, NodePattern)>,
+}
+
+/// `(variable:Type {key: value})`, every part optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodePattern {
+    pub variable: Option<String>,
+    pub type_: Option<String>,
+    pub properties: Vec<(String, Operand)>,
+}
+
+/// `-[variable:label|other*min..max {key: value}]->`. The direction is seen
+/// from the node to the left of the relationship.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationshipPattern {
+    pub variable: Option<String>,
+    pub labels: Vec<String>,
+    pub direction: Direction,
+    pub length: Option<Length>,
+    pub properties: Vec<(String, Operand)>,
+}
+
+/// Hop bounds of a variable-length relationship. A missing `max` means
+/// unbounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Length {
+    pub min: u32,
+    pub max: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Comparison(Operand, ComparisonOperator, Operand),
+    IsNull { operand: Operand, negated: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    StartsWith,
+    EndsWith,
+    Contains,
+    In,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Property(String, String),
+    Literal(Literal),
+    Parameter(String),
+}
+
+/// A constant in a query, or the value of a query parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    List(Vec<Literal>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnItem {
+    pub expr: ReturnExpr,
+    pub alias: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReturnExpr {
+    Variable(String),
+    Property(String, String),
+    /// `count(*)` when `None`, otherwise `count(variable)`.
+    Count(Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderItem {
+    pub key: OrderKey,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderKey {
+    Property(String, String),
+    /// A bare name, which must be the alias of a return item.
+    Alias(String),
+}
+
+impl ReturnItem {
+    /// The column name of the item in the result.
+    pub fn name(&self) -> String {
+        if let Some(alias) = &self.alias {
+            return alias.clone();
+        }
+
+        match &self.expr {
+            ReturnExpr::Variable(variable) => variable.clone(),
+            ReturnExpr::Property(variable, key) => format!("{variable}.{key}"),
+            ReturnExpr::Count(None) => "count(*)".to_string(),
+            ReturnExpr::Count(Some(variable)) => format!("count({variable})"),
+        }
+    }
+}
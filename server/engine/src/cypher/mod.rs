@@ -0,0 +1,330 @@
+//! A practical subset of Cypher over the vertex and edge tables:
+//!
+//! ```text
+//! MATCH (a:Person {name: 'x'})-[:knows|likes*1..3]->(b:Person), (b)<-[r]-(c)
+//! WHERE a.created_at > '2026-01-01' AND NOT c.name STARTS WITH $prefix
+//! RETURN DISTINCT b, r.label AS label, count(c)
+//! ORDER BY label DESC SKIP 10 LIMIT 10
+//! ```
+//!
+//! Node labels match any label of the vertex, its type included, and
+//! relationship types the edge label.
+//! Each query is planned onto a single SQL statement; values are always
+//! bound, never spliced into the SQL. Variable-length paths do not revisit
+//! vertices and are capped at [`MAX_PATH_LENGTH`](crate::constant::MAX_PATH_LENGTH)
+//! hops.
+
+pub mod ast;
+mod parser;
+mod planner;
+
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use diesel::pg::Pg;
+use diesel::sql_types::{Array, BigInt, Bool, Double, Jsonb, Text};
+use diesel::QueryableByName;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use ast::Literal;
+pub use parser::parse;
+
+use crate::{
+    error::Error,
+    model::{Edge, Vertex},
+};
+use planner::{Bind, ColumnType};
+
+/// A value in a result row, typed by what was returned: a whole vertex or
+/// edge, or one of its properties.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    DateTime(NaiveDateTime),
+    Vertex(Vertex),
+    Edge(Edge),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(QueryableByName)]
+struct Row {
+    #[diesel(sql_type = Jsonb)]
+    row: serde_json::Value,
+}
+
+/// Runs a query against the live graph. `parameters` supply the values of
+/// `$name` placeholders.
+pub async fn execute(
+    conn: &mut AsyncPgConnection,
+    text: &str,
+    parameters: &HashMap<String, Literal>,
+) -> Result<QueryResult, Error> {
+    let query = parse(text)?;
+    let plan = planner::plan(&query, parameters)?;
+
+    let mut statement = diesel::sql_query(plan.sql).into_boxed::<Pg>();
+    for bind in plan.binds {
+        statement = match bind {
+            Bind::Text(value) => statement.bind::<Text, _>(value),
+            Bind::BigInt(value) => statement.bind::<BigInt, _>(value),
+            Bind::Double(value) => statement.bind::<Double, _>(value),
+            Bind::Boolean(value) => statement.bind::<Bool, _>(value),
+            Bind::TextArray(values) => statement.bind::<Array<Text>, _>(values),
+            Bind::BigIntArray(values) => statement.bind::<Array<BigInt>, _>(values),
+            Bind::DoubleArray(values) => statement.bind::<Array<Double>, _>(values),
+        };
+    }
+
+    let rows = statement
+        .load::<Row>(conn)
+        .await?
+        .into_iter()
+        .map(|row| {
+            let serde_json::Value::Array(values) = row.row else {
+                return Err(Error::Other(std::io::Error::other("malformed row")));
+            };
+            values
+                .into_iter()
+                .zip(&plan.columns)
+                .map(|(value, (_, type_))| to_value(value, *type_))
+                .collect()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(QueryResult {
+        columns: plan.columns.into_iter().map(|(name, _)| name).collect(),
+        rows,
+    })
+}
+
+fn to_value(value: serde_json::Value, type_: ColumnType) -> Result<Value, Error> {
+    Ok(match type_ {
+        ColumnType::Integer => Value::Integer(from_json(value)?),
+        ColumnType::Float => Value::Float(from_json(value)?),
+        ColumnType::Text => Value::String(from_json(value)?),
+        ColumnType::Timestamp => Value::DateTime(from_json(value)?),
+        ColumnType::Vertex => Value::Vertex(from_json(value)?),
+        ColumnType::Edge => Value::Edge(from_json(value)?),
+    })
+}
+
+fn from_json<T: DeserializeOwned>(value: serde_json::Value) -> Result<T, Error> {
+    serde_json::from_value(value).map_err(|e| Error::Other(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+
+    use crate::cypher::{Literal, Value};
+    use crate::dto::{NewEdge, NewVertex};
+    use crate::error::Error;
+
+    #[tokio::test]
+    async fn test_execute() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for (name, type_, labels) in [
+            ("cypher_alice", "cypher_person", vec!["cypher_author"]),
+            ("cypher_bob", "cypher_person", vec![]),
+            ("cypher_carol", "cypher_person", vec!["cypher_author"]),
+            ("cypher_dave", "cypher_person", vec![]),
+            ("cypher_acme", "cypher_company", vec![]),
+        ] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: type_.to_string(),
+                created_by: "test".to_string(),
+                labels: labels.into_iter().map(str::to_string).collect(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // alice -> bob -> carol -> dave, and everyone but dave works at acme.
+        for (from, to, label, weight) in [
+            (0, 1, "cypher_knows", 1.0),
+            (1, 2, "cypher_knows", 1.0),
+            (2, 3, "cypher_knows", 1.0),
+            (0, 4, "cypher_works_at", 0.9),
+            (1, 4, "cypher_works_at", 0.5),
+            (2, 4, "cypher_works_at", 0.8),
+        ] {
+            let new_edge = NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: label.to_string(),
+                created_by: "test".to_string(),
+                weight,
+                ..Default::default()
+            };
+            crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        }
+
+        let no_parameters = HashMap::new();
+
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (a:cypher_person)-[:cypher_knows]->(b:cypher_person) \
+             WHERE a.name = 'cypher_alice' RETURN b",
+            &no_parameters,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.columns, vec!["b"]);
+        assert_eq!(result.rows.len(), 1);
+        let Value::Vertex(vertex) = &result.rows[0][0] else {
+            panic!("expected a vertex, got {:?}", result.rows[0][0]);
+        };
+        assert_eq!(vertex.id, vertices[1].id);
+
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (p)-[r:cypher_works_at]->(:cypher_company) \
+             RETURN p.name AS name, r ORDER BY name DESC LIMIT 2",
+            &no_parameters,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.columns, vec!["name", "r"]);
+        let names = result
+            .rows
+            .iter()
+            .map(|row| match &row[0] {
+                Value::String(name) => name.as_str(),
+                value => panic!("expected a string, got {value:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["cypher_carol", "cypher_bob"]);
+        assert!(
+            matches!(&result.rows[0][1], Value::Edge(edge) if edge.to_vertex_id == vertices[4].id)
+        );
+
+        let parameters = HashMap::from([(
+            "name".to_string(),
+            Literal::String("cypher_alice".to_string()),
+        )]);
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (a {name: $name})-[:cypher_knows*2..3]->(b) RETURN b.name ORDER BY b.name",
+            &parameters,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.columns, vec!["b.name"]);
+        let names = result
+            .rows
+            .iter()
+            .map(|row| match &row[0] {
+                Value::String(name) => name.as_str(),
+                value => panic!("expected a string, got {value:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["cypher_carol", "cypher_dave"]);
+
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (p)-[r:cypher_works_at]->(c) WHERE r.weight > 0.75 \
+             RETURN p.name, r.weight ORDER BY r.weight",
+            &no_parameters,
+        )
+        .await
+        .unwrap();
+        let weights = result
+            .rows
+            .iter()
+            .map(|row| match (&row[0], &row[1]) {
+                (Value::String(name), Value::Float(weight)) => (name.as_str(), *weight),
+                value => panic!("expected a name and a weight, got {value:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(weights, vec![("cypher_carol", 0.8), ("cypher_alice", 0.9)]);
+
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (c:cypher_company)-[:cypher_works_at]-(p), (p)-[:cypher_knows]->(q) \
+             WHERE q.name IN ['cypher_bob', 'cypher_carol', 'cypher_dave'] \
+             RETURN c.name, count(q)",
+            &no_parameters,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert!(matches!(&result.rows[0][1], Value::Integer(3)));
+
+        // Secondary labels match like the type does.
+        for (query, expected) in [
+            (
+                "MATCH (a:cypher_author)-[:cypher_knows]->(b:cypher_person) \
+                 RETURN a.name ORDER BY a.name",
+                vec!["cypher_alice", "cypher_carol"],
+            ),
+            (
+                "MATCH (a:cypher_author)-[:cypher_knows*2]->(b) RETURN b.name",
+                vec!["cypher_carol"],
+            ),
+        ] {
+            let result = crate::cypher::execute(&mut conn, query, &no_parameters)
+                .await
+                .unwrap();
+            let names = result
+                .rows
+                .iter()
+                .map(|row| match &row[0] {
+                    Value::String(name) => name.as_str(),
+                    value => panic!("expected a string, got {value:?}"),
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(names, expected, "{query}");
+        }
+
+        // Soft-deleted edges are not matched.
+        crate::api::soft_delete_vertex_by_id(&mut conn, vertices[1].id, None, "test")
+            .await
+            .unwrap();
+        let result = crate::cypher::execute(
+            &mut conn,
+            "MATCH (a {name: 'cypher_alice'})-[:cypher_knows*]->(b) RETURN b",
+            &no_parameters,
+        )
+        .await
+        .unwrap();
+        assert!(result.rows.is_empty());
+
+        for invalid in [
+            "MATCH (a) RETURN b",
+            "MATCH (a) RETURN a.weight",
+            "MATCH (a) WHERE a.id = 'x' RETURN a",
+            "MATCH (a) WHERE a.name = $missing RETURN a",
+            "MATCH (a)-[r*]->(b) RETURN a",
+            "MATCH (a) RETURN DISTINCT a.name ORDER BY a.id",
+        ] {
+            assert!(
+                matches!(
+                    crate::cypher::execute(&mut conn, invalid, &no_parameters).await,
+                    Err(Error::Query(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,751 @@
+use crate::cypher::ast::{
+    ComparisonOperator, Expr, Length, Literal, NodePattern, Operand, OrderItem, OrderKey, Pattern,
+    Query, RelationshipPattern, ReturnExpr, ReturnItem,
+};
+use crate::dto::Direction;
+use crate::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Backtick-quoted identifiers are never keywords.
+    Identifier {
+        name: String,
+        quoted: bool,
+    },
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Parameter(String),
+    LeftParen,
+    RightParen,
+    LeftBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Colon,
+    Comma,
+    Dot,
+    DotDot,
+    Pipe,
+    Star,
+    Dash,
+    RightArrow,
+    LeftArrow,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Parses a query in the supported Cypher subset.
+pub fn parse(text: &str) -> Result<Query, Error> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        position: 0,
+    };
+    let query = parser.query()?;
+    if let Some(token) = parser.peek() {
+        return Err(unexpected(token));
+    }
+
+    Ok(query)
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Identifier {
+                name: chars[start..i].iter().collect(),
+                quoted: false,
+            });
+            continue;
+        }
+
+        if c == '`' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|c| *c == '`')
+                .ok_or_else(|| query_error("unterminated quoted identifier"))?;
+            tokens.push(Token::Identifier {
+                name: chars[i + 1..i + 1 + end].iter().collect(),
+                quoted: true,
+            });
+            i += end + 2;
+            continue;
+        }
+
+        if c == '$' {
+            let start = i + 1;
+            i = start;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            if i == start {
+                return Err(query_error("expected a parameter name after '$'"));
+            }
+            tokens.push(Token::Parameter(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            // `1..3` is a range, not a float.
+            let is_float = i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit();
+            if is_float {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let number = chars[start..i].iter().collect::<String>();
+            tokens.push(if is_float {
+                Token::Float(number.parse().map_err(|_| invalid_number(&number))?)
+            } else {
+                Token::Integer(number.parse().map_err(|_| invalid_number(&number))?)
+            });
+            continue;
+        }
+
+        if c == '\'' || c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(query_error("unterminated string literal")),
+                    Some(quote) if *quote == c => break,
+                    Some('\\') => {
+                        value.push(match chars.get(i + 1) {
+                            Some('n') => '\n',
+                            Some('t') => '\t',
+                            Some('r') => '\r',
+                            Some(escaped) => *escaped,
+                            None => return Err(query_error("unterminated string literal")),
+                        });
+                        i += 2;
+                    }
+                    Some(other) => {
+                        value.push(*other);
+                        i += 1;
+                    }
+                }
+            }
+            tokens.push(Token::String(value));
+            i += 1;
+            continue;
+        }
+
+        let (token, width) = match (c, next) {
+            ('-', Some('>')) => (Token::RightArrow, 2),
+            ('<', Some('-')) => (Token::LeftArrow, 2),
+            ('<', Some('>')) => (Token::Ne, 2),
+            ('<', Some('=')) => (Token::Le, 2),
+            ('>', Some('=')) => (Token::Ge, 2),
+            ('.', Some('.')) => (Token::DotDot, 2),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            ('[', _) => (Token::LeftBracket, 1),
+            (']', _) => (Token::RightBracket, 1),
+            ('{', _) => (Token::LeftBrace, 1),
+            ('}', _) => (Token::RightBrace, 1),
+            (':', _) => (Token::Colon, 1),
+            (',', _) => (Token::Comma, 1),
+            ('.', _) => (Token::Dot, 1),
+            ('|', _) => (Token::Pipe, 1),
+            ('*', _) => (Token::Star, 1),
+            ('-', _) => (Token::Dash, 1),
+            ('=', _) => (Token::Eq, 1),
+            ('<', _) => (Token::Lt, 1),
+            ('>', _) => (Token::Gt, 1),
+            _ => return Err(query_error(&format!("unexpected character '{c}'"))),
+        };
+        tokens.push(token);
+        i += width;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn query(&mut self) -> Result<Query, Error> {
+        self.expect_keyword("MATCH")?;
+        let mut patterns = vec![self.pattern()?];
+        while self.eat(&Token::Comma) {
+            patterns.push(self.pattern()?);
+        }
+
+        let predicate = if self.eat_keyword("WHERE") {
+            Some(self.expr()?)
+        } else {
+            None
+        };
+
+        self.expect_keyword("RETURN")?;
+        let distinct = self.eat_keyword("DISTINCT");
+        let mut items = vec![self.return_item()?];
+        while self.eat(&Token::Comma) {
+            items.push(self.return_item()?);
+        }
+
+        let mut order_by = Vec::new();
+        if self.eat_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            order_by.push(self.order_item()?);
+            while self.eat(&Token::Comma) {
+                order_by.push(self.order_item()?);
+            }
+        }
+
+        let skip = if self.eat_keyword("SKIP") {
+            Some(self.count()?)
+        } else {
+            None
+        };
+        let limit = if self.eat_keyword("LIMIT") {
+            Some(self.count()?)
+        } else {
+            None
+        };
+
+        Ok(Query {
+            patterns,
+            predicate,
+            distinct,
+            items,
+            order_by,
+            skip,
+            limit,
+        })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, Error> {
+        let start = self.node()?;
+        let mut steps = Vec::new();
+        while matches!(self.peek(), Some(Token::Dash | Token::LeftArrow)) {
+            let relationship = self.relationship()?;
+            steps.push((relationship, self.node()?));
+        }
+
+        Ok(Pattern { start, steps })
+    }
+
+    fn node(&mut self) -> Result<NodePattern, Error> {
+        self.expect(&Token::LeftParen)?;
+        let variable = self.optional_identifier();
+        let type_ = if self.eat(&Token::Colon) {
+            Some(self.identifier()?)
+        } else {
+            None
+        };
+        let properties = self.properties()?;
+        self.expect(&Token::RightParen)?;
+
+        Ok(NodePattern {
+            variable,
+            type_,
+            properties,
+        })
+    }
+
+    fn relationship(&mut self) -> Result<RelationshipPattern, Error> {
+        let incoming = self.eat(&Token::LeftArrow);
+        if !incoming {
+            self.expect(&Token::Dash)?;
+        }
+
+        let mut relationship = RelationshipPattern {
+            variable: None,
+            labels: Vec::new(),
+            direction: Direction::Both,
+            length: None,
+            properties: Vec::new(),
+        };
+
+        if self.eat(&Token::LeftBracket) {
+            relationship.variable = self.optional_identifier();
+            if self.eat(&Token::Colon) {
+                relationship.labels.push(self.identifier()?);
+                while self.eat(&Token::Pipe) {
+                    self.eat(&Token::Colon);
+                    relationship.labels.push(self.identifier()?);
+                }
+            }
+            if self.eat(&Token::Star) {
+                relationship.length = Some(self.length()?);
+            }
+            relationship.properties = self.properties()?;
+            self.expect(&Token::RightBracket)?;
+        }
+
+        let outgoing = if self.eat(&Token::RightArrow) {
+            true
+        } else {
+            self.expect(&Token::Dash)?;
+            false
+        };
+
+        relationship.direction = match (incoming, outgoing) {
+            (false, true) => Direction::Outgoing,
+            (true, false) => Direction::Incoming,
+            (false, false) => Direction::Both,
+            (true, true) => return Err(query_error("a relationship cannot point both ways")),
+        };
+
+        Ok(relationship)
+    }
+
+    /// What follows the `*` of a variable-length relationship: `*`, `*2`,
+    /// `*1..`, `*..3` or `*1..3`.
+    fn length(&mut self) -> Result<Length, Error> {
+        let min = self.optional_hops()?;
+        if !self.eat(&Token::DotDot) {
+            return Ok(match min {
+                Some(hops) => Length {
+                    min: hops,
+                    max: Some(hops),
+                },
+                None => Length { min: 1, max: None },
+            });
+        }
+
+        Ok(Length {
+            min: min.unwrap_or(1),
+            max: self.optional_hops()?,
+        })
+    }
+
+    fn optional_hops(&mut self) -> Result<Option<u32>, Error> {
+        match self.peek() {
+            Some(Token::Integer(hops)) => {
+                let hops =
+                    u32::try_from(*hops).map_err(|_| query_error("path length out of range"))?;
+                self.position += 1;
+                Ok(Some(hops))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn properties(&mut self) -> Result<Vec<(String, Operand)>, Error> {
+        let mut properties = Vec::new();
+        if !self.eat(&Token::LeftBrace) {
+            return Ok(properties);
+        }
+
+        if self.eat(&Token::RightBrace) {
+            return Ok(properties);
+        }
+
+        loop {
+            let key = self.identifier()?;
+            self.expect(&Token::Colon)?;
+            let value = match self.next()? {
+                Token::Parameter(name) => Operand::Parameter(name),
+                token => Operand::Literal(self.literal(token)?),
+            };
+            properties.push((key, value));
+
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&Token::RightBrace)?;
+
+        Ok(properties)
+    }
+
+    fn expr(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.and_expr()?;
+        while self.eat_keyword("OR") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, Error> {
+        let mut expr = self.not_expr()?;
+        while self.eat_keyword("AND") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not_expr()?));
+        }
+
+        Ok(expr)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, Error> {
+        if self.eat_keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+
+        if self.eat(&Token::LeftParen) {
+            let expr = self.expr()?;
+            self.expect(&Token::RightParen)?;
+            return Ok(expr);
+        }
+
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, Error> {
+        let left = self.operand()?;
+
+        if self.eat_keyword("IS") {
+            let negated = self.eat_keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(Expr::IsNull {
+                operand: left,
+                negated,
+            });
+        }
+
+        let operator = match self.next()? {
+            Token::Eq => ComparisonOperator::Eq,
+            Token::Ne => ComparisonOperator::Ne,
+            Token::Lt => ComparisonOperator::Lt,
+            Token::Le => ComparisonOperator::Le,
+            Token::Gt => ComparisonOperator::Gt,
+            Token::Ge => ComparisonOperator::Ge,
+            token if is_keyword(&token, "IN") => ComparisonOperator::In,
+            token if is_keyword(&token, "CONTAINS") => ComparisonOperator::Contains,
+            token if is_keyword(&token, "STARTS") => {
+                self.expect_keyword("WITH")?;
+                ComparisonOperator::StartsWith
+            }
+            token if is_keyword(&token, "ENDS") => {
+                self.expect_keyword("WITH")?;
+                ComparisonOperator::EndsWith
+            }
+            token => return Err(unexpected(&token)),
+        };
+
+        Ok(Expr::Comparison(left, operator, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<Operand, Error> {
+        match self.next()? {
+            Token::Parameter(name) => Ok(Operand::Parameter(name)),
+            Token::Identifier {
+                name,
+                quoted: false,
+            } if is_literal_keyword(&name) => {
+                Ok(Operand::Literal(self.literal(Token::Identifier {
+                    name,
+                    quoted: false,
+                })?))
+            }
+            Token::Identifier { name, .. } => {
+                self.expect(&Token::Dot)?;
+                Ok(Operand::Property(name, self.identifier()?))
+            }
+            token => Ok(Operand::Literal(self.literal(token)?)),
+        }
+    }
+
+    /// A literal starting with the already consumed `token`.
+    fn literal(&mut self, token: Token) -> Result<Literal, Error> {
+        match token {
+            Token::String(value) => Ok(Literal::String(value)),
+            Token::Integer(value) => Ok(Literal::Integer(value)),
+            Token::Float(value) => Ok(Literal::Float(value)),
+            Token::Dash => match self.next()? {
+                Token::Integer(value) => Ok(Literal::Integer(-value)),
+                Token::Float(value) => Ok(Literal::Float(-value)),
+                token => Err(unexpected(&token)),
+            },
+            Token::LeftBracket => {
+                let mut values = Vec::new();
+                if self.eat(&Token::RightBracket) {
+                    return Ok(Literal::List(values));
+                }
+                loop {
+                    let token = self.next()?;
+                    values.push(self.literal(token)?);
+                    if !self.eat(&Token::Comma) {
+                        break;
+                    }
+                }
+                self.expect(&Token::RightBracket)?;
+                Ok(Literal::List(values))
+            }
+            token if is_keyword(&token, "NULL") => Ok(Literal::Null),
+            token if is_keyword(&token, "TRUE") => Ok(Literal::Boolean(true)),
+            token if is_keyword(&token, "FALSE") => Ok(Literal::Boolean(false)),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    fn return_item(&mut self) -> Result<ReturnItem, Error> {
+        let name = self.identifier()?;
+        let expr = if name.eq_ignore_ascii_case("count") && self.eat(&Token::LeftParen) {
+            let variable = if self.eat(&Token::Star) {
+                None
+            } else {
+                Some(self.identifier()?)
+            };
+            self.expect(&Token::RightParen)?;
+            ReturnExpr::Count(variable)
+        } else if self.eat(&Token::Dot) {
+            ReturnExpr::Property(name, self.identifier()?)
+        } else {
+            ReturnExpr::Variable(name)
+        };
+
+        let alias = if self.eat_keyword("AS") {
+            Some(self.identifier()?)
+        } else {
+            None
+        };
+
+        Ok(ReturnItem { expr, alias })
+    }
+
+    fn order_item(&mut self) -> Result<OrderItem, Error> {
+        let name = self.identifier()?;
+        let key = if self.eat(&Token::Dot) {
+            OrderKey::Property(name, self.identifier()?)
+        } else {
+            OrderKey::Alias(name)
+        };
+
+        let descending = self.eat_keyword("DESC") || self.eat_keyword("DESCENDING");
+        if !descending && !self.eat_keyword("ASC") {
+            self.eat_keyword("ASCENDING");
+        }
+
+        Ok(OrderItem { key, descending })
+    }
+
+    fn count(&mut self) -> Result<u64, Error> {
+        match self.next()? {
+            Token::Integer(value) if value >= 0 => Ok(value as u64),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Identifier { name, .. } => Ok(name),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    /// A variable name, unless the next token belongs to what follows it.
+    fn optional_identifier(&mut self) -> Option<String> {
+        match self.peek() {
+            Some(Token::Identifier { name, .. }) => {
+                let name = name.clone();
+                self.position += 1;
+                Some(name)
+            }
+            _ => None,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| query_error("unexpected end of query"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), Error> {
+        match self.next()? {
+            token if token == *expected => Ok(()),
+            token => Err(unexpected(&token)),
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek().is_some_and(|token| is_keyword(token, keyword)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Error> {
+        match self.next() {
+            Ok(token) if is_keyword(&token, keyword) => Ok(()),
+            Ok(token) => Err(query_error(&format!(
+                "expected {keyword}, found {}",
+                describe(&token)
+            ))),
+            Err(_) => Err(query_error(&format!("expected {keyword}"))),
+        }
+    }
+}
+
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    matches!(token, Token::Identifier { name, quoted: false } if name.eq_ignore_ascii_case(keyword))
+}
+
+fn is_literal_keyword(name: &str) -> bool {
+    ["NULL", "TRUE", "FALSE"]
+        .iter()
+        .any(|keyword| name.eq_ignore_ascii_case(keyword))
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Identifier { name, .. } => format!("'{name}'"),
+        Token::String(value) => format!("string '{value}'"),
+        Token::Integer(value) => value.to_string(),
+        Token::Float(value) => value.to_string(),
+        Token::Parameter(name) => format!("${name}"),
+        token => format!("{token:?}"),
+    }
+}
+
+fn unexpected(token: &Token) -> Error {
+    query_error(&format!("unexpected {}", describe(token)))
+}
+
+fn invalid_number(number: &str) -> Error {
+    query_error(&format!("invalid number {number}"))
+}
+
+pub(crate) fn query_error(message: &str) -> Error {
+    Error::Query(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cypher::ast::{
+        ComparisonOperator, Expr, Length, Literal, Operand, OrderKey, ReturnExpr,
+    };
+    use crate::dto::Direction;
+    use crate::error::Error;
+
+    #[test]
+    fn test_parse() {
+        let query = super::parse(
+            "MATCH (a:Person {name: 'x'})-[r:knows|:likes]->(b:Person)<-[:owns*1..3]-(c) \
+             WHERE a.version >= 1 AND NOT b.name STARTS WITH \"y\" OR c.id IN [1, -2] \
+             RETURN DISTINCT b, c.name AS name, count(*) \
+             ORDER BY name DESC, b.id SKIP 5 LIMIT 10",
+        )
+        .unwrap();
+
+        assert_eq!(query.patterns.len(), 1);
+        let pattern = &query.patterns[0];
+        assert_eq!(pattern.start.variable.as_deref(), Some("a"));
+        assert_eq!(pattern.start.type_.as_deref(), Some("Person"));
+        assert_eq!(
+            pattern.start.properties,
+            vec![(
+                "name".to_string(),
+                Operand::Literal(Literal::String("x".to_string()))
+            )]
+        );
+        assert_eq!(pattern.steps.len(), 2);
+        assert_eq!(pattern.steps[0].0.variable.as_deref(), Some("r"));
+        assert_eq!(pattern.steps[0].0.labels, vec!["knows", "likes"]);
+        assert_eq!(pattern.steps[0].0.direction, Direction::Outgoing);
+        assert_eq!(pattern.steps[0].0.length, None);
+        assert_eq!(pattern.steps[1].0.direction, Direction::Incoming);
+        assert_eq!(
+            pattern.steps[1].0.length,
+            Some(Length {
+                min: 1,
+                max: Some(3)
+            })
+        );
+        assert_eq!(pattern.steps[1].1.variable.as_deref(), Some("c"));
+
+        let Some(Expr::Or(left, right)) = &query.predicate else {
+            panic!("expected OR, got {:?}", query.predicate);
+        };
+        assert!(matches!(**left, Expr::And(_, _)));
+        assert_eq!(
+            **right,
+            Expr::Comparison(
+                Operand::Property("c".to_string(), "id".to_string()),
+                ComparisonOperator::In,
+                Operand::Literal(Literal::List(vec![
+                    Literal::Integer(1),
+                    Literal::Integer(-2)
+                ])),
+            )
+        );
+
+        assert!(query.distinct);
+        assert_eq!(query.items.len(), 3);
+        assert_eq!(query.items[0].expr, ReturnExpr::Variable("b".to_string()));
+        assert_eq!(query.items[1].name(), "name");
+        assert_eq!(query.items[2].expr, ReturnExpr::Count(None));
+        assert_eq!(query.order_by.len(), 2);
+        assert_eq!(query.order_by[0].key, OrderKey::Alias("name".to_string()));
+        assert!(query.order_by[0].descending);
+        assert!(!query.order_by[1].descending);
+        assert_eq!(query.skip, Some(5));
+        assert_eq!(query.limit, Some(10));
+
+        let query = super::parse("match (a)--(b)-[*]-(c) return a").unwrap();
+        let steps = &query.patterns[0].steps;
+        assert_eq!(steps[0].0.direction, Direction::Both);
+        assert_eq!(steps[1].0.length, Some(Length { min: 1, max: None }));
+
+        for invalid in [
+            "RETURN a",
+            "MATCH (a RETURN a",
+            "MATCH (a)<-[]->(b) RETURN a",
+            "MATCH (a) WHERE a.name = 'x RETURN a",
+            "MATCH (a) RETURN a LIMIT -1",
+            "MATCH (a) RETURN a extra",
+        ] {
+            assert!(
+                matches!(super::parse(invalid), Err(Error::Query(_))),
+                "{invalid}"
+            );
+        }
+    }
+}
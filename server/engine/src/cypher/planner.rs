@@ -0,0 +1,789 @@
+use std::collections::HashMap;
+
+use crate::constant::MAX_PATH_LENGTH;
+use crate::cypher::ast::{
+    ComparisonOperator, Expr, Length, Literal, NodePattern, Operand, OrderKey, Pattern, Query,
+    RelationshipPattern, ReturnExpr, ReturnItem,
+};
+use crate::cypher::parser::query_error;
+use crate::dto::Direction;
+use crate::error::Error;
+
+/// The SQL type of a property, or of a returned column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ColumnType {
+    Integer,
+    Float,
+    Text,
+    Timestamp,
+    Vertex,
+    Edge,
+}
+
+/// A value bound to a `$n` placeholder of the planned statement.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Bind {
+    Text(String),
+    BigInt(i64),
+    Double(f64),
+    Boolean(bool),
+    TextArray(Vec<String>),
+    BigIntArray(Vec<i64>),
+    DoubleArray(Vec<f64>),
+}
+
+/// One SQL statement selecting a single JSON array per result row, holding
+/// the values of `columns` in order.
+#[derive(Debug)]
+pub(crate) struct Plan {
+    pub sql: String,
+    pub binds: Vec<Bind>,
+    pub columns: Vec<(String, ColumnType)>,
+}
+
+// Soft-deleted columns are left out: matched rows are always live.
+const VERTEX_COLUMNS: &[(&str, ColumnType)] = &[
+    ("id", ColumnType::Integer),
+    ("name", ColumnType::Text),
+    ("type", ColumnType::Text),
+    ("created_by", ColumnType::Text),
+    ("created_at", ColumnType::Timestamp),
+    ("updated_by", ColumnType::Text),
+    ("updated_at", ColumnType::Timestamp),
+    ("version", ColumnType::Integer),
+];
+
+const EDGE_COLUMNS: &[(&str, ColumnType)] = &[
+    ("id", ColumnType::Integer),
+    ("label", ColumnType::Text),
+    ("from_vertex_id", ColumnType::Integer),
+    ("from_vertex_type", ColumnType::Text),
+    ("to_vertex_id", ColumnType::Integer),
+    ("to_vertex_type", ColumnType::Text),
+    ("created_by", ColumnType::Text),
+    ("created_at", ColumnType::Timestamp),
+    ("updated_by", ColumnType::Text),
+    ("updated_at", ColumnType::Timestamp),
+    ("version", ColumnType::Integer),
+    ("edge_key", ColumnType::Text),
+    ("weight", ColumnType::Float),
+    ("valid_from", ColumnType::Timestamp),
+    ("valid_to", ColumnType::Timestamp),
+];
+
+/// The edges as read by a directed relationship: undirected edges appear
+/// once more with their endpoints swapped, so that they match either way.
+const ORIENTED_EDGES: &str = "(SELECT * FROM edge \
+    UNION ALL \
+    SELECT (jsonb_populate_record(edge, jsonb_build_object( \
+        'from_vertex_id', to_vertex_id, 'from_vertex_type', to_vertex_type, \
+        'to_vertex_id', from_vertex_id, 'to_vertex_type', from_vertex_type \
+    ))).* FROM edge WHERE undirected)";
+
+#[derive(Debug, Clone)]
+enum Variable {
+    Vertex(String),
+    Edge(String),
+}
+
+/// A node of a pattern while it is being planned. `id` is the SQL expression
+/// of its vertex id; anonymous, unlabeled nodes that are only reached through
+/// directed edges have no vertex row of their own and take the id of the
+/// adjacent edge endpoint, so `id` stays `None` until that edge is planned.
+struct NodeRef {
+    id: Option<String>,
+}
+
+/// Plans `query` onto the vertex and edge tables: every node and
+/// fixed-length relationship becomes a joined table, every variable-length
+/// relationship a recursive CTE over the live edges.
+pub(crate) fn plan(query: &Query, parameters: &HashMap<String, Literal>) -> Result<Plan, Error> {
+    let mut planner = Planner {
+        parameters,
+        binds: Vec::new(),
+        variables: HashMap::new(),
+        ctes: Vec::new(),
+        from: Vec::new(),
+        conditions: Vec::new(),
+        edges: Vec::new(),
+        aliases: 0,
+    };
+
+    for pattern in &query.patterns {
+        planner.pattern(pattern)?;
+    }
+
+    // The same edge is never matched twice within one MATCH.
+    for (i, left) in planner.edges.iter().enumerate() {
+        for right in &planner.edges[i + 1..] {
+            planner.conditions.push(format!("{left}.id <> {right}.id"));
+        }
+    }
+
+    if let Some(predicate) = &query.predicate {
+        let condition = planner.expr(predicate)?;
+        planner.conditions.push(condition);
+    }
+
+    let mut columns = Vec::new();
+    let mut items = Vec::new();
+    for item in &query.items {
+        let name = item.name();
+        if columns.iter().any(|(column, _)| *column == name) {
+            return Err(query_error(&format!("duplicate column {name}")));
+        }
+        let (sql, type_) = planner.return_item(item)?;
+        columns.push((name, type_));
+        items.push((sql, matches!(item.expr, ReturnExpr::Count(_))));
+    }
+
+    let aggregating = items.iter().any(|(_, aggregate)| *aggregate);
+    let mut order_by = Vec::new();
+    for order_item in &query.order_by {
+        let sql = match &order_item.key {
+            OrderKey::Alias(alias) => columns
+                .iter()
+                .position(|(name, _)| name == alias)
+                .map(|i| items[i].0.clone())
+                .ok_or_else(|| query_error(&format!("unknown column {alias}")))?,
+            OrderKey::Property(variable, key) => {
+                let (sql, _) = planner.property(variable, key)?;
+                if (query.distinct || aggregating) && !items.iter().any(|(item, _)| *item == sql) {
+                    return Err(query_error(
+                        "ORDER BY must refer to returned columns with DISTINCT or count",
+                    ));
+                }
+                sql
+            }
+        };
+        order_by.push((sql, order_item.descending));
+    }
+
+    let mut sql = String::new();
+    if !planner.ctes.is_empty() {
+        sql.push_str("WITH RECURSIVE ");
+        sql.push_str(&planner.ctes.join(", "));
+        sql.push(' ');
+    }
+
+    sql.push_str("SELECT ");
+    if query.distinct {
+        sql.push_str("DISTINCT ");
+    }
+    let selected = items
+        .iter()
+        .map(|(item, _)| item.as_str())
+        .collect::<Vec<_>>();
+    sql.push_str(&format!(
+        "jsonb_build_array({}) AS row",
+        selected.join(", ")
+    ));
+    for (i, (key, _)) in order_by.iter().enumerate() {
+        sql.push_str(&format!(", {key} AS o{i}"));
+    }
+
+    sql.push_str(&format!(" FROM {}", planner.from.join(", ")));
+    if !planner.conditions.is_empty() {
+        sql.push_str(&format!(" WHERE {}", planner.conditions.join(" AND ")));
+    }
+
+    let grouped = items
+        .iter()
+        .filter(|(_, aggregate)| !*aggregate)
+        .map(|(item, _)| item.as_str())
+        .collect::<Vec<_>>();
+    if aggregating && !grouped.is_empty() {
+        sql.push_str(&format!(" GROUP BY {}", grouped.join(", ")));
+    }
+
+    if !order_by.is_empty() {
+        let keys = order_by
+            .iter()
+            .enumerate()
+            .map(|(i, (_, descending))| {
+                if *descending {
+                    format!("o{i} DESC")
+                } else {
+                    format!("o{i}")
+                }
+            })
+            .collect::<Vec<_>>();
+        sql.push_str(&format!(" ORDER BY {}", keys.join(", ")));
+    }
+
+    if let Some(skip) = query.skip {
+        let placeholder = planner.bind(Bind::BigInt(count(skip)?));
+        sql.push_str(&format!(" OFFSET {placeholder}"));
+    }
+    if let Some(limit) = query.limit {
+        let placeholder = planner.bind(Bind::BigInt(count(limit)?));
+        sql.push_str(&format!(" LIMIT {placeholder}"));
+    }
+
+    Ok(Plan {
+        sql,
+        binds: planner.binds,
+        columns,
+    })
+}
+
+/// How much of the FROM list and of the conditions had been planned at some
+/// point.
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    from: usize,
+    conditions: usize,
+}
+
+struct Planner<'a> {
+    parameters: &'a HashMap<String, Literal>,
+    binds: Vec<Bind>,
+    variables: HashMap<String, Variable>,
+    ctes: Vec<String>,
+    from: Vec<String>,
+    conditions: Vec<String>,
+    /// Aliases of the fixed-length relationships matched so far.
+    edges: Vec<String>,
+    aliases: usize,
+}
+
+impl Planner<'_> {
+    fn pattern(&mut self, pattern: &Pattern) -> Result<(), Error> {
+        let nodes = std::iter::once(&pattern.start)
+            .chain(pattern.steps.iter().map(|(_, node)| node))
+            .collect::<Vec<_>>();
+
+        let elidable = |i: usize| {
+            let adjacent = [i.checked_sub(1), Some(i)]
+                .into_iter()
+                .flatten()
+                .filter_map(|step| pattern.steps.get(step))
+                .map(|(relationship, _)| relationship)
+                .collect::<Vec<_>>();
+            let node = nodes[i];
+
+            // The denormalized endpoint types of an edge only hold primary
+            // labels, so a labeled node needs its vertex row.
+            node.variable.is_none()
+                && node.type_.is_none()
+                && node.properties.is_empty()
+                && !adjacent.is_empty()
+                && adjacent.iter().all(|relationship| {
+                    relationship.length.is_none() && relationship.direction != Direction::Both
+                })
+        };
+
+        let mut left = self.node(&pattern.start, elidable(0))?;
+        for (i, (relationship, node)) in pattern.steps.iter().enumerate() {
+            let scope = Scope {
+                from: self.from.len(),
+                conditions: self.conditions.len(),
+            };
+            let mut right = self.node(node, elidable(i + 1))?;
+            match relationship.length {
+                None => self.relationship(relationship, &mut left, &mut right)?,
+                Some(length) => self.path(relationship, length, &left, &right, scope)?,
+            }
+            left = right;
+        }
+
+        Ok(())
+    }
+
+    fn node(&mut self, node: &NodePattern, elide: bool) -> Result<NodeRef, Error> {
+        if elide {
+            return Ok(NodeRef { id: None });
+        }
+
+        let bound = match &node.variable {
+            Some(variable) => match self.variables.get(variable) {
+                Some(Variable::Vertex(alias)) => Some(alias.clone()),
+                Some(Variable::Edge(_)) => {
+                    return Err(query_error(&format!("{variable} is not a node")));
+                }
+                None => None,
+            },
+            None => None,
+        };
+
+        let alias = match bound {
+            Some(alias) => alias,
+            None => {
+                let alias = self.alias("n");
+                self.from.push(format!("vertex AS {alias}"));
+                self.conditions.push(format!("{alias}.deleted_at IS NULL"));
+                if let Some(variable) = &node.variable {
+                    self.variables
+                        .insert(variable.clone(), Variable::Vertex(alias.clone()));
+                }
+                alias
+            }
+        };
+
+        if let Some(label) = &node.type_ {
+            let placeholder = self.bind(Bind::Text(label.clone()));
+            self.conditions
+                .push(format!("{alias}.labels @> ARRAY[{placeholder}]"));
+        }
+        self.properties(&alias, VERTEX_COLUMNS, &node.properties)?;
+
+        Ok(NodeRef {
+            id: Some(format!("{alias}.id")),
+        })
+    }
+
+    fn relationship(
+        &mut self,
+        relationship: &RelationshipPattern,
+        left: &mut NodeRef,
+        right: &mut NodeRef,
+    ) -> Result<(), Error> {
+        let alias = self.alias("r");
+        if let Some(variable) = &relationship.variable {
+            if self.variables.contains_key(variable) {
+                return Err(query_error(&format!("{variable} is already bound")));
+            }
+            self.variables
+                .insert(variable.clone(), Variable::Edge(alias.clone()));
+        }
+
+        let source = match relationship.direction {
+            Direction::Both => "edge",
+            Direction::Outgoing | Direction::Incoming => ORIENTED_EDGES,
+        };
+        self.from.push(format!("{source} AS {alias}"));
+        self.conditions.push(format!("{alias}.deleted_at IS NULL"));
+        self.edges.push(alias.clone());
+
+        if let Some(condition) = self.labels(&format!("{alias}.label"), &relationship.labels) {
+            self.conditions.push(condition);
+        }
+        self.properties(&alias, EDGE_COLUMNS, &relationship.properties)?;
+
+        match relationship.direction {
+            Direction::Outgoing => {
+                self.endpoint(left, &format!("{alias}.from_vertex"));
+                self.endpoint(right, &format!("{alias}.to_vertex"));
+            }
+            Direction::Incoming => {
+                self.endpoint(left, &format!("{alias}.to_vertex"));
+                self.endpoint(right, &format!("{alias}.from_vertex"));
+            }
+            Direction::Both => {
+                // Nodes next to an undirected relationship are never elided.
+                let (Some(left), Some(right)) = (&left.id, &right.id) else {
+                    unreachable!("undirected relationship between elided nodes")
+                };
+                self.conditions.push(format!(
+                    "(({alias}.from_vertex_id = {left} AND {alias}.to_vertex_id = {right}) \
+                     OR ({alias}.from_vertex_id = {right} AND {alias}.to_vertex_id = {left}))"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ties `node` to one end of an edge.
+    fn endpoint(&mut self, node: &mut NodeRef, prefix: &str) {
+        let id = format!("{prefix}_id");
+        match &node.id {
+            Some(node_id) => self.conditions.push(format!("{id} = {node_id}")),
+            None => node.id = Some(id),
+        }
+    }
+
+    /// Plans a variable-length relationship as the recursive CTE of all
+    /// live paths starting at `left` that do not revisit a vertex. `scope` is
+    /// what was planned before the relationship, which the paths are seeded
+    /// from.
+    fn path(
+        &mut self,
+        relationship: &RelationshipPattern,
+        length: Length,
+        left: &NodeRef,
+        right: &NodeRef,
+        scope: Scope,
+    ) -> Result<(), Error> {
+        if relationship.variable.is_some() {
+            return Err(query_error(
+                "variable-length relationships cannot be bound to a variable",
+            ));
+        }
+        if !relationship.properties.is_empty() {
+            return Err(query_error(
+                "variable-length relationships cannot have properties",
+            ));
+        }
+
+        let max = length.max.unwrap_or(MAX_PATH_LENGTH);
+        if length.min == 0 || length.min > max || max > MAX_PATH_LENGTH {
+            return Err(query_error(&format!(
+                "path length must be within 1..{MAX_PATH_LENGTH}"
+            )));
+        }
+
+        // Variable-length relationships are never next to elided nodes.
+        let (Some(left_id), Some(right_id)) = (&left.id, &right.id) else {
+            unreachable!("variable-length relationship next to an elided node")
+        };
+
+        let steps = self.alias("s");
+        let paths = self.alias("p");
+
+        let mut filter = "deleted_at IS NULL".to_string();
+        if let Some(condition) = self.labels("label", &relationship.labels) {
+            filter.push_str(&format!(" AND {condition}"));
+        }
+        let outgoing =
+            format!("SELECT from_vertex_id AS src, to_vertex_id AS dst FROM edge WHERE {filter}");
+        let incoming =
+            format!("SELECT to_vertex_id AS src, from_vertex_id AS dst FROM edge WHERE {filter}");
+        let step_sql = match relationship.direction {
+            Direction::Outgoing => format!("{outgoing} UNION ALL {incoming} AND undirected"),
+            Direction::Incoming => format!("{incoming} UNION ALL {outgoing} AND undirected"),
+            Direction::Both => format!("{outgoing} UNION ALL {incoming}"),
+        };
+        self.ctes.push(format!("{steps} AS ({step_sql})"));
+
+        // Only the vertices `left` can still be are walked from, so that the
+        // CTE does not enumerate the paths of the whole graph. The conditions
+        // planned so far reuse their placeholders.
+        let mut seed = format!(
+            " WHERE src IN (SELECT {left_id} FROM {}",
+            self.from[..scope.from].join(", ")
+        );
+        if scope.conditions > 0 {
+            seed.push_str(&format!(
+                " WHERE {}",
+                self.conditions[..scope.conditions].join(" AND ")
+            ));
+        }
+        seed.push(')');
+        self.ctes.push(format!(
+            "{paths}(src, dst, depth, path) AS (\
+                SELECT src, dst, 1, ARRAY[src, dst] FROM {steps}{seed} \
+                UNION ALL \
+                SELECT p.src, s.dst, p.depth + 1, p.path || s.dst \
+                FROM {paths} AS p JOIN {steps} AS s ON s.src = p.dst \
+                WHERE p.depth < {max} AND s.dst <> ALL(p.path)\
+            )"
+        ));
+
+        let alias = self.alias("r");
+        self.from.push(format!("{paths} AS {alias}"));
+        self.conditions.push(format!("{alias}.src = {left_id}"));
+        self.conditions.push(format!("{alias}.dst = {right_id}"));
+        if length.min > 1 {
+            self.conditions
+                .push(format!("{alias}.depth >= {}", length.min));
+        }
+
+        Ok(())
+    }
+
+    fn labels(&mut self, column: &str, labels: &[String]) -> Option<String> {
+        match labels {
+            [] => None,
+            [label] => {
+                let placeholder = self.bind(Bind::Text(label.clone()));
+                Some(format!("{column} = {placeholder}"))
+            }
+            labels => {
+                let placeholder = self.bind(Bind::TextArray(labels.to_vec()));
+                Some(format!("{column} = ANY({placeholder})"))
+            }
+        }
+    }
+
+    fn properties(
+        &mut self,
+        alias: &str,
+        columns: &[(&str, ColumnType)],
+        properties: &[(String, Operand)],
+    ) -> Result<(), Error> {
+        for (key, value) in properties {
+            let type_ = column_type(columns, key)
+                .ok_or_else(|| query_error(&format!("unknown property {key}")))?;
+            let value = self.value(value, Some(type_))?;
+            self.conditions.push(format!("{alias}.\"{key}\" = {value}"));
+        }
+
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<String, Error> {
+        Ok(match expr {
+            Expr::Or(left, right) => format!("({} OR {})", self.expr(left)?, self.expr(right)?),
+            Expr::And(left, right) => format!("({} AND {})", self.expr(left)?, self.expr(right)?),
+            Expr::Not(expr) => format!("(NOT {})", self.expr(expr)?),
+            Expr::IsNull { operand, negated } => {
+                let value = self.value(operand, None)?;
+                if *negated {
+                    format!("({value} IS NOT NULL)")
+                } else {
+                    format!("({value} IS NULL)")
+                }
+            }
+            Expr::Comparison(left, operator, right) => self.comparison(left, *operator, right)?,
+        })
+    }
+
+    fn comparison(
+        &mut self,
+        left: &Operand,
+        operator: ComparisonOperator,
+        right: &Operand,
+    ) -> Result<String, Error> {
+        let left_type = self.operand_type(left)?;
+        let right_type = self.operand_type(right)?;
+        let is_list = |operand: &Operand, planner: &Self| match operand {
+            Operand::Literal(literal) => matches!(literal, Literal::List(_)),
+            Operand::Parameter(name) => {
+                matches!(planner.parameters.get(name), Some(Literal::List(_)))
+            }
+            Operand::Property(_, _) => false,
+        };
+
+        if is_list(left, self) || (operator != ComparisonOperator::In && is_list(right, self)) {
+            return Err(query_error("lists can only be used on the right of IN"));
+        }
+        if operator == ComparisonOperator::In && !is_list(right, self) {
+            return Err(query_error("IN expects a list"));
+        }
+        if let (Some(left_type), Some(right_type)) = (left_type, right_type) {
+            if left_type != right_type {
+                return Err(query_error(&format!(
+                    "cannot compare {left_type:?} with {right_type:?}"
+                )));
+            }
+        }
+        if matches!(
+            operator,
+            ComparisonOperator::StartsWith
+                | ComparisonOperator::EndsWith
+                | ComparisonOperator::Contains
+        ) && [left_type, right_type]
+            .iter()
+            .flatten()
+            .any(|type_| *type_ != ColumnType::Text)
+        {
+            return Err(query_error(
+                "string operators only apply to text properties",
+            ));
+        }
+
+        let left = self.value(left, right_type)?;
+        let right = self.value(right, left_type)?;
+
+        Ok(match operator {
+            ComparisonOperator::Eq => format!("({left} = {right})"),
+            ComparisonOperator::Ne => format!("({left} <> {right})"),
+            ComparisonOperator::Lt => format!("({left} < {right})"),
+            ComparisonOperator::Le => format!("({left} <= {right})"),
+            ComparisonOperator::Gt => format!("({left} > {right})"),
+            ComparisonOperator::Ge => format!("({left} >= {right})"),
+            ComparisonOperator::In => format!("({left} = ANY({right}))"),
+            ComparisonOperator::StartsWith => format!("starts_with({left}, {right})"),
+            ComparisonOperator::EndsWith => format!("(right({left}, length({right})) = {right})"),
+            ComparisonOperator::Contains => format!("(strpos({left}, {right}) > 0)"),
+        })
+    }
+
+    fn operand_type(&self, operand: &Operand) -> Result<Option<ColumnType>, Error> {
+        match operand {
+            Operand::Property(variable, key) => Ok(Some(self.property(variable, key)?.1)),
+            _ => Ok(None),
+        }
+    }
+
+    /// The SQL of `operand`; literals are bound as parameters typed to
+    /// compare against a column of type `against`.
+    fn value(&mut self, operand: &Operand, against: Option<ColumnType>) -> Result<String, Error> {
+        match operand {
+            Operand::Property(variable, key) => Ok(self.property(variable, key)?.0),
+            Operand::Literal(literal) => self.literal(literal, against),
+            Operand::Parameter(name) => {
+                let literal = self
+                    .parameters
+                    .get(name)
+                    .ok_or_else(|| query_error(&format!("missing parameter ${name}")))?;
+                self.literal(literal, against)
+            }
+        }
+    }
+
+    fn literal(&mut self, literal: &Literal, against: Option<ColumnType>) -> Result<String, Error> {
+        let mismatch = || query_error(&format!("cannot compare {literal:?} with {against:?}"));
+
+        let placeholder = match (literal, against) {
+            (Literal::Null, _) => return Ok("NULL".to_string()),
+            (Literal::Boolean(value), None) => self.bind(Bind::Boolean(*value)),
+            (Literal::Integer(value), None | Some(ColumnType::Integer)) => {
+                self.bind(Bind::BigInt(*value))
+            }
+            (Literal::Integer(value), Some(ColumnType::Float)) => {
+                self.bind(Bind::Double(*value as f64))
+            }
+            (Literal::Float(value), None | Some(ColumnType::Integer | ColumnType::Float)) => {
+                self.bind(Bind::Double(*value))
+            }
+            (Literal::String(value), None | Some(ColumnType::Text)) => {
+                self.bind(Bind::Text(value.clone()))
+            }
+            (Literal::String(value), Some(ColumnType::Timestamp)) => {
+                let placeholder = self.bind(Bind::Text(value.clone()));
+                return Ok(format!("CAST({placeholder} AS TIMESTAMP)"));
+            }
+            (Literal::List(values), against) => {
+                let bind = list(values, against).ok_or_else(mismatch)?;
+                let placeholder = self.bind(bind);
+                if against == Some(ColumnType::Timestamp) {
+                    return Ok(format!("CAST({placeholder} AS TIMESTAMP[])"));
+                }
+                placeholder
+            }
+            _ => return Err(mismatch()),
+        };
+
+        Ok(placeholder)
+    }
+
+    fn property(&self, variable: &str, key: &str) -> Result<(String, ColumnType), Error> {
+        let (alias, columns) = match self.variables.get(variable) {
+            Some(Variable::Vertex(alias)) => (alias, VERTEX_COLUMNS),
+            Some(Variable::Edge(alias)) => (alias, EDGE_COLUMNS),
+            None => return Err(query_error(&format!("unknown variable {variable}"))),
+        };
+
+        let type_ = column_type(columns, key)
+            .ok_or_else(|| query_error(&format!("unknown property {variable}.{key}")))?;
+
+        Ok((format!("{alias}.\"{key}\""), type_))
+    }
+
+    fn return_item(&self, item: &ReturnItem) -> Result<(String, ColumnType), Error> {
+        let variable = |name: &str| {
+            self.variables
+                .get(name)
+                .ok_or_else(|| query_error(&format!("unknown variable {name}")))
+        };
+
+        Ok(match &item.expr {
+            ReturnExpr::Variable(name) => match variable(name)? {
+                Variable::Vertex(alias) => (format!("to_jsonb({alias})"), ColumnType::Vertex),
+                Variable::Edge(alias) => (format!("to_jsonb({alias})"), ColumnType::Edge),
+            },
+            ReturnExpr::Property(name, key) => self.property(name, key)?,
+            ReturnExpr::Count(None) => ("count(*)".to_string(), ColumnType::Integer),
+            ReturnExpr::Count(Some(name)) => match variable(name)? {
+                Variable::Vertex(alias) | Variable::Edge(alias) => {
+                    (format!("count({alias}.id)"), ColumnType::Integer)
+                }
+            },
+        })
+    }
+
+    fn alias(&mut self, prefix: &str) -> String {
+        self.aliases += 1;
+        format!("{prefix}{}", self.aliases)
+    }
+
+    fn bind(&mut self, bind: Bind) -> String {
+        self.binds.push(bind);
+        format!("${}", self.binds.len())
+    }
+}
+
+fn column_type(columns: &[(&str, ColumnType)], key: &str) -> Option<ColumnType> {
+    columns
+        .iter()
+        .find(|(column, _)| *column == key)
+        .map(|(_, type_)| *type_)
+}
+
+/// Binds a list literal as an array of the type of its elements.
+fn list(values: &[Literal], against: Option<ColumnType>) -> Option<Bind> {
+    let strings = || {
+        values
+            .iter()
+            .map(|value| match value {
+                Literal::String(value) => Some(value.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    };
+    let integers = || {
+        values
+            .iter()
+            .map(|value| match value {
+                Literal::Integer(value) => Some(*value),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    };
+    let floats = || {
+        values
+            .iter()
+            .map(|value| match value {
+                Literal::Integer(value) => Some(*value as f64),
+                Literal::Float(value) => Some(*value),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+    };
+
+    match against {
+        Some(ColumnType::Text | ColumnType::Timestamp) => strings().map(Bind::TextArray),
+        Some(ColumnType::Integer) => integers()
+            .map(Bind::BigIntArray)
+            .or_else(|| floats().map(Bind::DoubleArray)),
+        Some(ColumnType::Float) => floats().map(Bind::DoubleArray),
+        Some(ColumnType::Vertex | ColumnType::Edge) => None,
+        None => strings()
+            .map(Bind::TextArray)
+            .or_else(|| integers().map(Bind::BigIntArray))
+            .or_else(|| floats().map(Bind::DoubleArray)),
+    }
+}
+
+fn count(value: u64) -> Result<i64, Error> {
+    i64::try_from(value).map_err(|_| query_error("SKIP and LIMIT must fit in 64 bits"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::cypher::ast::Literal;
+    use crate::cypher::parser::parse;
+
+    #[test]
+    fn test_path_seed() {
+        let parameters = HashMap::from([("x".to_string(), Literal::String("alice".to_string()))]);
+        for (query, seed) in [
+            (
+                "MATCH (a {name: $x})-[:knows*]->(b) RETURN b",
+                vec![
+                    "WHERE src IN (SELECT n1.id FROM vertex AS n1 \
+                     WHERE n1.deleted_at IS NULL AND n1.\"name\" = $1)",
+                ],
+            ),
+            // A node reached through a relationship is seeded through it.
+            (
+                "MATCH (a:Person)-[:owns]->(c)-[:knows*..3]-(b) RETURN b",
+                vec![
+                    "WHERE src IN (SELECT n2.id FROM vertex AS n1, vertex AS n2, ",
+                    "AS r3 WHERE n1.deleted_at IS NULL AND n1.labels @> ARRAY[$1] \
+                     AND n2.deleted_at IS NULL AND r3.deleted_at IS NULL AND r3.label = $2 \
+                     AND r3.from_vertex_id = n1.id AND r3.to_vertex_id = n2.id) UNION ALL",
+                ],
+            ),
+        ] {
+            let plan = super::plan(&parse(query).unwrap(), &parameters).unwrap();
+            for fragment in seed {
+                assert!(plan.sql.contains(fragment), "{}", plan.sql);
+            }
+        }
+    }
+}
@@ -0,0 +1,1492 @@
+//! Datalog rules deriving edges from edges, e.g.
+//!
+//! ```text
+//! ancestor(X, Z) :- parent(X, Z).
+//! ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).
+//! colleague(A, B) :- works_at(A, C), works_at(B, C), A != B.
+//! ```
+//!
+//! Every predicate is an edge label and every fact an edge from the first to
+//! the second argument. Arguments are variables (capitalized, or `_` for a
+//! fresh one); `X != Y` keeps two variables apart.
+//!
+//! Rules are evaluated semi-naively over the live asserted edges, either at
+//! query time with [`query`] or materialized with [`create_rule`]. Derived
+//! edges are marked [`Edge::derived`](crate::model::Edge::derived) and do not
+//! include facts already asserted by a real edge, nor self-loops of labels
+//! whose settings do not allow them. The model of
+//! the last [`maintain`] run is kept in `datalog_fact`; a trigger logs the
+//! asserted facts changed since, and the next run deletes and rederives from
+//! those, writing only the edges that appeared or disappeared.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use diesel::sql_types::{Array, Bool, Int4, Text};
+use diesel::{ExpressionMethods, QueryDsl, QueryableByName, SelectableHelper};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use validator::Validate;
+
+use crate::{
+    constant::DATALOG_ACTOR,
+    dto::{InsertableNewDatalogRule, NewDatalogRule},
+    error::Error,
+    model::DatalogRule,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Program {
+    pub rules: Vec<Rule>,
+}
+
+/// `head :- body, distinct.`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub head: Atom,
+    pub body: Vec<Atom>,
+    /// Pairs of variables that must be bound to different vertices.
+    pub distinct: Vec<(String, String)>,
+}
+
+/// `label(From, To)`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Atom {
+    pub label: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({}, {})", self.label, self.from, self.to)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} :- ", self.head)?;
+        let literals = self
+            .body
+            .iter()
+            .map(|atom| atom.to_string())
+            .chain(self.distinct.iter().map(|(x, y)| format!("{x} != {y}")))
+            .collect::<Vec<_>>();
+        write!(f, "{}.", literals.join(", "))
+    }
+}
+
+impl Program {
+    pub fn parse(text: &str) -> Result<Program, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+            fresh: 0,
+        };
+
+        let mut rules = Vec::new();
+        while parser.position < parser.tokens.len() {
+            rules.push(parser.rule()?);
+        }
+        if rules.is_empty() {
+            return Err(query_error("expected at least one rule"));
+        }
+
+        Ok(Program { rules })
+    }
+
+    /// The labels whose facts `label` is derived from, `label` included.
+    fn dependencies(&self, label: &str) -> HashSet<String> {
+        let mut labels = HashSet::from([label.to_string()]);
+        let mut pending = vec![label.to_string()];
+        while let Some(label) = pending.pop() {
+            for rule in self.rules.iter().filter(|rule| rule.head.label == label) {
+                for atom in &rule.body {
+                    if labels.insert(atom.label.clone()) {
+                        pending.push(atom.label.clone());
+                    }
+                }
+            }
+        }
+
+        labels
+    }
+
+    /// The labels derived by at least one rule.
+    fn heads(&self) -> HashSet<String> {
+        self.rules
+            .iter()
+            .map(|rule| rule.head.label.clone())
+            .collect()
+    }
+
+    /// Runs the rules to a fixpoint over `facts`, which hold the asserted
+    /// edges of every label the rules read, and adds what they derive.
+    fn evaluate(&self, facts: &mut Facts) {
+        // The first round sees every fact as new.
+        let mut delta = Facts::new();
+        for rule in &self.rules {
+            for pair in fire(rule, facts, None, None) {
+                if !facts.contains(&rule.head.label, pair) {
+                    delta.insert(&rule.head.label, pair);
+                }
+            }
+        }
+
+        self.propagate(facts, delta);
+    }
+
+    /// Adds `delta`, facts missing from `facts`, and runs the rules to a
+    /// fixpoint from them. Returns every fact added.
+    fn propagate(&self, facts: &mut Facts, mut delta: Facts) -> Facts {
+        let mut added = Facts::new();
+        while !delta.is_empty() {
+            for (label, pair) in delta.iter() {
+                facts.insert(label, pair);
+            }
+
+            // Only rules reading a label that just grew can derive something
+            // new, and only from joins using at least one of the new facts.
+            let mut next = Facts::new();
+            for rule in &self.rules {
+                for (i, atom) in rule.body.iter().enumerate() {
+                    let Some(relation) = delta.relations.get(&atom.label) else {
+                        continue;
+                    };
+                    for pair in fire(rule, facts, Some((i, relation)), None) {
+                        if !facts.contains(&rule.head.label, pair) {
+                            next.insert(&rule.head.label, pair);
+                        }
+                    }
+                }
+            }
+            added.extend(delta);
+            delta = next;
+        }
+
+        added
+    }
+
+    /// Brings `facts`, a fixpoint of the rules, up to date with the asserted
+    /// facts `added` and `removed` since, `asserted` holding the asserted
+    /// facts as they are now. Deletes and rederives: whatever follows from a
+    /// removed fact is taken out, what is still asserted or derivable from
+    /// the rest is put back, and the rules run again from those and the
+    /// added facts only. Returns the facts taken out and the facts put in,
+    /// which may overlap.
+    fn update(
+        &self,
+        facts: &mut Facts,
+        asserted: &Facts,
+        added: Facts,
+        removed: Facts,
+    ) -> (Facts, Facts) {
+        // Everything derivable from a removed fact in the old model, some of
+        // which may have other derivations.
+        let mut lost = Facts::new();
+        let mut delta = removed;
+        while !delta.is_empty() {
+            let mut next = Facts::new();
+            for rule in &self.rules {
+                for (i, atom) in rule.body.iter().enumerate() {
+                    let Some(relation) = delta.relations.get(&atom.label) else {
+                        continue;
+                    };
+                    for pair in fire(rule, facts, Some((i, relation)), None) {
+                        let label = &rule.head.label;
+                        if !lost.contains(label, pair) && !delta.contains(label, pair) {
+                            next.insert(label, pair);
+                        }
+                    }
+                }
+            }
+            lost.extend(delta);
+            delta = next;
+        }
+        for (label, pair) in lost.iter() {
+            facts.remove(label, pair);
+        }
+
+        // One step rederives what survives directly; the rest follows from
+        // those while propagating.
+        let mut delta = Facts::new();
+        for (label, pair) in lost.iter().chain(added.iter()) {
+            let derivable = asserted.contains(label, pair)
+                || self
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.head.label == label)
+                    .any(|rule| !fire(rule, facts, None, Some(pair)).is_empty());
+            if derivable && !facts.contains(label, pair) {
+                delta.insert(label, pair);
+            }
+        }
+        let gained = self.propagate(facts, delta);
+
+        (lost, gained)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Relation {
+    pairs: HashSet<(i32, i32)>,
+    forward: HashMap<i32, Vec<i32>>,
+    backward: HashMap<i32, Vec<i32>>,
+}
+
+impl Relation {
+    fn insert(&mut self, (from, to): (i32, i32)) -> bool {
+        if !self.pairs.insert((from, to)) {
+            return false;
+        }
+        self.forward.entry(from).or_default().push(to);
+        self.backward.entry(to).or_default().push(from);
+        true
+    }
+
+    fn remove(&mut self, (from, to): (i32, i32)) {
+        if !self.pairs.remove(&(from, to)) {
+            return;
+        }
+        if let Some(targets) = self.forward.get_mut(&from) {
+            targets.retain(|target| *target != to);
+        }
+        if let Some(sources) = self.backward.get_mut(&to) {
+            sources.retain(|source| *source != from);
+        }
+    }
+
+    fn matching(&self, from: Option<i32>, to: Option<i32>) -> Vec<(i32, i32)> {
+        match (from, to) {
+            (Some(from), Some(to)) => {
+                if self.pairs.contains(&(from, to)) {
+                    vec![(from, to)]
+                } else {
+                    Vec::new()
+                }
+            }
+            (Some(from), None) => self.forward.get(&from).map_or(Vec::new(), |targets| {
+                targets.iter().map(|to| (from, *to)).collect()
+            }),
+            (None, Some(to)) => self.backward.get(&to).map_or(Vec::new(), |sources| {
+                sources.iter().map(|from| (*from, to)).collect()
+            }),
+            (None, None) => self.pairs.iter().copied().collect(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct Facts {
+    relations: HashMap<String, Relation>,
+}
+
+impl Facts {
+    fn new() -> Self {
+        Facts::default()
+    }
+
+    fn insert(&mut self, label: &str, pair: (i32, i32)) -> bool {
+        self.relations
+            .entry(label.to_string())
+            .or_default()
+            .insert(pair)
+    }
+
+    fn remove(&mut self, label: &str, pair: (i32, i32)) {
+        if let Some(relation) = self.relations.get_mut(label) {
+            relation.remove(pair);
+        }
+    }
+
+    fn extend(&mut self, other: Facts) {
+        for (label, pair) in other.iter() {
+            self.insert(label, pair);
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, (i32, i32))> {
+        self.relations.iter().flat_map(|(label, relation)| {
+            relation
+                .pairs
+                .iter()
+                .map(move |pair| (label.as_str(), *pair))
+        })
+    }
+
+    fn contains(&self, label: &str, pair: (i32, i32)) -> bool {
+        self.relations
+            .get(label)
+            .is_some_and(|relation| relation.pairs.contains(&pair))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+
+    fn pairs(&self, label: &str) -> HashSet<(i32, i32)> {
+        self.relations
+            .get(label)
+            .map_or(HashSet::new(), |relation| relation.pairs.clone())
+    }
+}
+
+/// The head facts of `rule` over `facts`, reading the body atom at index
+/// `delta.0` from `delta.1` instead when given, and limited to `head` when
+/// given.
+fn fire(
+    rule: &Rule,
+    facts: &Facts,
+    delta: Option<(usize, &Relation)>,
+    head: Option<(i32, i32)>,
+) -> Vec<(i32, i32)> {
+    let empty = Relation::default();
+    let mut binding = HashMap::<&str, i32>::new();
+    if let Some((from, to)) = head {
+        if rule.head.from == rule.head.to && from != to {
+            return Vec::new();
+        }
+        binding.insert(&rule.head.from, from);
+        binding.insert(&rule.head.to, to);
+    }
+    let mut bindings = vec![binding];
+
+    for (i, atom) in rule.body.iter().enumerate() {
+        let relation = match delta {
+            Some((position, relation)) if position == i => relation,
+            _ => facts.relations.get(&atom.label).unwrap_or(&empty),
+        };
+
+        let mut next = Vec::new();
+        for binding in &bindings {
+            let from = binding.get(atom.from.as_str()).copied();
+            let to = binding.get(atom.to.as_str()).copied();
+            for (from, to) in relation.matching(from, to) {
+                if atom.from == atom.to && from != to {
+                    continue;
+                }
+                let mut binding = binding.clone();
+                binding.insert(&atom.from, from);
+                binding.insert(&atom.to, to);
+                next.push(binding);
+            }
+        }
+
+        bindings = next;
+        if bindings.is_empty() {
+            return Vec::new();
+        }
+    }
+
+    bindings
+        .into_iter()
+        .filter(|binding| {
+            rule.distinct
+                .iter()
+                .all(|(x, y)| binding[x.as_str()] != binding[y.as_str()])
+        })
+        .map(|binding| {
+            (
+                binding[rule.head.from.as_str()],
+                binding[rule.head.to.as_str()],
+            )
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Identifier(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Period,
+    Implies,
+    NotEqual,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '%' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_alphanumeric() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+        } else {
+            let (token, width) = match (c, chars.get(i + 1)) {
+                (':', Some('-')) => (Token::Implies, 2),
+                ('!', Some('=')) => (Token::NotEqual, 2),
+                ('(', _) => (Token::LeftParen, 1),
+                (')', _) => (Token::RightParen, 1),
+                (',', _) => (Token::Comma, 1),
+                ('.', _) => (Token::Period, 1),
+                _ => return Err(query_error(&format!("unexpected character '{c}'"))),
+            };
+            tokens.push(token);
+            i += width;
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Counter for renaming `_` to variables of their own.
+    fresh: usize,
+}
+
+impl Parser {
+    fn rule(&mut self) -> Result<Rule, Error> {
+        let head = self.atom()?;
+        self.expect(Token::Implies)?;
+
+        let mut body = Vec::new();
+        let mut distinct = Vec::new();
+        loop {
+            let first = self.identifier()?;
+            if self.eat(&Token::NotEqual) {
+                distinct.push((self.variable(first)?, self.variable_token()?));
+            } else {
+                body.push(self.atom_after(first)?);
+            }
+            if !self.eat(&Token::Comma) {
+                break;
+            }
+        }
+        if !self.eat(&Token::Period) && self.position < self.tokens.len() {
+            return Err(query_error("expected '.' after a rule"));
+        }
+
+        let bound = body
+            .iter()
+            .flat_map(|atom| [atom.from.as_str(), atom.to.as_str()])
+            .collect::<HashSet<_>>();
+        let used = [head.from.as_str(), head.to.as_str()]
+            .into_iter()
+            .chain(distinct.iter().flat_map(|(x, y)| [x.as_str(), y.as_str()]));
+        for variable in used {
+            if variable.starts_with('_') || !bound.contains(variable) {
+                return Err(query_error(&format!(
+                    "variable {variable} of rule {} is not bound by its body",
+                    head.label
+                )));
+            }
+        }
+
+        Ok(Rule {
+            head,
+            body,
+            distinct,
+        })
+    }
+
+    fn atom(&mut self) -> Result<Atom, Error> {
+        let label = self.identifier()?;
+        self.atom_after(label)
+    }
+
+    fn atom_after(&mut self, label: String) -> Result<Atom, Error> {
+        if is_variable(&label) {
+            return Err(query_error(&format!("expected a label, found {label}")));
+        }
+        self.expect(Token::LeftParen)?;
+        let from = self.variable_token()?;
+        self.expect(Token::Comma)?;
+        let to = self.variable_token()?;
+        self.expect(Token::RightParen)?;
+
+        Ok(Atom { label, from, to })
+    }
+
+    fn variable_token(&mut self) -> Result<String, Error> {
+        let name = self.identifier()?;
+        self.variable(name)
+    }
+
+    fn variable(&mut self, name: String) -> Result<String, Error> {
+        if !is_variable(&name) {
+            return Err(query_error(&format!("expected a variable, found {name}")));
+        }
+        if name == "_" {
+            self.fresh += 1;
+            return Ok(format!("_{}", self.fresh));
+        }
+
+        Ok(name)
+    }
+
+    fn identifier(&mut self) -> Result<String, Error> {
+        match self.tokens.get(self.position) {
+            Some(Token::Identifier(name)) => {
+                self.position += 1;
+                Ok(name.clone())
+            }
+            Some(token) => Err(query_error(&format!("unexpected {token:?}"))),
+            None => Err(query_error("unexpected end of rule")),
+        }
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), Error> {
+        if self.eat(&expected) {
+            Ok(())
+        } else {
+            Err(query_error(&format!("expected {expected:?}")))
+        }
+    }
+}
+
+fn is_variable(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase() || c == '_')
+}
+
+fn query_error(message: &str) -> Error {
+    Error::Query(message.to_string())
+}
+
+#[derive(QueryableByName)]
+struct Fact {
+    #[diesel(sql_type = Text)]
+    label: String,
+    #[diesel(sql_type = Int4)]
+    from_vertex_id: i32,
+    #[diesel(sql_type = Int4)]
+    to_vertex_id: i32,
+}
+
+/// Loads the live asserted edges with one of `labels`, undirected edges as
+/// facts both ways.
+async fn load_facts(
+    conn: &mut AsyncPgConnection,
+    labels: &HashSet<String>,
+) -> Result<Facts, Error> {
+    let labels = labels.iter().cloned().collect::<Vec<_>>();
+    let rows = diesel::sql_query(
+        "SELECT label, from_vertex_id, to_vertex_id FROM edge \
+         WHERE deleted_at IS NULL AND NOT derived AND label = ANY($1) \
+         UNION ALL \
+         SELECT label, to_vertex_id, from_vertex_id FROM edge \
+         WHERE deleted_at IS NULL AND NOT derived AND label = ANY($1) AND undirected",
+    )
+    .bind::<Array<Text>, _>(labels)
+    .load::<Fact>(conn)
+    .await?;
+
+    let mut facts = Facts::new();
+    for row in rows {
+        facts.insert(&row.label, (row.from_vertex_id, row.to_vertex_id));
+    }
+
+    Ok(facts)
+}
+
+/// Evaluates `program` at query time and returns the `(from, to)` pairs of
+/// the `label` facts, asserted or derived, in order. Materialized edges are
+/// not consulted.
+pub async fn query(
+    conn: &mut AsyncPgConnection,
+    program: &Program,
+    label: &str,
+) -> Result<Vec<(i32, i32)>, Error> {
+    let mut facts = load_facts(conn, &program.dependencies(label)).await?;
+    program.evaluate(&mut facts);
+
+    let mut result = facts.pairs(label).into_iter().collect::<Vec<_>>();
+    result.sort_unstable();
+
+    Ok(result)
+}
+
+/// Stores a rule and materializes the edges it derives.
+pub async fn create_rule(
+    conn: &mut AsyncPgConnection,
+    new_rule: &NewDatalogRule,
+) -> Result<DatalogRule, Error> {
+    use crate::schema::datalog_dirty_label;
+    use crate::schema::datalog_rule;
+
+    new_rule.validate()?;
+    let program = Program::parse(&new_rule.rule)?;
+    let [rule] = program.rules.as_slice() else {
+        return Err(query_error("expected exactly one rule"));
+    };
+
+    let new_rule = InsertableNewDatalogRule {
+        rule: rule.to_string(),
+        head_label: rule.head.label.clone(),
+        body_labels: rule
+            .body
+            .iter()
+            .map(|atom| Some(atom.label.clone()))
+            .collect(),
+        created_by: new_rule.created_by.clone(),
+    };
+
+    let result = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                let result = diesel::insert_into(datalog_rule::table)
+                    .values(&new_rule)
+                    .returning(DatalogRule::as_returning())
+                    .get_result(conn)
+                    .await?;
+
+                diesel::insert_into(datalog_dirty_label::table)
+                    .values(datalog_dirty_label::label.eq(&new_rule.head_label))
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+
+                Ok(result)
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    maintain(conn).await?;
+
+    Ok(result)
+}
+
+/// Removes a rule together with the edges only it derived.
+pub async fn delete_rule(conn: &mut AsyncPgConnection, rule_id: i32) -> Result<usize, Error> {
+    use crate::schema::datalog_dirty_label;
+    use crate::schema::datalog_rule::dsl::*;
+
+    if rule_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let deleted = conn
+        .transaction::<_, Error, _>(|conn| {
+            async move {
+                let labels = diesel::delete(datalog_rule.filter(id.eq(rule_id)))
+                    .returning(head_label)
+                    .get_results::<String>(conn)
+                    .await?;
+
+                for label in &labels {
+                    diesel::insert_into(datalog_dirty_label::table)
+                        .values(datalog_dirty_label::label.eq(label))
+                        .on_conflict_do_nothing()
+                        .execute(conn)
+                        .await?;
+                }
+
+                Ok(labels.len())
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    maintain(conn).await?;
+
+    Ok(deleted)
+}
+
+pub async fn get_rules(conn: &mut AsyncPgConnection) -> Result<Vec<DatalogRule>, Error> {
+    use crate::schema::datalog_rule::dsl::*;
+
+    let result = datalog_rule
+        .order(id.asc())
+        .select(DatalogRule::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceSummary {
+    pub inserted: usize,
+    pub deleted: usize,
+}
+
+/// Brings the derived edges up to date with the asserted facts changed since
+/// the last run, deleting and rederiving from the changed facts alone. A
+/// changed rule makes the run evaluate every rule from scratch instead.
+///
+/// Nothing runs this on its own: [`create_rule`] and [`delete_rule`] call it,
+/// but any other write to the edges only logs the facts it changed, and the
+/// derived edges lag behind until the caller runs `maintain`, after its
+/// writes or in the background with [`run`].
+pub async fn maintain(conn: &mut AsyncPgConnection) -> Result<MaintenanceSummary, Error> {
+    conn.transaction::<_, Error, _>(|conn| {
+        async move {
+            use crate::schema::datalog_dirty_label::dsl::*;
+
+            // Each run starts from the model the previous one left.
+            diesel::sql_query("LOCK TABLE datalog_fact IN EXCLUSIVE MODE")
+                .execute(conn)
+                .await?;
+
+            // Labels marked and facts logged by transactions committing after
+            // these statements are left for the next run.
+            let dirty = diesel::delete(datalog_dirty_label)
+                .returning(label)
+                .get_results::<String>(conn)
+                .await?;
+            let changes = diesel::sql_query(
+                "WITH change AS ( \
+                     DELETE FROM datalog_fact_change \
+                     RETURNING label, from_vertex_id, to_vertex_id \
+                 ) \
+                 SELECT DISTINCT change.label, change.from_vertex_id, change.to_vertex_id, \
+                     EXISTS ( \
+                         SELECT 1 FROM edge \
+                         WHERE edge.deleted_at IS NULL AND NOT edge.derived \
+                         AND edge.label = change.label \
+                         AND (edge.from_vertex_id = change.from_vertex_id \
+                              AND edge.to_vertex_id = change.to_vertex_id \
+                              OR edge.undirected \
+                              AND edge.from_vertex_id = change.to_vertex_id \
+                              AND edge.to_vertex_id = change.from_vertex_id) \
+                     ) AS asserted \
+                 FROM change",
+            )
+            .load::<StoredFact>(conn)
+            .await?;
+            if dirty.is_empty() && changes.is_empty() {
+                return Ok(MaintenanceSummary::default());
+            }
+
+            // Without any rule left every derived edge goes. A stored rule
+            // that no longer parses fails the run instead of dropping the
+            // edges it derived.
+            let rules = get_rules(conn).await?;
+            let program = if rules.is_empty() {
+                Program { rules: Vec::new() }
+            } else {
+                Program::parse(
+                    &rules
+                        .iter()
+                        .map(|rule| rule.rule.as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                )?
+            };
+
+            crate::api::set_history_actor(conn, DATALOG_ACTOR).await?;
+            if dirty.is_empty() {
+                update_model(conn, &program, changes).await
+            } else {
+                rebuild_model(conn, &program, dirty).await
+            }
+        }
+        .scope_boxed()
+    })
+    .await
+}
+
+/// Maintains forever, sleeping `poll_interval` between runs that had nothing
+/// to do.
+pub async fn run(conn: &mut AsyncPgConnection, poll_interval: Duration) -> Result<(), Error> {
+    loop {
+        if maintain(conn).await? == MaintenanceSummary::default() {
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+}
+
+/// Evaluates every rule from the asserted edges and replaces the stored
+/// model, and the derived edges of the current heads and of the `dirty`
+/// ones, whose rules may be gone.
+async fn rebuild_model(
+    conn: &mut AsyncPgConnection,
+    program: &Program,
+    dirty: Vec<String>,
+) -> Result<MaintenanceSummary, Error> {
+    let mut heads = program.heads();
+    let mut labels = HashSet::new();
+    for head in &heads {
+        labels.extend(program.dependencies(head));
+    }
+    heads.extend(dirty);
+    let self_loops = self_loop_labels(conn, &heads).await?;
+
+    let asserted = load_facts(conn, &labels).await?;
+    let mut facts = asserted.clone();
+    program.evaluate(&mut facts);
+
+    diesel::sql_query("DELETE FROM datalog_fact")
+        .execute(conn)
+        .await?;
+    store_facts(conn, &asserted, facts.iter()).await?;
+
+    let mut summary = MaintenanceSummary::default();
+    for head in &heads {
+        let wanted = facts
+            .pairs(head)
+            .into_iter()
+            .filter(|pair| materialized(&facts, &asserted, head, self_loops.contains(head), *pair))
+            .collect::<HashSet<_>>();
+        let written = replace_derived_edges(conn, head, &wanted).await?;
+        summary.inserted += written.inserted;
+        summary.deleted += written.deleted;
+    }
+
+    Ok(summary)
+}
+
+/// Applies the logged `changes` to the stored model of the labels depending
+/// on them, and writes back only the facts and derived edges they touched.
+async fn update_model(
+    conn: &mut AsyncPgConnection,
+    program: &Program,
+    changes: Vec<StoredFact>,
+) -> Result<MaintenanceSummary, Error> {
+    let changed = changes
+        .iter()
+        .map(|change| change.label.clone())
+        .collect::<HashSet<_>>();
+    let heads = program
+        .heads()
+        .into_iter()
+        .filter(|head| !program.dependencies(head).is_disjoint(&changed))
+        .collect::<HashSet<_>>();
+    let mut labels = HashSet::new();
+    for head in &heads {
+        labels.extend(program.dependencies(head));
+    }
+    let self_loops = self_loop_labels(conn, &heads).await?;
+
+    let (mut facts, mut asserted) = load_model(conn, &labels).await?;
+
+    let mut added = Facts::new();
+    let mut removed = Facts::new();
+    for change in changes
+        .iter()
+        .filter(|change| labels.contains(&change.label))
+    {
+        let pair = (change.from_vertex_id, change.to_vertex_id);
+        if change.asserted == asserted.contains(&change.label, pair) {
+            continue;
+        }
+        if change.asserted {
+            asserted.insert(&change.label, pair);
+            added.insert(&change.label, pair);
+        } else {
+            asserted.remove(&change.label, pair);
+            removed.insert(&change.label, pair);
+        }
+    }
+    let mut touched = added.clone();
+    touched.extend(removed.clone());
+
+    let (lost, gained) = program.update(&mut facts, &asserted, added, removed);
+    touched.extend(lost);
+    touched.extend(gained);
+
+    diesel::sql_query(
+        "DELETE FROM datalog_fact \
+         WHERE (label, from_vertex_id, to_vertex_id) IN (SELECT * FROM unnest($1, $2, $3))",
+    )
+    .bind::<Array<Text>, _>(touched.iter().map(|(label, _)| label).collect::<Vec<_>>())
+    .bind::<Array<Int4>, _>(touched.iter().map(|(_, pair)| pair.0).collect::<Vec<_>>())
+    .bind::<Array<Int4>, _>(touched.iter().map(|(_, pair)| pair.1).collect::<Vec<_>>())
+    .execute(conn)
+    .await?;
+    store_facts(
+        conn,
+        &asserted,
+        touched
+            .iter()
+            .filter(|(label, pair)| facts.contains(label, *pair)),
+    )
+    .await?;
+
+    let mut summary = MaintenanceSummary::default();
+    for head in &heads {
+        let (wanted, unwanted): (Vec<_>, Vec<_>) =
+            touched.pairs(head).into_iter().partition(|pair| {
+                materialized(&facts, &asserted, head, self_loops.contains(head), *pair)
+            });
+        let (from_ids, to_ids): (Vec<i32>, Vec<i32>) = unwanted.into_iter().unzip();
+        summary.deleted += diesel::sql_query(
+            "DELETE FROM edge WHERE derived AND label = $1 \
+             AND (from_vertex_id, to_vertex_id) IN (SELECT * FROM unnest($2, $3))",
+        )
+        .bind::<Text, _>(head)
+        .bind::<Array<Int4>, _>(from_ids)
+        .bind::<Array<Int4>, _>(to_ids)
+        .execute(conn)
+        .await?;
+        summary.inserted += insert_derived_edges(conn, head, wanted).await?;
+    }
+
+    Ok(summary)
+}
+
+/// Whether the `label` fact `pair` is materialized as a derived edge: it
+/// holds, no asserted edge states it already, and it is no self-loop unless
+/// the label allows them.
+fn materialized(
+    facts: &Facts,
+    asserted: &Facts,
+    label: &str,
+    allows_self_loops: bool,
+    pair: (i32, i32),
+) -> bool {
+    (allows_self_loops || pair.0 != pair.1)
+        && facts.contains(label, pair)
+        && !asserted.contains(label, pair)
+}
+
+/// The labels among `labels` whose settings allow self-loops.
+async fn self_loop_labels(
+    conn: &mut AsyncPgConnection,
+    labels: &HashSet<String>,
+) -> Result<HashSet<String>, Error> {
+    use crate::schema::edge_label_setting::dsl::*;
+
+    let labels = labels.iter().cloned().collect::<Vec<_>>();
+    let result = edge_label_setting
+        .filter(label.eq_any(labels))
+        .filter(allow_self_loops)
+        .select(label)
+        .load::<String>(conn)
+        .await?;
+
+    Ok(result.into_iter().collect())
+}
+
+#[derive(QueryableByName)]
+struct StoredFact {
+    #[diesel(sql_type = Text)]
+    label: String,
+    #[diesel(sql_type = Int4)]
+    from_vertex_id: i32,
+    #[diesel(sql_type = Int4)]
+    to_vertex_id: i32,
+    #[diesel(sql_type = Bool)]
+    asserted: bool,
+}
+
+/// Loads the stored model of `labels`, and the part of it that is asserted.
+async fn load_model(
+    conn: &mut AsyncPgConnection,
+    labels: &HashSet<String>,
+) -> Result<(Facts, Facts), Error> {
+    let labels = labels.iter().cloned().collect::<Vec<_>>();
+    let rows = diesel::sql_query(
+        "SELECT label, from_vertex_id, to_vertex_id, asserted FROM datalog_fact \
+         WHERE label = ANY($1)",
+    )
+    .bind::<Array<Text>, _>(labels)
+    .load::<StoredFact>(conn)
+    .await?;
+
+    let mut facts = Facts::new();
+    let mut asserted = Facts::new();
+    for row in rows {
+        let pair = (row.from_vertex_id, row.to_vertex_id);
+        facts.insert(&row.label, pair);
+        if row.asserted {
+            asserted.insert(&row.label, pair);
+        }
+    }
+
+    Ok((facts, asserted))
+}
+
+/// Stores the model facts `rows`, flagging the `asserted` ones.
+async fn store_facts<'a>(
+    conn: &mut AsyncPgConnection,
+    asserted: &Facts,
+    rows: impl Iterator<Item = (&'a str, (i32, i32))>,
+) -> Result<usize, Error> {
+    let mut labels = Vec::new();
+    let mut from_ids = Vec::new();
+    let mut to_ids = Vec::new();
+    let mut flags = Vec::new();
+    for (label, pair) in rows {
+        labels.push(label);
+        from_ids.push(pair.0);
+        to_ids.push(pair.1);
+        flags.push(asserted.contains(label, pair));
+    }
+
+    let stored = diesel::sql_query(
+        "INSERT INTO datalog_fact (label, from_vertex_id, to_vertex_id, asserted) \
+         SELECT * FROM unnest($1, $2, $3, $4)",
+    )
+    .bind::<Array<Text>, _>(labels)
+    .bind::<Array<Int4>, _>(from_ids)
+    .bind::<Array<Int4>, _>(to_ids)
+    .bind::<Array<Bool>, _>(flags)
+    .execute(conn)
+    .await?;
+
+    Ok(stored)
+}
+
+#[derive(QueryableByName)]
+struct DerivedEdge {
+    #[diesel(sql_type = Int4)]
+    id: i32,
+    #[diesel(sql_type = Int4)]
+    from_vertex_id: i32,
+    #[diesel(sql_type = Int4)]
+    to_vertex_id: i32,
+}
+
+/// Makes the live derived `label` edges exactly `wanted`. Trashed derived
+/// edges are dropped as well, since they can always be derived again.
+async fn replace_derived_edges(
+    conn: &mut AsyncPgConnection,
+    label: &str,
+    wanted: &HashSet<(i32, i32)>,
+) -> Result<MaintenanceSummary, Error> {
+    let existing = diesel::sql_query(
+        "SELECT id, from_vertex_id, to_vertex_id FROM edge \
+         WHERE derived AND label = $1 AND deleted_at IS NULL",
+    )
+    .bind::<Text, _>(label)
+    .load::<DerivedEdge>(conn)
+    .await?;
+
+    let kept = existing
+        .iter()
+        .filter(|edge| wanted.contains(&(edge.from_vertex_id, edge.to_vertex_id)))
+        .map(|edge| edge.id)
+        .collect::<Vec<_>>();
+    let present = existing
+        .iter()
+        .map(|edge| (edge.from_vertex_id, edge.to_vertex_id))
+        .collect::<HashSet<_>>();
+
+    let deleted =
+        diesel::sql_query("DELETE FROM edge WHERE derived AND label = $1 AND NOT id = ANY($2)")
+            .bind::<Text, _>(label)
+            .bind::<Array<Int4>, _>(&kept)
+            .execute(conn)
+            .await?;
+
+    let inserted = insert_derived_edges(
+        conn,
+        label,
+        wanted
+            .iter()
+            .filter(|pair| !present.contains(pair))
+            .copied()
+            .collect(),
+    )
+    .await?;
+
+    Ok(MaintenanceSummary { inserted, deleted })
+}
+
+/// Inserts derived `label` edges for `pairs` not linked by one already.
+async fn insert_derived_edges(
+    conn: &mut AsyncPgConnection,
+    label: &str,
+    pairs: Vec<(i32, i32)>,
+) -> Result<usize, Error> {
+    let (from_ids, to_ids): (Vec<i32>, Vec<i32>) = pairs.into_iter().unzip();
+
+    // Endpoints are only linked while both vertices are live.
+    let inserted = diesel::sql_query(
+        "INSERT INTO edge \
+            (from_vertex_id, from_vertex_type, to_vertex_id, to_vertex_type, label, \
+             created_by, updated_by, derived) \
+         SELECT source.id, source.type, target.id, target.type, $1, $2, $2, TRUE \
+         FROM unnest($3, $4) AS pair (from_vertex_id, to_vertex_id) \
+         JOIN vertex AS source ON source.id = pair.from_vertex_id AND source.deleted_at IS NULL \
+         JOIN vertex AS target ON target.id = pair.to_vertex_id AND target.deleted_at IS NULL \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind::<Text, _>(label)
+    .bind::<Text, _>(DATALOG_ACTOR)
+    .bind::<Array<Int4>, _>(from_ids)
+    .bind::<Array<Int4>, _>(to_ids)
+    .execute(conn)
+    .await?;
+
+    Ok(inserted)
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::ExpressionMethods;
+    use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+
+    use crate::datalog::{Atom, Program};
+    use crate::dto::{NewDatalogRule, NewEdge, NewVertex};
+    use crate::error::Error;
+
+    #[test]
+    fn test_parse() {
+        let program = Program::parse(
+            "% transitive closure\n\
+             ancestor(X, Z) :- parent(X, Z).\n\
+             ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).\n\
+             colleague(A, B) :- works_at(A, C), works_at(B, C), A != B",
+        )
+        .unwrap();
+        assert_eq!(program.rules.len(), 3);
+        assert_eq!(
+            program.rules[1].body,
+            vec![
+                Atom {
+                    label: "parent".to_string(),
+                    from: "X".to_string(),
+                    to: "Y".to_string()
+                },
+                Atom {
+                    label: "ancestor".to_string(),
+                    from: "Y".to_string(),
+                    to: "Z".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            program.rules[2].to_string(),
+            "colleague(A, B) :- works_at(A, C), works_at(B, C), A != B."
+        );
+        assert_eq!(
+            program.dependencies("ancestor"),
+            ["ancestor", "parent"].map(String::from).into()
+        );
+
+        for invalid in [
+            "",
+            "p(X, Y)",
+            "p(X, Y) :- q(X, Z).",
+            "p(X, Y) :- q(X, Y), X != W.",
+            "p(X, _) :- q(X, _).",
+            "p(x, Y) :- q(x, Y).",
+            "P(X, Y) :- q(X, Y).",
+            "p(X, Y) :- q(X, Y) r(X, Y).",
+        ] {
+            assert!(
+                matches!(Program::parse(invalid), Err(Error::Query(_))),
+                "{invalid}"
+            );
+        }
+    }
+
+    /// The live derived `label` edges leaving `ids`.
+    async fn derived_pairs(
+        conn: &mut AsyncPgConnection,
+        label: &str,
+        ids: &[i32],
+    ) -> Vec<(i32, i32)> {
+        let query = crate::dto::EdgeQuery {
+            label: Some(label.to_string()),
+            ..Default::default()
+        };
+        let mut pairs = Vec::new();
+        for id in ids {
+            for edge in crate::api::get_incident_edges(conn, *id, &query)
+                .await
+                .unwrap()
+            {
+                assert!(edge.derived);
+                pairs.push((edge.from_vertex_id, edge.to_vertex_id));
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[tokio::test]
+    async fn test_query_and_maintain() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for name in ["datalog_a", "datalog_b", "datalog_c", "datalog_d"] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: "datalog_person".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let ids = vertices.iter().map(|vertex| vertex.id).collect::<Vec<_>>();
+
+        // a -> b -> c
+        let mut parents = Vec::new();
+        for (from, to) in [(0, 1), (1, 2)] {
+            let new_edge = NewEdge {
+                from_vertex_id: ids[from],
+                to_vertex_id: ids[to],
+                label: "datalog_parent".to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            parents.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let program = Program::parse(
+            "datalog_ancestor(X, Z) :- datalog_parent(X, Z).\n\
+             datalog_ancestor(X, Z) :- datalog_parent(X, Y), datalog_ancestor(Y, Z).",
+        )
+        .unwrap();
+        let result = crate::datalog::query(&mut conn, &program, "datalog_ancestor")
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![(ids[0], ids[1]), (ids[0], ids[2]), (ids[1], ids[2])]
+        );
+
+        let mut rules = Vec::new();
+        for rule in [
+            "datalog_ancestor(X, Z) :- datalog_parent(X, Z).",
+            "datalog_ancestor(X, Z) :- datalog_parent(X, Y), datalog_ancestor(Y, Z).",
+        ] {
+            let new_rule = NewDatalogRule {
+                rule: rule.to_string(),
+                created_by: "test".to_string(),
+            };
+            rules.push(
+                crate::datalog::create_rule(&mut conn, &new_rule)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        assert_eq!(
+            derived_pairs(&mut conn, "datalog_ancestor", &ids).await,
+            vec![(ids[0], ids[1]), (ids[0], ids[2]), (ids[1], ids[2])]
+        );
+
+        // c -> d extends every chain.
+        let new_edge = NewEdge {
+            from_vertex_id: ids[2],
+            to_vertex_id: ids[3],
+            label: "datalog_parent".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        let c_d = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 3);
+        assert_eq!(summary.deleted, 0);
+
+        // Cutting a -> b leaves b's descendants only.
+        crate::api::soft_delete_edge_by_id(&mut conn, parents[0].id, None, "test")
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.deleted, 3);
+        assert_eq!(
+            derived_pairs(&mut conn, "datalog_ancestor", &ids).await,
+            vec![(ids[1], ids[2]), (ids[1], ids[3]), (ids[2], ids[3])]
+        );
+
+        // Nothing changed since.
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary, Default::default());
+
+        // b -> d is derived already, and still is once c -> d goes.
+        let new_edge = NewEdge {
+            from_vertex_id: ids[1],
+            to_vertex_id: ids[3],
+            label: "datalog_parent".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary, Default::default());
+        crate::api::soft_delete_edge_by_id(&mut conn, c_d.id, None, "test")
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.deleted, 1);
+        assert_eq!(
+            derived_pairs(&mut conn, "datalog_ancestor", &ids).await,
+            vec![(ids[1], ids[2]), (ids[1], ids[3])]
+        );
+
+        // An asserted edge replaces the derived one for as long as it lives.
+        let new_edge = NewEdge {
+            from_vertex_id: ids[1],
+            to_vertex_id: ids[2],
+            label: "datalog_ancestor".to_string(),
+            ..new_edge
+        };
+        let asserted = crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.deleted, 1);
+        crate::api::soft_delete_edge_by_id(&mut conn, asserted.id, None, "test")
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 1);
+        assert_eq!(
+            derived_pairs(&mut conn, "datalog_ancestor", &ids).await,
+            vec![(ids[1], ids[2]), (ids[1], ids[3])]
+        );
+
+        // A stored rule that does not parse leaves the derived edges alone.
+        let broken = diesel::insert_into(crate::schema::datalog_rule::table)
+            .values(crate::dto::InsertableNewDatalogRule {
+                rule: "datalog_broken(X) :-".to_string(),
+                head_label: "datalog_ancestor".to_string(),
+                body_labels: Vec::new(),
+                created_by: "test".to_string(),
+            })
+            .returning(crate::schema::datalog_rule::id)
+            .get_result::<i32>(&mut conn)
+            .await
+            .unwrap();
+        diesel::insert_into(crate::schema::datalog_dirty_label::table)
+            .values(crate::schema::datalog_dirty_label::label.eq("datalog_ancestor"))
+            .on_conflict_do_nothing()
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        let result = crate::datalog::maintain(&mut conn).await;
+        assert!(matches!(result, Err(Error::Query(_))));
+        assert_eq!(
+            derived_pairs(&mut conn, "datalog_ancestor", &ids)
+                .await
+                .len(),
+            2
+        );
+        diesel::delete(crate::schema::datalog_rule::table)
+            .filter(crate::schema::datalog_rule::id.eq(broken))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        for rule in &rules {
+            crate::datalog::delete_rule(&mut conn, rule.id)
+                .await
+                .unwrap();
+        }
+        assert!(derived_pairs(&mut conn, "datalog_ancestor", &ids)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_self_loop_policy() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut ids = Vec::new();
+        for name in [
+            "datalog_loop_p",
+            "datalog_loop_x",
+            "datalog_loop_y",
+            "datalog_loop_z",
+        ] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: "datalog_loop".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            let vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+                .await
+                .unwrap();
+            ids.push(vertex.id);
+        }
+        let child = |to| NewEdge {
+            from_vertex_id: ids[0],
+            to_vertex_id: to,
+            label: "datalogchild".to_string(),
+            created_by: "test".to_string(),
+            ..Default::default()
+        };
+        for to in [ids[1], ids[2]] {
+            crate::api::create_edge(&mut conn, &child(to))
+                .await
+                .unwrap();
+        }
+
+        // Everyone is their own sibling, which is only materialized once the
+        // label allows self-loops.
+        let new_rule = NewDatalogRule {
+            rule: "datalogsibling(X, Y) :- datalogchild(P, X), datalogchild(P, Y).".to_string(),
+            created_by: "test".to_string(),
+        };
+        let rule = crate::datalog::create_rule(&mut conn, &new_rule)
+            .await
+            .unwrap();
+        assert_eq!(
+            derived_pairs(&mut conn, "datalogsibling", &ids).await,
+            vec![(ids[1], ids[2]), (ids[2], ids[1])]
+        );
+
+        let new_setting = |allow_self_loops| crate::dto::NewEdgeLabelSetting {
+            label: "datalogsibling".to_string(),
+            delete_policy: crate::model::DeletePolicy::Cascade,
+            inverse_label: None,
+            symmetric: false,
+            allow_self_loops,
+            multigraph: false,
+            created_by: "test".to_string(),
+        };
+        crate::api::save_edge_label_setting(&mut conn, &new_setting(true))
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 2);
+        assert_eq!(summary.deleted, 0);
+
+        // A new child is their own sibling too.
+        crate::api::create_edge(&mut conn, &child(ids[3]))
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 5);
+        assert!(derived_pairs(&mut conn, "datalogsibling", &ids)
+            .await
+            .contains(&(ids[3], ids[3])));
+
+        crate::api::save_edge_label_setting(&mut conn, &new_setting(false))
+            .await
+            .unwrap();
+        let summary = crate::datalog::maintain(&mut conn).await.unwrap();
+        assert_eq!(summary.inserted, 0);
+        assert_eq!(summary.deleted, 3);
+        assert!(derived_pairs(&mut conn, "datalogsibling", &ids)
+            .await
+            .iter()
+            .all(|pair| pair.0 != pair.1));
+
+        crate::datalog::delete_rule(&mut conn, rule.id)
+            .await
+            .unwrap();
+    }
+}
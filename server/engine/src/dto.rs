@@ -1,17 +1,27 @@
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use serde::Deserialize;
 use validator::{Validate, ValidationError};
 
-use crate::pattern::{EDGE_LABEL_LIKE, USERNAME_LIKE};
+use crate::change::ChangeFilter;
+use crate::constant::MAX_TYPE_LENGTH;
+use crate::model::{DeletePolicy, Interval, PropertyMerge};
+use crate::pattern::{EDGE_LABEL_LIKE, NOT_BLANK, SOURCE_LIKE, USERNAME_LIKE};
 use crate::schema;
 
+/// A vertex. `type` is its primary label; `labels` adds more.
 #[derive(Debug, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct NewVertex {
     pub name: String,
     #[serde(rename = "type")]
     pub type_: String,
     #[validate(regex(path = *USERNAME_LIKE))]
+    #[cfg_attr(feature = "openapi", schema(schema_with = crate::openapi::username_like))]
     pub created_by: String,
+    #[serde(default)]
+    #[validate(custom(function = "labels_not_blank"))]
+    pub labels: Vec<String>,
 }
 
 #[derive(Debug, Insertable)]
@@ -21,19 +31,136 @@ pub struct InsertableNewVertex {
     pub type_: String,
     pub created_by: String,
     pub updated_by: String,
+    pub labels: Vec<String>,
 }
 
+#[derive(Debug, Deserialize, Validate, AsChangeset)]
+#[diesel(table_name = schema::vertex)]
+pub struct UpdateVertex {
+    pub name: Option<String>,
+    #[serde(rename = "type")]
+    pub type_: Option<String>,
+    /// Replaces the labels. The type is kept among them either way.
+    #[validate(custom(function = "labels_not_blank"))]
+    pub labels: Option<Vec<String>>,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub updated_by: String,
+}
+
+/// An alternate name for a vertex. It may not be the name of another vertex
+/// of the same type, nor an alias of one.
+#[derive(Debug, Deserialize, Validate)]
+pub struct NewVertexAlias {
+    #[validate(range(min = 1))]
+    pub vertex_id: i32,
+    #[validate(length(min = 1, max = 255))]
+    pub alias: String,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub created_by: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::vertex_alias)]
+pub struct InsertableNewVertexAlias {
+    pub vertex_id: i32,
+    pub alias: String,
+    pub type_: String,
+    pub created_by: String,
+}
+
+/// Folds `duplicate_ids` into `survivor_id`. See [`crate::api::merge_vertices`].
 #[derive(Debug, Deserialize, Validate)]
-#[validate(schema(function = "vertices_not_same"))]
+pub struct MergeVertices {
+    #[validate(range(min = 1))]
+    pub survivor_id: i32,
+    #[validate(length(min = 1))]
+    pub duplicate_ids: Vec<i32>,
+    #[serde(default)]
+    pub property_merge: PropertyMerge,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub merged_by: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::vertex_merge)]
+pub struct InsertableVertexMerge {
+    pub survivor_id: i32,
+    pub duplicates: serde_json::Value,
+    pub property_merge: PropertyMerge,
+    pub repointed_edges: i32,
+    pub merged_edges: i32,
+    pub dropped_edges: i32,
+    pub dropped_edge_ids: Vec<i32>,
+    pub merged_by: String,
+}
+
+/// Identifies a live vertex by its type and its name or one of its aliases.
+#[derive(Debug, Clone, Deserialize)]
+pub struct VertexKey {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// An edge between two vertices. Self-loops are only accepted for labels
+/// whose settings allow them.
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "validity_not_empty"))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct NewEdge {
     #[validate(range(min = 1))]
+    #[cfg_attr(feature = "openapi", schema(minimum = 1))]
     pub from_vertex_id: i32,
     #[validate(range(min = 1))]
+    #[cfg_attr(feature = "openapi", schema(minimum = 1))]
     pub to_vertex_id: i32,
     #[validate(regex(path = *EDGE_LABEL_LIKE))]
+    #[cfg_attr(feature = "openapi", schema(schema_with = crate::openapi::edge_label_like))]
     pub label: String,
     #[validate(regex(path = *USERNAME_LIKE))]
+    #[cfg_attr(feature = "openapi", schema(schema_with = crate::openapi::username_like))]
     pub created_by: String,
+    /// Makes the edge read the same in both directions. Its endpoints may
+    /// come back swapped.
+    #[serde(default)]
+    pub undirected: bool,
+    /// Tells apart parallel edges of a multigraph label. Defaults to a hash
+    /// of the properties there, and is rejected for other labels.
+    #[validate(length(min = 1, max = 255))]
+    pub edge_key: Option<String>,
+    #[serde(default)]
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    /// Must be finite. Defaults to 1.
+    #[serde(default = "default_weight")]
+    #[validate(custom(function = "finite"))]
+    #[cfg_attr(feature = "openapi", schema(default = 1.0))]
+    pub weight: f64,
+    /// Bounds when the relation holds in the domain, as opposed to when the
+    /// edge was written.
+    #[serde(default)]
+    pub valid_from: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+/// The defaults of a deserialized edge. The endpoints, label and author have
+/// none and are left blank.
+impl Default for NewEdge {
+    fn default() -> Self {
+        NewEdge {
+            from_vertex_id: 0,
+            to_vertex_id: 0,
+            label: String::new(),
+            created_by: String::new(),
+            undirected: false,
+            edge_key: None,
+            properties: Default::default(),
+            weight: default_weight(),
+            valid_from: None,
+            valid_to: None,
+        }
+    }
 }
 
 #[derive(Debug, Insertable)]
@@ -46,12 +173,307 @@ pub struct InsertableNewEdge {
     pub label: String,
     pub created_by: String,
     pub updated_by: String,
+    pub undirected: bool,
+    pub edge_key: Option<String>,
+    pub properties: serde_json::Value,
+    pub weight: f64,
+    pub valid_from: Option<NaiveDateTime>,
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, Validate, AsChangeset)]
+#[diesel(table_name = schema::edge)]
+pub struct UpdateEdge {
+    #[validate(regex(path = *EDGE_LABEL_LIKE))]
+    pub label: Option<String>,
+    #[validate(custom(function = "finite"))]
+    pub weight: Option<f64>,
+    /// `null` clears the bound, leaving it out keeps it.
+    #[serde(default, deserialize_with = "present")]
+    pub valid_from: Option<Option<NaiveDateTime>>,
+    #[serde(default, deserialize_with = "present")]
+    pub valid_to: Option<Option<NaiveDateTime>>,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub updated_by: String,
+}
+
+/// A source's claim that an edge holds. The edge is created unless a live
+/// one with the same endpoints, label and key already exists, in which case
+/// the assertion is merged into it and the edge fields given here are left
+/// unused. A source asserting an edge again replaces its earlier assertion.
+#[derive(Debug, Deserialize, Validate)]
+pub struct NewEdgeAssertion {
+    #[serde(flatten)]
+    #[validate(nested)]
+    pub edge: NewEdge,
+    #[validate(regex(path = *SOURCE_LIKE))]
+    pub source: String,
+    /// Between 0 and 1.
+    #[validate(custom(function = "probability"))]
+    pub confidence: f64,
+    #[serde(default)]
+    pub evidence: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::edge_assertion)]
+pub struct InsertableNewEdgeAssertion {
+    pub edge_id: i32,
+    pub source: String,
+    pub confidence: f64,
+    pub evidence: serde_json::Value,
+    pub created_by: String,
+    pub updated_by: String,
+}
+
+/// A `NewEdge` whose endpoints are given by key rather than by id.
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "keyed_validity_not_empty"))]
+pub struct NewEdgeByKey {
+    pub from: VertexKey,
+    pub to: VertexKey,
+    #[validate(regex(path = *EDGE_LABEL_LIKE))]
+    pub label: String,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub created_by: String,
+    #[serde(default)]
+    pub undirected: bool,
+    #[validate(length(min = 1, max = 255))]
+    pub edge_key: Option<String>,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    #[serde(default = "default_weight")]
+    #[validate(custom(function = "finite"))]
+    pub weight: f64,
+    #[serde(default)]
+    pub valid_from: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+impl NewEdgeByKey {
+    /// The edge to create once the endpoints are resolved.
+    pub fn to_new_edge(&self, from_vertex_id: i32, to_vertex_id: i32) -> NewEdge {
+        NewEdge {
+            from_vertex_id,
+            to_vertex_id,
+            label: self.label.clone(),
+            created_by: self.created_by.clone(),
+            undirected: self.undirected,
+            edge_key: self.edge_key.clone(),
+            properties: self.properties.clone(),
+            weight: self.weight,
+            valid_from: self.valid_from,
+            valid_to: self.valid_to,
+        }
+    }
+}
+
+/// Settings of an edge label. A symmetric label, or one with an inverse, is
+/// paired by the engine with a mirror edge in the other direction.
+#[derive(Debug, Deserialize, Validate)]
+#[validate(schema(function = "inverse_not_ambiguous"))]
+pub struct NewEdgeLabelSetting {
+    #[validate(regex(path = *EDGE_LABEL_LIKE))]
+    pub label: String,
+    #[serde(default)]
+    pub delete_policy: DeletePolicy,
+    #[validate(regex(path = *EDGE_LABEL_LIKE))]
+    pub inverse_label: Option<String>,
+    #[serde(default)]
+    pub symmetric: bool,
+    #[serde(default)]
+    pub allow_self_loops: bool,
+    #[serde(default)]
+    pub multigraph: bool,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub created_by: String,
 }
 
-fn vertices_not_same(new_edge: &NewEdge) -> Result<(), ValidationError> {
-    if new_edge.from_vertex_id != new_edge.to_vertex_id {
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::edge_label_setting)]
+pub struct InsertableNewEdgeLabelSetting {
+    pub label: String,
+    pub delete_policy: DeletePolicy,
+    pub inverse_label: Option<String>,
+    pub symmetric: bool,
+    pub allow_self_loops: bool,
+    pub multigraph: bool,
+    pub created_by: String,
+    pub updated_by: String,
+}
+
+/// A Datalog rule deriving edges, e.g.
+/// `ancestor(X, Z) :- parent(X, Y), ancestor(Y, Z).`
+#[derive(Debug, Deserialize, Validate)]
+pub struct NewDatalogRule {
+    pub rule: String,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub created_by: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::datalog_rule)]
+pub struct InsertableNewDatalogRule {
+    pub rule: String,
+    pub head_label: String,
+    pub body_labels: Vec<Option<String>>,
+    pub created_by: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct NewWebhook {
+    #[validate(url)]
+    pub url: String,
+    #[validate(length(min = 16, max = 255))]
+    pub secret: String,
+    #[serde(default)]
+    pub filter: ChangeFilter,
+    #[validate(regex(path = *USERNAME_LIKE))]
+    pub created_by: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::webhook)]
+pub struct InsertableNewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub filter: serde_json::Value,
+    pub created_by: String,
+    pub updated_by: String,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = schema::webhook_delivery)]
+pub struct InsertableNewWebhookDelivery {
+    pub webhook_id: i32,
+    pub outbox_id: i64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    #[default]
+    Outgoing,
+    Incoming,
+    Both,
+}
+
+/// Selects the edges followed by neighbor queries and traversals.
+///
+/// `as_of` reads the graph as it was at that moment, reconstructed from the
+/// change history instead of the live tables. `min_weight` leaves out the
+/// lighter edges. `valid_at` and `valid_during` keep the edges holding in
+/// the domain at that instant or at some point of that interval.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EdgeQuery {
+    #[serde(default)]
+    pub direction: Direction,
+    pub label: Option<String>,
+    pub as_of: Option<NaiveDateTime>,
+    pub min_weight: Option<f64>,
+    pub valid_at: Option<NaiveDateTime>,
+    pub valid_during: Option<Interval>,
+}
+
+/// Selects the live edges weighted algorithms run over, and the direction
+/// they are followed in. Bounds are inclusive.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WeightedEdgeQuery {
+    #[serde(default)]
+    pub direction: Direction,
+    pub label: Option<String>,
+    pub min_weight: Option<f64>,
+    pub max_weight: Option<f64>,
+    pub valid_at: Option<NaiveDateTime>,
+}
+
+/// Selects live edges by their provenance: asserted by `source`, and by
+/// some source at least `min_confidence` sure of them. Mirror edges are
+/// selected along with their edge.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProvenanceQuery {
+    pub label: Option<String>,
+    pub source: Option<String>,
+    pub min_confidence: Option<f64>,
+}
+
+/// Selects the parallel edges to aggregate. `sum` names the numeric edge
+/// property to add up.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ParallelEdgeQuery {
+    pub from_vertex_id: Option<i32>,
+    pub to_vertex_id: Option<i32>,
+    pub label: Option<String>,
+    pub sum: Option<String>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+fn labels_not_blank(labels: &[String]) -> Result<(), ValidationError> {
+    let valid =
+        |label: &String| NOT_BLANK.is_match(label) && label.chars().count() <= MAX_TYPE_LENGTH;
+    if labels.iter().all(valid) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("labels_not_blank"))
+    }
+}
+
+fn finite(weight: f64) -> Result<(), ValidationError> {
+    if weight.is_finite() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("finite"))
+    }
+}
+
+fn probability(confidence: f64) -> Result<(), ValidationError> {
+    if (0.0..=1.0).contains(&confidence) {
         Ok(())
     } else {
-        Err(ValidationError::new("matched"))
+        Err(ValidationError::new("probability"))
+    }
+}
+
+fn validity_not_empty(new_edge: &NewEdge) -> Result<(), ValidationError> {
+    not_empty(Interval {
+        from: new_edge.valid_from,
+        to: new_edge.valid_to,
+    })
+}
+
+fn keyed_validity_not_empty(new_edge: &NewEdgeByKey) -> Result<(), ValidationError> {
+    not_empty(Interval {
+        from: new_edge.valid_from,
+        to: new_edge.valid_to,
+    })
+}
+
+fn not_empty(validity: Interval) -> Result<(), ValidationError> {
+    if validity.is_empty() {
+        Err(ValidationError::new("empty_validity"))
+    } else {
+        Ok(())
+    }
+}
+
+// Tells a field set to `null` apart from a missing one, which stays `None`.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+fn inverse_not_ambiguous(new_setting: &NewEdgeLabelSetting) -> Result<(), ValidationError> {
+    match &new_setting.inverse_label {
+        Some(inverse_label) if new_setting.symmetric || *inverse_label == new_setting.label => {
+            Err(ValidationError::new("ambiguous"))
+        }
+        _ => Ok(()),
     }
 }
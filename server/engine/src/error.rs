@@ -1,4 +1,5 @@
-use diesel::result::Error as DieselError;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
 use thiserror::Error as ThisError;
 
 #[derive(ThisError, Debug)]
@@ -7,6 +8,75 @@ pub enum Error {
     Validation(#[from] validator::ValidationErrors),
     #[error("Diesel error: {0}")]
     Database(#[from] DieselError),
+    #[error("Delete restricted by edges labeled: {}", .0.join(", "))]
+    DeleteRestricted(Vec<String>),
+    #[error("Version conflict: expected {expected}, found {actual}")]
+    Conflict { expected: i32, actual: i32 },
+    #[error("Query error: {0}")]
+    Query(String),
+    #[error("Notification error: {0}")]
+    Notification(#[from] tokio_postgres::Error),
+    #[error("Webhook error: {0}")]
+    Webhook(#[from] reqwest::Error),
     #[error("Other error: {0}")]
     Other(#[from] std::io::Error),
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Validation,
+    NotFound,
+    AlreadyExists,
+    DeleteRestricted,
+    Conflict,
+    Internal,
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Validation(_) | Error::Query(_) => ErrorKind::Validation,
+            Error::Database(DieselError::NotFound) => ErrorKind::NotFound,
+            Error::Database(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                ErrorKind::AlreadyExists
+            }
+            Error::DeleteRestricted(_) => ErrorKind::DeleteRestricted,
+            Error::Conflict { .. } => ErrorKind::Conflict,
+            _ => ErrorKind::Internal,
+        }
+    }
+}
+
+/// The error as reported to clients. Internal errors are not detailed.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ErrorResponse {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The failed constraints of each field, for validation errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Object>))]
+    pub fields: Option<validator::ValidationErrors>,
+}
+
+impl From<&Error> for ErrorResponse {
+    fn from(error: &Error) -> Self {
+        let kind = error.kind();
+        let message = match kind {
+            ErrorKind::Internal => "Internal error".to_string(),
+            _ => error.to_string(),
+        };
+        let fields = match error {
+            Error::Validation(errors) => Some(errors.clone()),
+            _ => None,
+        };
+
+        ErrorResponse {
+            kind,
+            message,
+            fields,
+        }
+    }
+}
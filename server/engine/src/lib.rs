@@ -1,10 +1,19 @@
+pub mod algorithm;
 pub mod api;
+pub mod change;
 pub mod constant;
+pub mod cypher;
+pub mod datalog;
 pub mod error;
 pub mod pattern;
 pub mod dto;
 pub mod schema;
 pub mod model;
+pub mod rpq;
+#[cfg(feature = "openapi")]
+mod openapi;
+pub mod traversal;
+pub mod webhook;
 
 use diesel::pg::PgConnection;
 use diesel::prelude::*;
@@ -1,9 +1,17 @@
+use std::io::Write;
+
 use chrono::NaiveDateTime;
+use diesel::deserialize::{self, FromSql, FromSqlRow};
+use diesel::expression::AsExpression;
+use diesel::pg::{Pg, PgValue};
 use diesel::prelude::*;
+use diesel::serialize::{self, IsNull, Output, ToSql};
+use diesel::sql_types::Varchar;
 use diesel::Selectable;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Queryable, Selectable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[diesel(table_name = crate::schema::vertex)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Vertex {
@@ -15,9 +23,18 @@ pub struct Vertex {
     pub created_at: NaiveDateTime,
     pub updated_by: String,
     pub updated_at: NaiveDateTime,
+    pub deleted_by: Option<String>,
+    pub deleted_at: Option<NaiveDateTime>,
+    #[serde(default = "initial_version")]
+    pub version: i32,
+    /// All labels of the vertex, sorted. Always holds the type, which is the
+    /// primary label.
+    #[serde(default)]
+    pub labels: Vec<String>,
 }
 
-#[derive(Debug, Serialize, Queryable, Selectable)]
+#[derive(Debug, Clone, Serialize, Deserialize, Queryable, Selectable)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[diesel(table_name = crate::schema::edge)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Edge {
@@ -31,4 +48,430 @@ pub struct Edge {
     pub created_at: NaiveDateTime,
     pub updated_by: String,
     pub updated_at: NaiveDateTime,
+    pub deleted_by: Option<String>,
+    pub deleted_at: Option<NaiveDateTime>,
+    #[serde(default = "initial_version")]
+    pub version: i32,
+    /// Set on edges materialized by a Datalog rule.
+    #[serde(default)]
+    pub derived: bool,
+    /// Set on the edge maintained as the other direction of an edge with an
+    /// inverse or symmetric label.
+    #[serde(default)]
+    pub mirror_of: Option<i32>,
+    /// Undirected edges are followed both ways and stored with the smaller
+    /// vertex id as `from_vertex_id`.
+    #[serde(default)]
+    pub undirected: bool,
+    /// Tells apart parallel edges of a multigraph label.
+    pub edge_key: Option<String>,
+    #[serde(default = "empty_properties")]
+    #[cfg_attr(feature = "openapi", schema(value_type = Object))]
+    pub properties: serde_json::Value,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+    /// When the relation starts to hold in the domain, if not always.
+    pub valid_from: Option<NaiveDateTime>,
+    /// When the relation stops holding, if ever.
+    pub valid_to: Option<NaiveDateTime>,
+}
+
+impl Vertex {
+    /// Entity tag for HTTP caching and `If-Match` preconditions.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    pub fn has_label(&self, label: &str) -> bool {
+        self.type_ == label || self.labels.iter().any(|other| other == label)
+    }
+}
+
+impl Edge {
+    /// Entity tag for HTTP caching and `If-Match` preconditions.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.version)
+    }
+
+    pub fn validity(&self) -> Interval {
+        Interval {
+            from: self.valid_from,
+            to: self.valid_to,
+        }
+    }
+}
+
+/// A half-open span of domain time, `[from, to)`. A missing bound leaves
+/// that side unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct Interval {
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
+
+impl Interval {
+    pub fn contains(&self, instant: NaiveDateTime) -> bool {
+        self.from.is_none_or(|from| from <= instant) && self.to.is_none_or(|to| instant < to)
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        starts_before(self.from, other.to) && starts_before(other.from, self.to)
+    }
+
+    /// Whether no instant at all is in the interval.
+    pub fn is_empty(&self) -> bool {
+        !starts_before(self.from, self.to)
+    }
+}
+
+fn starts_before(from: Option<NaiveDateTime>, to: Option<NaiveDateTime>) -> bool {
+    match (from, to) {
+        (Some(from), Some(to)) => from < to,
+        _ => true,
+    }
+}
+
+/// Reads the version out of an `If-Match` header value produced from
+/// [`Vertex::etag`] or [`Edge::etag`]. Weak tags are accepted.
+pub fn version_from_etag(etag: &str) -> Option<i32> {
+    let etag = etag.trim();
+    let etag = etag.strip_prefix("W/").unwrap_or(etag);
+    etag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()
+}
+
+// Rows logged before versioning was introduced carry no version.
+fn initial_version() -> i32 {
+    1
+}
+
+// Nor do rows logged before edges had properties carry any.
+fn empty_properties() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+// Nor a weight; every edge weighed 1 then.
+fn default_weight() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::vertex_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VertexHistory {
+    pub id: i64,
+    pub vertex_id: i32,
+    pub operation: String,
+    pub old_row: Option<serde_json::Value>,
+    pub new_row: Option<serde_json::Value>,
+    pub changed_by: String,
+    pub changed_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::edge_history)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EdgeHistory {
+    pub id: i64,
+    pub edge_id: i32,
+    pub operation: String,
+    pub old_row: Option<serde_json::Value>,
+    pub new_row: Option<serde_json::Value>,
+    pub changed_by: String,
+    pub changed_at: NaiveDateTime,
+}
+
+/// What happens to the edges of a label when one of their vertices is deleted.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletePolicy {
+    /// The edges are trashed and restored together with the vertex, and
+    /// removed by the database when it is.
+    #[default]
+    Cascade,
+    /// The vertex cannot be deleted while it still has such edges.
+    Restrict,
+    /// The edges are removed for good while the vertex goes to the trash, so
+    /// restoring the vertex does not bring them back.
+    Detach,
+}
+
+impl DeletePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeletePolicy::Cascade => "cascade",
+            DeletePolicy::Restrict => "restrict",
+            DeletePolicy::Detach => "detach",
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for DeletePolicy {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for DeletePolicy {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Varchar, Pg>>::from_sql(bytes)?.as_str() {
+            "cascade" => Ok(DeletePolicy::Cascade),
+            "restrict" => Ok(DeletePolicy::Restrict),
+            "detach" => Ok(DeletePolicy::Detach),
+            other => Err(format!("Unrecognized delete policy: {other}").into()),
+        }
+    }
+}
+
+/// How the properties of an edge re-pointed by a vertex merge are combined
+/// with those of the edge it collides with, which is the one kept.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[diesel(sql_type = Varchar)]
+#[serde(rename_all = "snake_case")]
+pub enum PropertyMerge {
+    /// The kept edge keeps its properties as they are.
+    KeepSurvivor,
+    /// Properties only the duplicate edge has are added to the kept edge.
+    #[default]
+    PreferSurvivor,
+    /// The properties of the duplicate edge overwrite those of the kept edge.
+    PreferDuplicate,
+}
+
+impl PropertyMerge {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PropertyMerge::KeepSurvivor => "keep_survivor",
+            PropertyMerge::PreferSurvivor => "prefer_survivor",
+            PropertyMerge::PreferDuplicate => "prefer_duplicate",
+        }
+    }
+}
+
+impl ToSql<Varchar, Pg> for PropertyMerge {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.as_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Varchar, Pg> for PropertyMerge {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match <String as FromSql<Varchar, Pg>>::from_sql(bytes)?.as_str() {
+            "keep_survivor" => Ok(PropertyMerge::KeepSurvivor),
+            "prefer_survivor" => Ok(PropertyMerge::PreferSurvivor),
+            "prefer_duplicate" => Ok(PropertyMerge::PreferDuplicate),
+            other => Err(format!("Unrecognized property merge: {other}").into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::edge_label_setting)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EdgeLabelSetting {
+    pub label: String,
+    pub delete_policy: DeletePolicy,
+    pub inverse_label: Option<String>,
+    pub symmetric: bool,
+    pub allow_self_loops: bool,
+    pub multigraph: bool,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// An alternate name of a vertex, unique within the vertex type.
+#[derive(Debug, Clone, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::vertex_alias)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VertexAlias {
+    pub id: i32,
+    pub vertex_id: i32,
+    pub alias: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+}
+
+/// The audit record of a vertex merge.
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::vertex_merge)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct VertexMerge {
+    pub id: i32,
+    pub survivor_id: i32,
+    /// The merged vertices as they were before the merge.
+    pub duplicates: serde_json::Value,
+    pub property_merge: PropertyMerge,
+    /// Edges moved over to the survivor as they were.
+    pub repointed_edges: i32,
+    /// Edges that collided with an edge of the survivor, or of another
+    /// duplicate, and were merged into it.
+    pub merged_edges: i32,
+    /// Edges between the merged vertices, which would have become self-loops.
+    pub dropped_edges: i32,
+    /// Ids of the dropped edges, which were moved to the trash.
+    pub dropped_edge_ids: Vec<i32>,
+    pub merged_at: NaiveDateTime,
+    pub merged_by: String,
+}
+
+/// One source's claim that an edge holds. Assertions always hang off the
+/// edge itself, never off its mirror.
+#[derive(Debug, Clone, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::edge_assertion)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct EdgeAssertion {
+    pub id: i64,
+    pub edge_id: i32,
+    pub source: String,
+    pub confidence: f64,
+    /// References to what the source based the assertion on, as it gave them.
+    pub evidence: serde_json::Value,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+/// What retracting a source removed.
+#[derive(Debug, Serialize)]
+pub struct Retraction {
+    pub assertions: usize,
+    /// The edges moved to the trash because no source asserts them anymore.
+    pub trashed_edge_ids: Vec<i32>,
+}
+
+/// The incident edges of one label, as seen by a vertex delete.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct IncidentLabel {
+    pub label: String,
+    pub delete_policy: DeletePolicy,
+    pub edge_count: i64,
+}
+
+/// What a vertex delete removes, or would remove when run as a dry run.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct DeletePreview {
+    pub vertex: Vertex,
+    pub incident_labels: Vec<IncidentLabel>,
+    pub neighbors: Vec<Vertex>,
+    pub deleted: bool,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::outbox)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event: serde_json::Value,
+    pub created_at: NaiveDateTime,
+    pub dispatched_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub filter: serde_json::Value,
+    pub active: bool,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub updated_by: String,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook_delivery)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub outbox_id: i64,
+    pub attempts: i32,
+    pub next_attempt_at: NaiveDateTime,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::webhook_dead_letter)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct WebhookDeadLetter {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub outbox_id: i64,
+    pub event: serde_json::Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A path found by a weighted search, with the total weight of its edges.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WeightedPath {
+    pub vertex_ids: Vec<i32>,
+    pub edge_ids: Vec<i32>,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VertexRank {
+    pub vertex_id: i32,
+    pub rank: f64,
+}
+
+/// A walk through the graph: `edge_ids[i]` connects `vertex_ids[i]` and
+/// `vertex_ids[i + 1]`, in either direction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, QueryableByName)]
+pub struct Path {
+    #[diesel(sql_type = diesel::sql_types::Array<diesel::sql_types::Int4>)]
+    pub vertex_ids: Vec<i32>,
+    #[diesel(sql_type = diesel::sql_types::Array<diesel::sql_types::Int4>)]
+    pub edge_ids: Vec<i32>,
+}
+
+#[derive(Debug, Serialize, Queryable, Selectable)]
+#[diesel(table_name = crate::schema::datalog_rule)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DatalogRule {
+    pub id: i32,
+    pub rule: String,
+    pub head_label: String,
+    pub body_labels: Vec<Option<String>>,
+    pub created_at: NaiveDateTime,
+    pub created_by: String,
+}
+
+/// The live edges of one label from one vertex to another.
+#[derive(Debug, Serialize, QueryableByName)]
+pub struct ParallelEdges {
+    #[diesel(sql_type = diesel::sql_types::Int4)]
+    pub from_vertex_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Int4)]
+    pub to_vertex_id: i32,
+    #[diesel(sql_type = diesel::sql_types::Text)]
+    pub label: String,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub edge_count: i64,
+    /// The total of the summed property over the edges where it is a number.
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Double>)]
+    pub sum: Option<f64>,
 }
@@ -0,0 +1,25 @@
+use regex::Regex;
+use utoipa::openapi::schema::{Object, ObjectBuilder, Type};
+
+use crate::constant::{MAX_EDGE_LABEL_LENGTH, MAX_USERNAME_LENGTH, MIN_IDENTIFIER_LENGTH};
+use crate::pattern::{EDGE_LABEL_LIKE, USERNAME_LIKE};
+
+// The `*_LIKE` patterns are only known at runtime, so the schemas of the
+// fields validated against them are built here rather than derived.
+
+pub(crate) fn username_like() -> Object {
+    like(&USERNAME_LIKE, MAX_USERNAME_LENGTH)
+}
+
+pub(crate) fn edge_label_like() -> Object {
+    like(&EDGE_LABEL_LIKE, MAX_EDGE_LABEL_LENGTH)
+}
+
+fn like(pattern: &Regex, max_length: usize) -> Object {
+    ObjectBuilder::new()
+        .schema_type(Type::String)
+        .pattern(Some(pattern.as_str()))
+        .min_length(Some(MIN_IDENTIFIER_LENGTH))
+        .max_length(Some(max_length))
+        .build()
+}
@@ -0,0 +1,545 @@
+//! Regular path queries: which vertices are connected by a walk whose edge
+//! labels spell a word of a regular expression, e.g. `part_of+/located_in`.
+//!
+//! | syntax    | matches                                    |
+//! |-----------|--------------------------------------------|
+//! | `label`   | one edge with that label, followed forward |
+//! | `^label`  | one edge with that label, followed back    |
+//! | `a/b`     | `a` then `b`                               |
+//! | `a\|b`    | `a` or `b`                                 |
+//! | `a*`      | zero or more `a`                           |
+//! | `a+`      | one or more `a`                            |
+//! | `a?`      | zero or one `a`                            |
+//!
+//! `^` also applies to groups: `^(a/b)` is `^b/^a`. Expressions compile to
+//! an epsilon-free automaton whose product with the graph is walked by one
+//! recursive query, at most `max_depth` edges deep.
+
+use std::collections::BTreeSet;
+
+use crate::{constant::MAX_PATH_LENGTH, error::Error, model::Path};
+use diesel::sql_types::{Array, Bool, Int4, Text};
+use diesel::QueryableByName;
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathExpression {
+    Label { label: String, inverse: bool },
+    Sequence(Box<PathExpression>, Box<PathExpression>),
+    Alternation(Box<PathExpression>, Box<PathExpression>),
+    ZeroOrMore(Box<PathExpression>),
+    OneOrMore(Box<PathExpression>),
+    ZeroOrOne(Box<PathExpression>),
+}
+
+impl PathExpression {
+    pub fn parse(text: &str) -> Result<PathExpression, Error> {
+        let mut parser = Parser {
+            chars: text.chars().collect(),
+            position: 0,
+        };
+        let expression = parser.alternation()?;
+        if let Some(c) = parser.peek() {
+            return Err(query_error(&format!("unexpected '{c}'")));
+        }
+
+        Ok(expression)
+    }
+
+    /// The expression matching the reversed walks.
+    fn inverse(self) -> PathExpression {
+        match self {
+            PathExpression::Label { label, inverse } => PathExpression::Label {
+                label,
+                inverse: !inverse,
+            },
+            PathExpression::Sequence(first, second) => {
+                PathExpression::Sequence(Box::new(second.inverse()), Box::new(first.inverse()))
+            }
+            PathExpression::Alternation(left, right) => {
+                PathExpression::Alternation(Box::new(left.inverse()), Box::new(right.inverse()))
+            }
+            PathExpression::ZeroOrMore(inner) => {
+                PathExpression::ZeroOrMore(Box::new(inner.inverse()))
+            }
+            PathExpression::OneOrMore(inner) => {
+                PathExpression::OneOrMore(Box::new(inner.inverse()))
+            }
+            PathExpression::ZeroOrOne(inner) => {
+                PathExpression::ZeroOrOne(Box::new(inner.inverse()))
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    fn alternation(&mut self) -> Result<PathExpression, Error> {
+        let mut expression = self.sequence()?;
+        while self.eat('|') {
+            expression =
+                PathExpression::Alternation(Box::new(expression), Box::new(self.sequence()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn sequence(&mut self) -> Result<PathExpression, Error> {
+        let mut expression = self.repetition()?;
+        while self.eat('/') {
+            expression =
+                PathExpression::Sequence(Box::new(expression), Box::new(self.repetition()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn repetition(&mut self) -> Result<PathExpression, Error> {
+        let mut expression = self.primary()?;
+        loop {
+            expression = if self.eat('*') {
+                PathExpression::ZeroOrMore(Box::new(expression))
+            } else if self.eat('+') {
+                PathExpression::OneOrMore(Box::new(expression))
+            } else if self.eat('?') {
+                PathExpression::ZeroOrOne(Box::new(expression))
+            } else {
+                return Ok(expression);
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<PathExpression, Error> {
+        if self.eat('^') {
+            return Ok(self.primary()?.inverse());
+        }
+
+        if self.eat('(') {
+            let expression = self.alternation()?;
+            if !self.eat(')') {
+                return Err(query_error("expected ')'"));
+            }
+            return Ok(expression);
+        }
+
+        let start = self.position;
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_ascii_alphanumeric() || *c == '_')
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(match self.peek() {
+                Some(c) => query_error(&format!("unexpected '{c}'")),
+                None => query_error("unexpected end of path expression"),
+            });
+        }
+
+        Ok(PathExpression::Label {
+            label: self.chars[start..self.position].iter().collect(),
+            inverse: false,
+        })
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .chars
+            .get(self.position)
+            .is_some_and(|c| c.is_whitespace())
+        {
+            self.position += 1;
+        }
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A Glushkov automaton: state 0 is the start, state `p > 0` is reached by
+/// taking the edge of the `p`-th label occurrence in the expression.
+#[derive(Debug, PartialEq, Eq)]
+struct Automaton {
+    /// `(from_state, label, inverse, to_state)`
+    transitions: Vec<(i32, String, bool, i32)>,
+    accepting: Vec<i32>,
+}
+
+impl Automaton {
+    fn new(expression: &PathExpression) -> Automaton {
+        let mut symbols = vec![(String::new(), false)];
+        let mut follow = vec![BTreeSet::new()];
+        let (nullable, first, last) = Self::analyze(expression, &mut symbols, &mut follow);
+
+        let mut transitions = Vec::new();
+        let successors = std::iter::once((0, &first)).chain(follow.iter().enumerate().skip(1));
+        for (from, targets) in successors {
+            for to in targets {
+                let (label, inverse) = &symbols[*to];
+                transitions.push((from as i32, label.clone(), *inverse, *to as i32));
+            }
+        }
+
+        let mut accepting = last.iter().map(|state| *state as i32).collect::<Vec<_>>();
+        if nullable {
+            accepting.insert(0, 0);
+        }
+
+        Automaton {
+            transitions,
+            accepting,
+        }
+    }
+
+    /// Returns whether `expression` matches the empty walk, and the label
+    /// occurrences that can start and end a match; records which
+    /// occurrences can follow each other in `follow`.
+    fn analyze(
+        expression: &PathExpression,
+        symbols: &mut Vec<(String, bool)>,
+        follow: &mut Vec<BTreeSet<usize>>,
+    ) -> (bool, BTreeSet<usize>, BTreeSet<usize>) {
+        match expression {
+            PathExpression::Label { label, inverse } => {
+                let position = symbols.len();
+                symbols.push((label.clone(), *inverse));
+                follow.push(BTreeSet::new());
+                (
+                    false,
+                    BTreeSet::from([position]),
+                    BTreeSet::from([position]),
+                )
+            }
+            PathExpression::Sequence(first, second) => {
+                let (first_nullable, first_first, first_last) =
+                    Self::analyze(first, symbols, follow);
+                let (second_nullable, second_first, second_last) =
+                    Self::analyze(second, symbols, follow);
+                for position in &first_last {
+                    follow[*position].extend(&second_first);
+                }
+
+                let mut starts = first_first;
+                if first_nullable {
+                    starts.extend(&second_first);
+                }
+                let mut ends = second_last;
+                if second_nullable {
+                    ends.extend(&first_last);
+                }
+                (first_nullable && second_nullable, starts, ends)
+            }
+            PathExpression::Alternation(left, right) => {
+                let (left_nullable, mut starts, mut ends) = Self::analyze(left, symbols, follow);
+                let (right_nullable, right_first, right_last) =
+                    Self::analyze(right, symbols, follow);
+                starts.extend(right_first);
+                ends.extend(right_last);
+                (left_nullable || right_nullable, starts, ends)
+            }
+            PathExpression::ZeroOrMore(inner) | PathExpression::OneOrMore(inner) => {
+                let (nullable, starts, ends) = Self::analyze(inner, symbols, follow);
+                for position in &ends {
+                    follow[*position].extend(&starts);
+                }
+                let nullable = nullable || matches!(expression, PathExpression::ZeroOrMore(_));
+                (nullable, starts, ends)
+            }
+            PathExpression::ZeroOrOne(inner) => {
+                let (_, starts, ends) = Self::analyze(inner, symbols, follow);
+                (true, starts, ends)
+            }
+        }
+    }
+}
+
+#[derive(QueryableByName)]
+struct Pair {
+    #[diesel(sql_type = Int4)]
+    source_id: i32,
+    #[diesel(sql_type = Int4)]
+    vertex_id: i32,
+}
+
+// The transition table of the automaton is passed in as parallel arrays.
+const TRANSITIONS: &str = "transition(from_state, label, inverse, to_state) AS ( \
+        SELECT * FROM unnest($1, $2, $3, $4) \
+    )";
+
+const STEP: &str = "FROM walk AS w \
+    JOIN transition AS t ON t.from_state = w.state \
+    JOIN edge AS e ON e.label = t.label AND e.deleted_at IS NULL AND ( \
+        ((NOT t.inverse OR e.undirected) AND e.from_vertex_id = w.vertex_id) \
+        OR ((t.inverse OR e.undirected) AND e.to_vertex_id = w.vertex_id) \
+    )";
+
+// The vertex a step ends on. Undirected edges are taken either way.
+const NEXT_VERTEX: &str =
+    "CASE WHEN (NOT t.inverse OR e.undirected) AND e.from_vertex_id = w.vertex_id \
+    THEN e.to_vertex_id ELSE e.from_vertex_id END";
+
+/// Returns the `(source, target)` pairs of vertices connected by a walk of
+/// at most `max_depth` edges matching `expression`, for every source in
+/// `source_ids`.
+pub async fn find_pairs(
+    conn: &mut AsyncPgConnection,
+    expression: &str,
+    source_ids: &[i32],
+    max_depth: u32,
+) -> Result<Vec<(i32, i32)>, Error> {
+    let automaton = compile(expression, source_ids, max_depth)?;
+    let (from_states, labels, inverses, to_states) = transition_columns(&automaton);
+
+    let pairs = diesel::sql_query(format!(
+        "WITH RECURSIVE {TRANSITIONS}, \
+         walk(source_id, vertex_id, state, depth) AS ( \
+            SELECT id, id, 0, 0 FROM vertex WHERE deleted_at IS NULL AND id = ANY($5) \
+            UNION \
+            SELECT w.source_id, \
+                   {NEXT_VERTEX}, \
+                   t.to_state, w.depth + 1 \
+            {STEP} \
+            WHERE w.depth < $6 \
+         ) \
+         SELECT DISTINCT source_id, vertex_id FROM walk \
+         WHERE state = ANY($7) \
+         ORDER BY source_id, vertex_id"
+    ))
+    .bind::<Array<Int4>, _>(from_states)
+    .bind::<Array<Text>, _>(labels)
+    .bind::<Array<Bool>, _>(inverses)
+    .bind::<Array<Int4>, _>(to_states)
+    .bind::<Array<Int4>, _>(source_ids)
+    .bind::<Int4, _>(max_depth as i32)
+    .bind::<Array<Int4>, _>(&automaton.accepting)
+    .load::<Pair>(conn)
+    .await?;
+
+    Ok(pairs
+        .into_iter()
+        .map(|pair| (pair.source_id, pair.vertex_id))
+        .collect())
+}
+
+/// Returns the walks of at most `max_depth` edges matching `expression`
+/// that start at one of `source_ids`. A walk never takes the same edge
+/// twice.
+pub async fn find_paths(
+    conn: &mut AsyncPgConnection,
+    expression: &str,
+    source_ids: &[i32],
+    max_depth: u32,
+) -> Result<Vec<Path>, Error> {
+    let automaton = compile(expression, source_ids, max_depth)?;
+    let (from_states, labels, inverses, to_states) = transition_columns(&automaton);
+
+    let paths = diesel::sql_query(format!(
+        "WITH RECURSIVE {TRANSITIONS}, \
+         walk(vertex_id, state, vertex_ids, edge_ids) AS ( \
+            SELECT id, 0, ARRAY[id], ARRAY[]::INT[] FROM vertex \
+            WHERE deleted_at IS NULL AND id = ANY($5) \
+            UNION ALL \
+            SELECT {NEXT_VERTEX}, \
+                   t.to_state, \
+                   w.vertex_ids || {NEXT_VERTEX}, \
+                   w.edge_ids || e.id \
+            {STEP} \
+            WHERE cardinality(w.edge_ids) < $6 AND e.id <> ALL(w.edge_ids) \
+         ) \
+         SELECT DISTINCT vertex_ids, edge_ids FROM walk \
+         WHERE state = ANY($7) \
+         ORDER BY vertex_ids, edge_ids"
+    ))
+    .bind::<Array<Int4>, _>(from_states)
+    .bind::<Array<Text>, _>(labels)
+    .bind::<Array<Bool>, _>(inverses)
+    .bind::<Array<Int4>, _>(to_states)
+    .bind::<Array<Int4>, _>(source_ids)
+    .bind::<Int4, _>(max_depth as i32)
+    .bind::<Array<Int4>, _>(&automaton.accepting)
+    .load::<Path>(conn)
+    .await?;
+
+    Ok(paths)
+}
+
+fn compile(expression: &str, source_ids: &[i32], max_depth: u32) -> Result<Automaton, Error> {
+    if source_ids.iter().any(|source_id| *source_id < 1) || max_depth > MAX_PATH_LENGTH {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    Ok(Automaton::new(&PathExpression::parse(expression)?))
+}
+
+fn transition_columns(automaton: &Automaton) -> (Vec<i32>, Vec<String>, Vec<bool>, Vec<i32>) {
+    let mut columns = (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+    for (from, label, inverse, to) in &automaton.transitions {
+        columns.0.push(*from);
+        columns.1.push(label.clone());
+        columns.2.push(*inverse);
+        columns.3.push(*to);
+    }
+
+    columns
+}
+
+fn query_error(message: &str) -> Error {
+    Error::Query(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+
+    use crate::dto::{NewEdge, NewVertex};
+    use crate::error::Error;
+    use crate::rpq::{Automaton, PathExpression};
+
+    #[test]
+    fn test_automaton() {
+        let expression = PathExpression::parse("^(a/b) | c*").unwrap();
+        assert_eq!(
+            expression,
+            PathExpression::Alternation(
+                Box::new(PathExpression::Sequence(
+                    Box::new(PathExpression::Label {
+                        label: "b".to_string(),
+                        inverse: true
+                    }),
+                    Box::new(PathExpression::Label {
+                        label: "a".to_string(),
+                        inverse: true
+                    }),
+                )),
+                Box::new(PathExpression::ZeroOrMore(Box::new(
+                    PathExpression::Label {
+                        label: "c".to_string(),
+                        inverse: false
+                    }
+                ))),
+            )
+        );
+
+        let automaton = Automaton::new(&expression);
+        assert_eq!(
+            automaton.transitions,
+            vec![
+                (0, "b".to_string(), true, 1),
+                (0, "c".to_string(), false, 3),
+                (1, "a".to_string(), true, 2),
+                (3, "c".to_string(), false, 3),
+            ]
+        );
+        assert_eq!(automaton.accepting, vec![0, 2, 3]);
+
+        for invalid in ["", "a/", "(a", "a||b", "a-b"] {
+            assert!(
+                matches!(PathExpression::parse(invalid), Err(Error::Query(_))),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_pairs_and_paths() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for name in ["rpq_room", "rpq_floor", "rpq_building", "rpq_city"] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: "rpq_place".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // room part_of floor part_of building located_in city
+        let mut edges = Vec::new();
+        for (from, to, label) in [
+            (0, 1, "rpq_part_of"),
+            (1, 2, "rpq_part_of"),
+            (2, 3, "rpq_located_in"),
+        ] {
+            let new_edge = NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: label.to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            edges.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let sources = [vertices[0].id, vertices[1].id];
+        let result = crate::rpq::find_pairs(&mut conn, "rpq_part_of+/rpq_located_in", &sources, 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                (vertices[0].id, vertices[3].id),
+                (vertices[1].id, vertices[3].id)
+            ]
+        );
+
+        // Too shallow to get from the room to the city.
+        let result = crate::rpq::find_pairs(&mut conn, "rpq_part_of+/rpq_located_in", &sources, 2)
+            .await
+            .unwrap();
+        assert_eq!(result, vec![(vertices[1].id, vertices[3].id)]);
+
+        let result = crate::rpq::find_pairs(&mut conn, "^rpq_part_of*", &[vertices[2].id], 5)
+            .await
+            .unwrap();
+        assert_eq!(
+            result,
+            vec![
+                (vertices[2].id, vertices[0].id),
+                (vertices[2].id, vertices[1].id),
+                (vertices[2].id, vertices[2].id),
+            ]
+        );
+
+        let result = crate::rpq::find_paths(
+            &mut conn,
+            "rpq_part_of+/rpq_located_in",
+            &[vertices[0].id],
+            5,
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].vertex_ids,
+            vertices.iter().map(|vertex| vertex.id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            result[0].edge_ids,
+            edges.iter().map(|edge| edge.id).collect::<Vec<_>>()
+        );
+
+        let result = crate::rpq::find_pairs(&mut conn, "rpq_part_of", &[0], 5).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+}
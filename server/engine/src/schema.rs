@@ -1,5 +1,45 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    datalog_dirty_label (label) {
+        #[max_length = 255]
+        label -> Varchar,
+    }
+}
+
+diesel::table! {
+    datalog_fact (label, from_vertex_id, to_vertex_id) {
+        #[max_length = 255]
+        label -> Varchar,
+        from_vertex_id -> Int4,
+        to_vertex_id -> Int4,
+        asserted -> Bool,
+    }
+}
+
+diesel::table! {
+    datalog_fact_change (id) {
+        id -> Int8,
+        #[max_length = 255]
+        label -> Varchar,
+        from_vertex_id -> Int4,
+        to_vertex_id -> Int4,
+    }
+}
+
+diesel::table! {
+    datalog_rule (id) {
+        id -> Int4,
+        rule -> Text,
+        #[max_length = 255]
+        head_label -> Varchar,
+        body_labels -> Array<Nullable<Varchar>>,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        created_by -> Varchar,
+    }
+}
+
 diesel::table! {
     edge (id) {
         id -> Int4,
@@ -17,6 +57,79 @@ diesel::table! {
         created_by -> Varchar,
         #[max_length = 255]
         updated_by -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 255]
+        deleted_by -> Nullable<Varchar>,
+        version -> Int4,
+        derived -> Bool,
+        mirror_of -> Nullable<Int4>,
+        undirected -> Bool,
+        #[max_length = 255]
+        edge_key -> Nullable<Varchar>,
+        properties -> Jsonb,
+        weight -> Float8,
+        valid_from -> Nullable<Timestamp>,
+        valid_to -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    edge_assertion (id) {
+        id -> Int8,
+        edge_id -> Int4,
+        #[max_length = 255]
+        source -> Varchar,
+        confidence -> Float8,
+        evidence -> Jsonb,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        created_by -> Varchar,
+        updated_at -> Timestamp,
+        #[max_length = 255]
+        updated_by -> Varchar,
+    }
+}
+
+diesel::table! {
+    edge_history (id) {
+        id -> Int8,
+        edge_id -> Int4,
+        #[max_length = 6]
+        operation -> Varchar,
+        old_row -> Nullable<Jsonb>,
+        new_row -> Nullable<Jsonb>,
+        #[max_length = 255]
+        changed_by -> Varchar,
+        changed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    edge_label_setting (label) {
+        #[max_length = 255]
+        label -> Varchar,
+        #[max_length = 8]
+        delete_policy -> Varchar,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        created_by -> Varchar,
+        updated_at -> Timestamp,
+        #[max_length = 255]
+        updated_by -> Varchar,
+        #[max_length = 255]
+        inverse_label -> Nullable<Varchar>,
+        symmetric -> Bool,
+        allow_self_loops -> Bool,
+        multigraph -> Bool,
+    }
+}
+
+diesel::table! {
+    outbox (id) {
+        id -> Int8,
+        event -> Jsonb,
+        created_at -> Timestamp,
+        dispatched_at -> Nullable<Timestamp>,
     }
 }
 
@@ -34,10 +147,124 @@ diesel::table! {
         updated_at -> Timestamp,
         #[max_length = 255]
         updated_by -> Varchar,
+        deleted_at -> Nullable<Timestamp>,
+        #[max_length = 255]
+        deleted_by -> Nullable<Varchar>,
+        version -> Int4,
+        labels -> Array<Text>,
     }
 }
 
+diesel::table! {
+    vertex_alias (id) {
+        id -> Int4,
+        vertex_id -> Int4,
+        #[max_length = 255]
+        alias -> Varchar,
+        #[sql_name = "type"]
+        #[max_length = 255]
+        type_ -> Varchar,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        created_by -> Varchar,
+    }
+}
+
+diesel::table! {
+    vertex_history (id) {
+        id -> Int8,
+        vertex_id -> Int4,
+        #[max_length = 6]
+        operation -> Varchar,
+        old_row -> Nullable<Jsonb>,
+        new_row -> Nullable<Jsonb>,
+        #[max_length = 255]
+        changed_by -> Varchar,
+        changed_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    vertex_merge (id) {
+        id -> Int4,
+        survivor_id -> Int4,
+        duplicates -> Jsonb,
+        #[max_length = 16]
+        property_merge -> Varchar,
+        repointed_edges -> Int4,
+        merged_edges -> Int4,
+        dropped_edges -> Int4,
+        dropped_edge_ids -> Array<Int4>,
+        merged_at -> Timestamp,
+        #[max_length = 255]
+        merged_by -> Varchar,
+    }
+}
+
+diesel::table! {
+    webhook (id) {
+        id -> Int4,
+        #[max_length = 2048]
+        url -> Varchar,
+        #[max_length = 255]
+        secret -> Varchar,
+        filter -> Jsonb,
+        active -> Bool,
+        created_at -> Timestamp,
+        #[max_length = 255]
+        created_by -> Varchar,
+        updated_at -> Timestamp,
+        #[max_length = 255]
+        updated_by -> Varchar,
+    }
+}
+
+diesel::table! {
+    webhook_dead_letter (id) {
+        id -> Int8,
+        webhook_id -> Int4,
+        outbox_id -> Int8,
+        event -> Jsonb,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    webhook_delivery (id) {
+        id -> Int8,
+        webhook_id -> Int4,
+        outbox_id -> Int8,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(edge_assertion -> edge (edge_id));
+diesel::joinable!(vertex_alias -> vertex (vertex_id));
+diesel::joinable!(webhook_dead_letter -> webhook (webhook_id));
+diesel::joinable!(webhook_delivery -> outbox (outbox_id));
+diesel::joinable!(webhook_delivery -> webhook (webhook_id));
+
 diesel::allow_tables_to_appear_in_same_query!(
+    datalog_dirty_label,
+    datalog_fact,
+    datalog_fact_change,
+    datalog_rule,
     edge,
+    edge_assertion,
+    edge_history,
+    edge_label_setting,
+    outbox,
     vertex,
+    vertex_alias,
+    vertex_history,
+    vertex_merge,
+    webhook,
+    webhook_dead_letter,
+    webhook_delivery,
 );
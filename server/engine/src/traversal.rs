@@ -0,0 +1,949 @@
+//! A Gremlin-style fluent traversal builder:
+//!
+//! ```ignore
+//! let friends = g().v(id).out("knows").has_type("Person").dedup().limit(10).to_list(conn).await?;
+//! ```
+//!
+//! Steps are only recorded while the traversal is built. The terminal step
+//! compiles all of them into a single statement of nested subqueries, each
+//! hop holding the hops before it once, so a traversal costs one round trip
+//! however many hops it takes.
+//!
+//! Unlike Gremlin, every step yields each element at most once: a traversal
+//! works on sets of live vertices or edges, so [`VertexTraversal::dedup`]
+//! never changes the result. Results are returned in id order.
+//!
+//! `g().valid_at(instant)` only follows the edges holding in the domain at
+//! that instant, in every step of the traversals it starts.
+
+use crate::{
+    api::valid_at,
+    dto::Direction,
+    error::Error,
+    model::{Edge, Vertex},
+    schema::{edge, vertex, vertex_alias},
+};
+use chrono::NaiveDateTime;
+use diesel::dsl::{case_when, count_star, exists};
+use diesel::pg::Pg;
+use diesel::sql_types::Int4;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, PgArrayExpressionMethods, QueryDsl, SelectableHelper,
+};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+type VertexIds = vertex::BoxedQuery<'static, Pg, Int4>;
+type EdgeIds = edge::BoxedQuery<'static, Pg, Int4>;
+
+/// Where a traversal starts: `g().v(..)` or `g().e(..)`.
+pub fn g() -> GraphTraversalSource {
+    GraphTraversalSource::default()
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphTraversalSource {
+    valid_at: Option<NaiveDateTime>,
+}
+
+/// Element ids to start from.
+#[derive(Debug, Clone)]
+pub struct Ids(Vec<i32>);
+
+/// Edge labels to follow. No labels means any label.
+#[derive(Debug, Clone, Default)]
+pub struct Labels(Vec<String>);
+
+impl From<i32> for Ids {
+    fn from(id: i32) -> Self {
+        Ids(vec![id])
+    }
+}
+
+impl From<&[i32]> for Ids {
+    fn from(ids: &[i32]) -> Self {
+        Ids(ids.to_vec())
+    }
+}
+
+impl<const N: usize> From<[i32; N]> for Ids {
+    fn from(ids: [i32; N]) -> Self {
+        Ids(ids.to_vec())
+    }
+}
+
+impl From<Vec<i32>> for Ids {
+    fn from(ids: Vec<i32>) -> Self {
+        Ids(ids)
+    }
+}
+
+impl From<&str> for Labels {
+    fn from(label: &str) -> Self {
+        Labels(vec![label.to_string()])
+    }
+}
+
+impl From<&[&str]> for Labels {
+    fn from(labels: &[&str]) -> Self {
+        Labels(labels.iter().map(|label| label.to_string()).collect())
+    }
+}
+
+impl<const N: usize> From<[&str; N]> for Labels {
+    fn from(labels: [&str; N]) -> Self {
+        Labels(labels.iter().map(|label| label.to_string()).collect())
+    }
+}
+
+impl From<Vec<String>> for Labels {
+    fn from(labels: Vec<String>) -> Self {
+        Labels(labels)
+    }
+}
+
+impl Labels {
+    /// Follows edges of every label.
+    pub fn any() -> Self {
+        Labels::default()
+    }
+}
+
+/// A bound on edge weights, like Gremlin's `P.gt(0.8)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weight {
+    Gt(f64),
+    Gte(f64),
+    Lt(f64),
+    Lte(f64),
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    /// `None` starts from every live element.
+    Start(Option<Vec<i32>>),
+    Out(Vec<String>),
+    In(Vec<String>),
+    Both(Vec<String>),
+    OutE(Vec<String>),
+    InE(Vec<String>),
+    BothE(Vec<String>),
+    OutV,
+    InV,
+    BothV,
+    HasId(Vec<i32>),
+    HasName(String),
+    HasType(String),
+    HasVertexLabel(String),
+    HasLabel(String),
+    HasWeight(Weight),
+    Limit(i64),
+}
+
+/// A traversal whose current elements are vertices.
+#[derive(Debug, Clone)]
+pub struct VertexTraversal {
+    steps: Vec<Step>,
+    valid_at: Option<NaiveDateTime>,
+}
+
+/// A traversal whose current elements are edges.
+#[derive(Debug, Clone)]
+pub struct EdgeTraversal {
+    steps: Vec<Step>,
+    valid_at: Option<NaiveDateTime>,
+}
+
+impl GraphTraversalSource {
+    /// Restricts the traversals to the edges valid at `instant`.
+    pub fn valid_at(self, instant: NaiveDateTime) -> GraphTraversalSource {
+        GraphTraversalSource {
+            valid_at: Some(instant),
+        }
+    }
+
+    /// Starts from the live vertices among `ids`.
+    pub fn v(&self, ids: impl Into<Ids>) -> VertexTraversal {
+        VertexTraversal {
+            steps: vec![Step::Start(Some(ids.into().0))],
+            valid_at: self.valid_at,
+        }
+    }
+
+    /// Starts from every live vertex.
+    pub fn all_v(&self) -> VertexTraversal {
+        VertexTraversal {
+            steps: vec![Step::Start(None)],
+            valid_at: self.valid_at,
+        }
+    }
+
+    /// Starts from the live edges among `ids`.
+    pub fn e(&self, ids: impl Into<Ids>) -> EdgeTraversal {
+        EdgeTraversal {
+            steps: vec![Step::Start(Some(ids.into().0))],
+            valid_at: self.valid_at,
+        }
+    }
+
+    /// Starts from every live edge.
+    pub fn all_e(&self) -> EdgeTraversal {
+        EdgeTraversal {
+            steps: vec![Step::Start(None)],
+            valid_at: self.valid_at,
+        }
+    }
+}
+
+impl VertexTraversal {
+    /// Moves to the targets of outgoing edges with one of `labels`.
+    pub fn out(self, labels: impl Into<Labels>) -> VertexTraversal {
+        self.then(Step::Out(labels.into().0))
+    }
+
+    /// Moves to the sources of incoming edges with one of `labels`.
+    pub fn in_(self, labels: impl Into<Labels>) -> VertexTraversal {
+        self.then(Step::In(labels.into().0))
+    }
+
+    /// Moves to the vertices on the other end of edges with one of `labels`.
+    pub fn both(self, labels: impl Into<Labels>) -> VertexTraversal {
+        self.then(Step::Both(labels.into().0))
+    }
+
+    pub fn out_e(self, labels: impl Into<Labels>) -> EdgeTraversal {
+        self.then_edges(Step::OutE(labels.into().0))
+    }
+
+    pub fn in_e(self, labels: impl Into<Labels>) -> EdgeTraversal {
+        self.then_edges(Step::InE(labels.into().0))
+    }
+
+    pub fn both_e(self, labels: impl Into<Labels>) -> EdgeTraversal {
+        self.then_edges(Step::BothE(labels.into().0))
+    }
+
+    pub fn has_id(self, ids: impl Into<Ids>) -> VertexTraversal {
+        self.then(Step::HasId(ids.into().0))
+    }
+
+    /// Keeps the vertices going by `name`, as their name or as an alias.
+    pub fn has_name(self, name: &str) -> VertexTraversal {
+        self.then(Step::HasName(name.to_string()))
+    }
+
+    pub fn has_type(self, type_: &str) -> VertexTraversal {
+        self.then(Step::HasType(type_.to_string()))
+    }
+
+    /// Keeps the vertices carrying `label`, whether as their type or not.
+    pub fn has_label(self, label: &str) -> VertexTraversal {
+        self.then(Step::HasVertexLabel(label.to_string()))
+    }
+
+    /// Kept for Gremlin familiarity; traversals never hold duplicates.
+    pub fn dedup(self) -> VertexTraversal {
+        self
+    }
+
+    /// Keeps the `limit` vertices with the lowest ids.
+    pub fn limit(self, limit: i64) -> VertexTraversal {
+        self.then(Step::Limit(limit))
+    }
+
+    pub async fn to_list(&self, conn: &mut AsyncPgConnection) -> Result<Vec<Vertex>, Error> {
+        let ids = vertex_ids(&self.steps, self.valid_at)?;
+        let result = vertex::table
+            .filter(vertex::id.eq_any(ids))
+            .order(vertex::id.asc())
+            .select(Vertex::as_select())
+            .load(conn)
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn count(&self, conn: &mut AsyncPgConnection) -> Result<i64, Error> {
+        let ids = vertex_ids(&self.steps, self.valid_at)?;
+        let result = vertex::table
+            .filter(vertex::id.eq_any(ids))
+            .select(count_star())
+            .first(conn)
+            .await?;
+
+        Ok(result)
+    }
+
+    fn then(mut self, step: Step) -> VertexTraversal {
+        self.steps.push(step);
+        self
+    }
+
+    fn then_edges(mut self, step: Step) -> EdgeTraversal {
+        self.steps.push(step);
+        EdgeTraversal {
+            steps: self.steps,
+            valid_at: self.valid_at,
+        }
+    }
+}
+
+impl EdgeTraversal {
+    /// Moves to the source vertices of the edges.
+    pub fn out_v(self) -> VertexTraversal {
+        self.then_vertices(Step::OutV)
+    }
+
+    /// Moves to the target vertices of the edges.
+    pub fn in_v(self) -> VertexTraversal {
+        self.then_vertices(Step::InV)
+    }
+
+    pub fn both_v(self) -> VertexTraversal {
+        self.then_vertices(Step::BothV)
+    }
+
+    pub fn has_id(self, ids: impl Into<Ids>) -> EdgeTraversal {
+        self.then(Step::HasId(ids.into().0))
+    }
+
+    pub fn has_label(self, label: &str) -> EdgeTraversal {
+        self.then(Step::HasLabel(label.to_string()))
+    }
+
+    /// Keeps the edges whose weight is within `bound`.
+    pub fn has_weight(self, bound: Weight) -> EdgeTraversal {
+        self.then(Step::HasWeight(bound))
+    }
+
+    /// Kept for Gremlin familiarity; traversals never hold duplicates.
+    pub fn dedup(self) -> EdgeTraversal {
+        self
+    }
+
+    /// Keeps the `limit` edges with the lowest ids.
+    pub fn limit(self, limit: i64) -> EdgeTraversal {
+        self.then(Step::Limit(limit))
+    }
+
+    pub async fn to_list(&self, conn: &mut AsyncPgConnection) -> Result<Vec<Edge>, Error> {
+        let ids = edge_ids(&self.steps, self.valid_at)?;
+        let result = edge::table
+            .filter(edge::id.eq_any(ids))
+            .order(edge::id.asc())
+            .select(Edge::as_select())
+            .load(conn)
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn count(&self, conn: &mut AsyncPgConnection) -> Result<i64, Error> {
+        let ids = edge_ids(&self.steps, self.valid_at)?;
+        let result = edge::table
+            .filter(edge::id.eq_any(ids))
+            .select(count_star())
+            .first(conn)
+            .await?;
+
+        Ok(result)
+    }
+
+    fn then(mut self, step: Step) -> EdgeTraversal {
+        self.steps.push(step);
+        self
+    }
+
+    fn then_vertices(mut self, step: Step) -> VertexTraversal {
+        self.steps.push(step);
+        VertexTraversal {
+            steps: self.steps,
+            valid_at: self.valid_at,
+        }
+    }
+}
+
+fn live_vertex_ids() -> VertexIds {
+    vertex::table
+        .select(vertex::id)
+        .filter(vertex::deleted_at.is_null())
+        .into_boxed()
+}
+
+fn live_edge_ids(labels: &[String], instant: Option<NaiveDateTime>) -> EdgeIds {
+    let mut query = edge::table
+        .select(edge::id)
+        .filter(edge::deleted_at.is_null())
+        .into_boxed();
+
+    if let Some(instant) = instant {
+        query = query.filter(valid_at(instant));
+    }
+    if labels.is_empty() {
+        query
+    } else {
+        query.filter(edge::label.eq_any(labels.to_vec()))
+    }
+}
+
+/// The live vertices one hop away from `sources` along live edges of
+/// `labels`. Each edge is matched against the candidate vertex first, and the
+/// endpoint it leaves over is looked up in `sources`, so that `sources` is only
+/// compiled once per hop. Undirected edges are followed against their stored
+/// orientation too.
+fn adjacent_vertex_ids(
+    direction: Direction,
+    labels: &[String],
+    instant: Option<NaiveDateTime>,
+    sources: VertexIds,
+) -> VertexIds {
+    let edges = edge::table.filter(edge::id.eq_any(live_edge_ids(labels, instant)));
+    let arrives = edge::to_vertex_id.eq(vertex::id);
+    let leaves = edge::from_vertex_id.eq(vertex::id);
+
+    let query = live_vertex_ids();
+    match direction {
+        Direction::Outgoing => query.filter(exists(
+            edges
+                .filter(arrives.or(edge::undirected.and(leaves)))
+                .filter(
+                    case_when(arrives, edge::from_vertex_id)
+                        .otherwise(edge::to_vertex_id)
+                        .eq_any(sources),
+                ),
+        )),
+        Direction::Incoming => query.filter(exists(
+            edges
+                .filter(leaves.or(edge::undirected.and(arrives)))
+                .filter(
+                    case_when(leaves, edge::to_vertex_id)
+                        .otherwise(edge::from_vertex_id)
+                        .eq_any(sources),
+                ),
+        )),
+        Direction::Both => query.filter(exists(
+            edges.filter(arrives.or(leaves)).filter(
+                case_when(arrives, edge::from_vertex_id)
+                    .otherwise(edge::to_vertex_id)
+                    .eq_any(sources),
+            ),
+        )),
+    }
+}
+
+/// The live edges of `labels` leaving, entering or touching `sources`, which
+/// is compiled once like in [`adjacent_vertex_ids`].
+fn incident_edge_ids(
+    direction: Direction,
+    labels: &[String],
+    instant: Option<NaiveDateTime>,
+    sources: VertexIds,
+) -> EdgeIds {
+    let sources = vertex::table.filter(vertex::id.eq_any(sources));
+    let from_source = vertex::id.eq(edge::from_vertex_id);
+    let to_source = vertex::id.eq(edge::to_vertex_id);
+
+    let query = live_edge_ids(labels, instant);
+    match direction {
+        Direction::Outgoing => query.filter(exists(
+            sources.filter(from_source.or(edge::undirected.and(to_source))),
+        )),
+        Direction::Incoming => query.filter(exists(
+            sources.filter(to_source.or(edge::undirected.and(from_source))),
+        )),
+        Direction::Both => query.filter(exists(sources.filter(from_source.or(to_source)))),
+    }
+}
+
+/// Compiles `steps`, which end on vertices, into a query of their ids.
+fn vertex_ids(steps: &[Step], instant: Option<NaiveDateTime>) -> Result<VertexIds, Error> {
+    let Some((step, previous)) = steps.split_last() else {
+        unreachable!("a traversal always has a start step");
+    };
+
+    Ok(match step {
+        Step::Start(ids) => {
+            let query = live_vertex_ids();
+            match ids {
+                Some(ids) => {
+                    validate_ids(ids)?;
+                    query.filter(vertex::id.eq_any(ids.clone()))
+                }
+                None => query,
+            }
+        }
+        Step::Out(labels) => adjacent_vertex_ids(
+            Direction::Outgoing,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::In(labels) => adjacent_vertex_ids(
+            Direction::Incoming,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::Both(labels) => adjacent_vertex_ids(
+            Direction::Both,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::OutV => live_vertex_ids().filter(
+            vertex::id.eq_any(
+                edge::table
+                    .select(edge::from_vertex_id)
+                    .filter(edge::id.eq_any(edge_ids(previous, instant)?)),
+            ),
+        ),
+        Step::InV => live_vertex_ids().filter(
+            vertex::id.eq_any(
+                edge::table
+                    .select(edge::to_vertex_id)
+                    .filter(edge::id.eq_any(edge_ids(previous, instant)?)),
+            ),
+        ),
+        Step::BothV => live_vertex_ids().filter(exists(
+            edge::table
+                .filter(edge::id.eq_any(edge_ids(previous, instant)?))
+                .filter(
+                    edge::from_vertex_id
+                        .eq(vertex::id)
+                        .or(edge::to_vertex_id.eq(vertex::id)),
+                ),
+        )),
+        Step::HasId(ids) => vertex_ids(previous, instant)?.filter(vertex::id.eq_any(ids.clone())),
+        Step::HasName(name) => vertex_ids(previous, instant)?.filter(
+            vertex::name.eq(name.clone()).or(vertex::id.eq_any(
+                vertex_alias::table
+                    .select(vertex_alias::vertex_id)
+                    .filter(vertex_alias::alias.eq(name.clone())),
+            )),
+        ),
+        Step::HasType(type_) => {
+            vertex_ids(previous, instant)?.filter(vertex::type_.eq(type_.clone()))
+        }
+        Step::HasVertexLabel(label) => {
+            vertex_ids(previous, instant)?.filter(vertex::labels.contains(vec![label.clone()]))
+        }
+        // Filters after a limit must not be folded into the limited query.
+        Step::Limit(limit) => {
+            validate_limit(*limit)?;
+            live_vertex_ids().filter(
+                vertex::id.eq_any(
+                    vertex_ids(previous, instant)?
+                        .order(vertex::id.asc())
+                        .limit(*limit),
+                ),
+            )
+        }
+        Step::OutE(_) | Step::InE(_) | Step::BothE(_) | Step::HasLabel(_) | Step::HasWeight(_) => {
+            unreachable!("edge step at the end of a vertex traversal")
+        }
+    })
+}
+
+/// Compiles `steps`, which end on edges, into a query of their ids.
+fn edge_ids(steps: &[Step], instant: Option<NaiveDateTime>) -> Result<EdgeIds, Error> {
+    let Some((step, previous)) = steps.split_last() else {
+        unreachable!("a traversal always has a start step");
+    };
+
+    Ok(match step {
+        Step::Start(ids) => {
+            let query = live_edge_ids(&[], instant);
+            match ids {
+                Some(ids) => {
+                    validate_ids(ids)?;
+                    query.filter(edge::id.eq_any(ids.clone()))
+                }
+                None => query,
+            }
+        }
+        Step::OutE(labels) => incident_edge_ids(
+            Direction::Outgoing,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::InE(labels) => incident_edge_ids(
+            Direction::Incoming,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::BothE(labels) => incident_edge_ids(
+            Direction::Both,
+            labels,
+            instant,
+            vertex_ids(previous, instant)?,
+        ),
+        Step::HasId(ids) => edge_ids(previous, instant)?.filter(edge::id.eq_any(ids.clone())),
+        Step::HasLabel(label) => edge_ids(previous, instant)?.filter(edge::label.eq(label.clone())),
+        Step::HasWeight(bound) => {
+            let query = edge_ids(previous, instant)?;
+            match *bound {
+                Weight::Gt(weight) => query.filter(edge::weight.gt(validate_weight(weight)?)),
+                Weight::Gte(weight) => query.filter(edge::weight.ge(validate_weight(weight)?)),
+                Weight::Lt(weight) => query.filter(edge::weight.lt(validate_weight(weight)?)),
+                Weight::Lte(weight) => query.filter(edge::weight.le(validate_weight(weight)?)),
+            }
+        }
+        Step::Limit(limit) => {
+            validate_limit(*limit)?;
+            live_edge_ids(&[], instant).filter(
+                edge::id.eq_any(
+                    edge_ids(previous, instant)?
+                        .order(edge::id.asc())
+                        .limit(*limit),
+                ),
+            )
+        }
+        Step::Out(_)
+        | Step::In(_)
+        | Step::Both(_)
+        | Step::OutV
+        | Step::InV
+        | Step::BothV
+        | Step::HasName(_)
+        | Step::HasType(_)
+        | Step::HasVertexLabel(_) => unreachable!("vertex step at the end of an edge traversal"),
+    })
+}
+
+fn validate_ids(ids: &[i32]) -> Result<(), Error> {
+    if ids.iter().any(|id| *id < 1) {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    Ok(())
+}
+
+fn validate_weight(weight: f64) -> Result<f64, Error> {
+    if !weight.is_finite() {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    Ok(weight)
+}
+
+fn validate_limit(limit: i64) -> Result<(), Error> {
+    if limit < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+
+    use crate::dto::{NewEdge, NewVertex};
+    use crate::error::Error;
+    use crate::traversal::{g, vertex_ids, Labels, Weight};
+
+    const HOPS: usize = 20;
+
+    #[tokio::test]
+    async fn test_traversal() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for (name, type_, labels) in [
+            ("traversal_alice", "traversal_person", vec![]),
+            (
+                "traversal_bob",
+                "traversal_person",
+                vec!["traversal_manager"],
+            ),
+            ("traversal_carol", "traversal_person", vec![]),
+            ("traversal_acme", "traversal_company", vec![]),
+        ] {
+            let new_vertex = NewVertex {
+                name: name.to_string(),
+                type_: type_.to_string(),
+                created_by: "test".to_string(),
+                labels: labels.into_iter().map(str::to_string).collect(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // alice knows bob and carol, bob knows carol, and all of them work at acme.
+        let mut edges = Vec::new();
+        for (from, to, label) in [
+            (0, 1, "traversal_knows"),
+            (0, 2, "traversal_knows"),
+            (1, 2, "traversal_knows"),
+            (0, 3, "traversal_works_at"),
+            (1, 3, "traversal_works_at"),
+            (2, 3, "traversal_works_at"),
+        ] {
+            let new_edge = NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: label.to_string(),
+                created_by: "test".to_string(),
+                ..Default::default()
+            };
+            edges.push(crate::api::create_edge(&mut conn, &new_edge).await.unwrap());
+        }
+
+        let date = |year| {
+            chrono::NaiveDate::from_ymd_opt(year, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        let ids = |vertices: &[crate::model::Vertex]| {
+            vertices.iter().map(|vertex| vertex.id).collect::<Vec<_>>()
+        };
+
+        // carol is reached twice but returned once.
+        let result = g()
+            .v(vertices[0].id)
+            .out("traversal_knows")
+            .out(Labels::any())
+            .has_type("traversal_person")
+            .dedup()
+            .limit(10)
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ids(&result), vec![vertices[2].id]);
+
+        let result = g()
+            .v(vertices[3].id)
+            .in_(["traversal_works_at"])
+            .has_name("traversal_bob")
+            .both("traversal_knows")
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ids(&result), vec![vertices[0].id, vertices[2].id]);
+
+        // The type is a label too.
+        for (label, expected) in [
+            ("traversal_manager", vec![vertices[1].id]),
+            (
+                "traversal_person",
+                vec![vertices[0].id, vertices[1].id, vertices[2].id],
+            ),
+        ] {
+            let result = g()
+                .v(vertices[3].id)
+                .in_("traversal_works_at")
+                .has_label(label)
+                .to_list(&mut conn)
+                .await
+                .unwrap();
+            assert_eq!(ids(&result), expected, "{label}");
+        }
+
+        // Filters after a limit apply to the limited vertices only.
+        let count = g()
+            .v(vertices[3].id)
+            .in_("traversal_works_at")
+            .limit(1)
+            .has_name("traversal_bob")
+            .count(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+
+        let result = g()
+            .v([vertices[0].id, vertices[1].id])
+            .out_e(Labels::any())
+            .has_label("traversal_knows")
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            result.iter().map(|edge| edge.id).collect::<Vec<_>>(),
+            vec![edges[0].id, edges[1].id, edges[2].id]
+        );
+
+        let result = g()
+            .e(edges[2].id)
+            .both_v()
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ids(&result), vec![vertices[1].id, vertices[2].id]);
+
+        let update = crate::dto::UpdateEdge {
+            label: None,
+            weight: Some(0.3),
+            valid_from: None,
+            valid_to: None,
+            updated_by: "test".to_string(),
+        };
+        crate::api::update_edge(&mut conn, edges[1].id, &update, None)
+            .await
+            .unwrap();
+        let result = g()
+            .v(vertices[0].id)
+            .out_e("traversal_knows")
+            .has_weight(Weight::Gt(0.5))
+            .in_v()
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ids(&result), vec![vertices[1].id]);
+        let result = g()
+            .all_e()
+            .has_weight(Weight::Lte(f64::INFINITY))
+            .to_list(&mut conn)
+            .await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+
+        crate::api::soft_delete_edge_by_id(&mut conn, edges[0].id, None, "test")
+            .await
+            .unwrap();
+        let count = g()
+            .v(vertices[0].id)
+            .out("traversal_knows")
+            .count(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Undirected edges are followed out of both ends.
+        let new_edge = NewEdge {
+            from_vertex_id: vertices[2].id,
+            to_vertex_id: vertices[1].id,
+            label: "traversal_married".to_string(),
+            created_by: "test".to_string(),
+            undirected: true,
+            ..Default::default()
+        };
+        crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        for (from, to) in [(1, 2), (2, 1)] {
+            let result = g()
+                .v(vertices[from].id)
+                .out("traversal_married")
+                .to_list(&mut conn)
+                .await
+                .unwrap();
+            assert_eq!(ids(&result), vec![vertices[to].id]);
+        }
+
+        // Only edges valid at the instant are followed.
+        let update = crate::dto::UpdateEdge {
+            label: None,
+            weight: None,
+            valid_from: None,
+            valid_to: Some(Some(date(2000))),
+            updated_by: "test".to_string(),
+        };
+        crate::api::update_edge(&mut conn, edges[3].id, &update, None)
+            .await
+            .unwrap();
+        let result = g()
+            .valid_at(date(2020))
+            .v(vertices[3].id)
+            .in_("traversal_works_at")
+            .to_list(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ids(&result), vec![vertices[1].id, vertices[2].id]);
+        let count = g()
+            .valid_at(date(1990))
+            .e(edges[3].id)
+            .count(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let result = g().v(0).out("traversal_knows").to_list(&mut conn).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+        let result = g().all_e().limit(0).to_list(&mut conn).await;
+        assert!(matches!(result, Err(Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_long_traversal() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+        let mut vertices = Vec::new();
+        for hop in 0..=HOPS {
+            let new_vertex = NewVertex {
+                name: format!("long_traversal_{hop}"),
+                type_: "long_traversal".to_string(),
+                created_by: "test".to_string(),
+                labels: Vec::new(),
+            };
+            vertices.push(
+                crate::api::create_vertex(&mut conn, &new_vertex)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // A chain whose last link is undirected and stored against the
+        // direction of the chain.
+        for hop in 0..HOPS {
+            let undirected = hop == HOPS - 1;
+            let (from, to) = if undirected {
+                (hop + 1, hop)
+            } else {
+                (hop, hop + 1)
+            };
+            let new_edge = NewEdge {
+                from_vertex_id: vertices[from].id,
+                to_vertex_id: vertices[to].id,
+                label: "long_traversal_next".to_string(),
+                created_by: "test".to_string(),
+                undirected,
+                ..Default::default()
+            };
+            crate::api::create_edge(&mut conn, &new_edge).await.unwrap();
+        }
+
+        let forward = (0..HOPS).fold(g().v(vertices[0].id), |traversal, _| {
+            traversal.out("long_traversal_next")
+        });
+        let result = forward.to_list(&mut conn).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, vertices[HOPS].id);
+
+        let backward = (2..HOPS).fold(g().v(vertices[HOPS - 2].id), |traversal, _| {
+            traversal.in_("long_traversal_next")
+        });
+        let result = backward.to_list(&mut conn).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, vertices[0].id);
+        let count = (1..HOPS)
+            .fold(g().v(vertices[0].id), |traversal, _| {
+                traversal.out("long_traversal_next")
+            })
+            .out_e("long_traversal_next")
+            .count(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Each hop compiles the hops before it once, so the statement grows
+        // linearly with the length of the traversal.
+        let statement_length = |hops| {
+            let traversal = (0..hops).fold(g().v(vertices[0].id), |traversal, _| {
+                traversal.both("long_traversal_next")
+            });
+            let query = vertex_ids(&traversal.steps, None).unwrap();
+            diesel::debug_query::<diesel::pg::Pg, _>(&query)
+                .to_string()
+                .len()
+        };
+        assert!(statement_length(HOPS) < 3 * statement_length(HOPS / 2));
+    }
+}
@@ -0,0 +1,577 @@
+use std::time::Duration;
+
+use diesel::dsl::{now, IntervalDsl};
+use diesel::{ExpressionMethods, NullableExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, AsyncPgConnection, RunQueryDsl};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    change::{ChangeEvent, ChangeFilter},
+    dto::{InsertableNewWebhook, InsertableNewWebhookDelivery, NewWebhook},
+    error::Error,
+    model::{OutboxEvent, Webhook, WebhookDeadLetter, WebhookDelivery},
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Broccoli-Signature";
+pub const EVENT_ID_HEADER: &str = "X-Broccoli-Event-Id";
+
+#[derive(Debug, Clone)]
+pub struct DispatcherConfig {
+    /// Outbox events scheduled and deliveries attempted per round.
+    pub batch_size: i64,
+    /// Attempts after which a delivery is moved to the dead-letter table.
+    pub max_attempts: i32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub request_timeout: Duration,
+    /// Pause between rounds that found nothing to do.
+    pub poll_interval: Duration,
+}
+
+impl Default for DispatcherConfig {
+    fn default() -> Self {
+        DispatcherConfig {
+            batch_size: 100,
+            max_attempts: 8,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(3600),
+            request_timeout: Duration::from_secs(10),
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchSummary {
+    pub scheduled: usize,
+    pub delivered: usize,
+    pub failed: usize,
+    pub dead_lettered: usize,
+}
+
+/// Delivers the events of the outbox to the registered webhooks.
+///
+/// Every event is POSTed as JSON, signed with HMAC-SHA256 over the body using
+/// the webhook's secret. Failed deliveries are retried with exponential
+/// backoff and end up in `webhook_dead_letter` after `max_attempts`. Several
+/// dispatchers can run against the same database.
+pub struct Dispatcher {
+    client: reqwest::Client,
+    config: DispatcherConfig,
+}
+
+impl Dispatcher {
+    pub fn new(config: DispatcherConfig) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .build()?;
+
+        Ok(Dispatcher { client, config })
+    }
+
+    /// Dispatches forever, sleeping between rounds that had nothing to do.
+    pub async fn run(&self, conn: &mut AsyncPgConnection) -> Result<(), Error> {
+        loop {
+            let summary = self.run_once(conn).await?;
+            if summary == DispatchSummary::default() {
+                tokio::time::sleep(self.config.poll_interval).await;
+            }
+        }
+    }
+
+    /// Schedules new outbox events and attempts the deliveries that are due.
+    pub async fn run_once(&self, conn: &mut AsyncPgConnection) -> Result<DispatchSummary, Error> {
+        let mut summary = DispatchSummary {
+            scheduled: self.schedule(conn).await?,
+            ..Default::default()
+        };
+
+        for (delivery, webhook, event) in self.claim(conn).await? {
+            match self.send(&webhook, &event).await {
+                Ok(()) => {
+                    use crate::schema::webhook_delivery::dsl::*;
+
+                    diesel::update(webhook_delivery.filter(id.eq(delivery.id)))
+                        .set((
+                            attempts.eq(delivery.attempts + 1),
+                            delivered_at.eq(now.nullable()),
+                            last_error.eq(None::<String>),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    summary.delivered += 1;
+                }
+                Err(e) if delivery.attempts + 1 >= self.config.max_attempts => {
+                    self.dead_letter(conn, &delivery, &event, e).await?;
+                    summary.dead_lettered += 1;
+                }
+                Err(e) => {
+                    use crate::schema::webhook_delivery::dsl::*;
+
+                    let backoff = self.backoff(delivery.attempts + 1);
+                    diesel::update(webhook_delivery.filter(id.eq(delivery.id)))
+                        .set((
+                            attempts.eq(delivery.attempts + 1),
+                            next_attempt_at.eq(now + (backoff.as_micros() as i64).microseconds()),
+                            last_error.eq(Some(e)),
+                        ))
+                        .execute(conn)
+                        .await?;
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Creates the deliveries of the outbox events that have not been looked
+    /// at yet, one per matching active webhook.
+    async fn schedule(&self, conn: &mut AsyncPgConnection) -> Result<usize, Error> {
+        let batch_size = self.config.batch_size;
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                use crate::schema::{outbox, webhook, webhook_delivery};
+
+                let events = outbox::table
+                    .filter(outbox::dispatched_at.is_null())
+                    .order(outbox::id.asc())
+                    .limit(batch_size)
+                    .for_update()
+                    .skip_locked()
+                    .select(OutboxEvent::as_select())
+                    .load(conn)
+                    .await?;
+                if events.is_empty() {
+                    return Ok(0);
+                }
+
+                let webhooks = webhook::table
+                    .filter(webhook::active.eq(true))
+                    .select(Webhook::as_select())
+                    .load(conn)
+                    .await?
+                    .into_iter()
+                    .map(|hook| {
+                        let filter = serde_json::from_value::<ChangeFilter>(hook.filter.clone())
+                            .map_err(|e| Error::Other(e.into()))?;
+                        Ok((hook, filter))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+
+                let mut deliveries = Vec::new();
+                for event in &events {
+                    let change = change_event(event)?;
+                    for (hook, filter) in &webhooks {
+                        if filter.matches(&change) {
+                            deliveries.push(InsertableNewWebhookDelivery {
+                                webhook_id: hook.id,
+                                outbox_id: event.id,
+                            });
+                        }
+                    }
+                }
+
+                let scheduled = diesel::insert_into(webhook_delivery::table)
+                    .values(&deliveries)
+                    .on_conflict_do_nothing()
+                    .execute(conn)
+                    .await?;
+
+                diesel::update(
+                    outbox::table.filter(outbox::id.eq_any(events.iter().map(|event| event.id))),
+                )
+                .set(outbox::dispatched_at.eq(now.nullable()))
+                .execute(conn)
+                .await?;
+
+                Ok(scheduled)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    /// Takes a lease on the deliveries that are due, so that concurrent
+    /// dispatchers skip them while they are being sent.
+    async fn claim(
+        &self,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(WebhookDelivery, Webhook, OutboxEvent)>, Error> {
+        let batch_size = self.config.batch_size;
+        let lease = self.config.request_timeout * 2;
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                use crate::schema::{outbox, webhook, webhook_delivery};
+
+                let due = webhook_delivery::table
+                    .filter(webhook_delivery::delivered_at.is_null())
+                    .filter(webhook_delivery::next_attempt_at.le(now))
+                    .order(webhook_delivery::next_attempt_at.asc())
+                    .limit(batch_size)
+                    .for_update()
+                    .skip_locked()
+                    .select(webhook_delivery::id)
+                    .load::<i64>(conn)
+                    .await?;
+                if due.is_empty() {
+                    return Ok(Vec::new());
+                }
+
+                diesel::update(webhook_delivery::table.filter(webhook_delivery::id.eq_any(&due)))
+                    .set(
+                        webhook_delivery::next_attempt_at
+                            .eq(now + (lease.as_micros() as i64).microseconds()),
+                    )
+                    .execute(conn)
+                    .await?;
+
+                let claimed = webhook_delivery::table
+                    .inner_join(webhook::table)
+                    .inner_join(outbox::table)
+                    .filter(webhook_delivery::id.eq_any(&due))
+                    .order(webhook_delivery::id.asc())
+                    .select((
+                        WebhookDelivery::as_select(),
+                        Webhook::as_select(),
+                        OutboxEvent::as_select(),
+                    ))
+                    .load(conn)
+                    .await?;
+
+                Ok(claimed)
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    /// POSTs one event, returning the reason on failure.
+    async fn send(&self, webhook: &Webhook, event: &OutboxEvent) -> Result<(), String> {
+        let body = serde_json::to_vec(&change_event(event).map_err(|e| e.to_string())?)
+            .map_err(|e| e.to_string())?;
+
+        let response = self
+            .client
+            .post(&webhook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_ID_HEADER, event.id)
+            .header(SIGNATURE_HEADER, sign(&webhook.secret, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Unexpected response status: {}", response.status()))
+        }
+    }
+
+    async fn dead_letter(
+        &self,
+        conn: &mut AsyncPgConnection,
+        delivery: &WebhookDelivery,
+        event: &OutboxEvent,
+        error: String,
+    ) -> Result<(), Error> {
+        let delivery_id = delivery.id;
+        let row = (
+            crate::schema::webhook_dead_letter::webhook_id.eq(delivery.webhook_id),
+            crate::schema::webhook_dead_letter::outbox_id.eq(event.id),
+            crate::schema::webhook_dead_letter::event.eq(event.event.clone()),
+            crate::schema::webhook_dead_letter::attempts.eq(delivery.attempts + 1),
+            crate::schema::webhook_dead_letter::last_error.eq(Some(error)),
+        );
+
+        conn.transaction::<_, Error, _>(|conn| {
+            async move {
+                use crate::schema::{webhook_dead_letter, webhook_delivery};
+
+                diesel::insert_into(webhook_dead_letter::table)
+                    .values(row)
+                    .execute(conn)
+                    .await?;
+                diesel::delete(
+                    webhook_delivery::table.filter(webhook_delivery::id.eq(delivery_id)),
+                )
+                .execute(conn)
+                .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+    }
+
+    fn backoff(&self, attempts: i32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1) as u32);
+        self.config
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.config.max_backoff)
+    }
+}
+
+/// The `X-Broccoli-Signature` value for `body`: `sha256=` followed by the
+/// hex-encoded HMAC-SHA256 of the body keyed with the webhook secret.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+
+    let digest = mac.finalize().into_bytes();
+    let hex = digest
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!("sha256={hex}")
+}
+
+pub async fn create_webhook(
+    conn: &mut AsyncPgConnection,
+    new_webhook: &NewWebhook,
+) -> Result<Webhook, Error> {
+    use crate::schema::webhook::dsl::*;
+
+    let new_webhook = InsertableNewWebhook {
+        url: new_webhook.url.clone(),
+        secret: new_webhook.secret.clone(),
+        filter: serde_json::to_value(&new_webhook.filter).map_err(|e| Error::Other(e.into()))?,
+        created_by: new_webhook.created_by.clone(),
+        updated_by: new_webhook.created_by.clone(),
+    };
+
+    let result = diesel::insert_into(webhook)
+        .values(&new_webhook)
+        .returning(Webhook::as_returning())
+        .get_result(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn delete_webhook(conn: &mut AsyncPgConnection, webhook_id: i32) -> Result<usize, Error> {
+    use crate::schema::webhook::dsl::*;
+
+    if webhook_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let result = diesel::delete(webhook.filter(id.eq(webhook_id)))
+        .execute(conn)
+        .await?;
+
+    Ok(result)
+}
+
+pub async fn get_dead_letters(
+    conn: &mut AsyncPgConnection,
+    webhook_id: i32,
+) -> Result<Vec<WebhookDeadLetter>, Error> {
+    use crate::schema::webhook_dead_letter::dsl::{self, webhook_dead_letter};
+
+    if webhook_id < 1 {
+        return Err(Error::Validation(validator::ValidationErrors::new()));
+    }
+
+    let result = webhook_dead_letter
+        .filter(dsl::webhook_id.eq(webhook_id))
+        .order(dsl::id.asc())
+        .select(WebhookDeadLetter::as_select())
+        .load(conn)
+        .await?;
+
+    Ok(result)
+}
+
+fn change_event(event: &OutboxEvent) -> Result<ChangeEvent, Error> {
+    let mut payload = event.event.clone();
+    if let Some(payload) = payload.as_object_mut() {
+        payload.insert("event_id".to_string(), event.id.into());
+    }
+
+    serde_json::from_value(payload).map_err(|e| Error::Other(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::Duration;
+
+    use crate::change::ChangeFilter;
+    use crate::dto::{NewVertex, NewWebhook};
+    use crate::webhook::{Dispatcher, DispatcherConfig};
+    use diesel_async::{AsyncConnection, AsyncPgConnection};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// Accepts requests on a local port, answering the first one with a 500
+    /// and every later one with a 200. Hands over the signature header and
+    /// body of each request.
+    async fn receiver() -> (String, mpsc::UnboundedReceiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let (sender, received) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let mut status = "500 Internal Server Error";
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                let (head, body) = loop {
+                    let read = socket.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+                        continue;
+                    };
+                    let head = String::from_utf8_lossy(&request[..end]).to_lowercase();
+                    let length = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .map_or(0, |length| length.trim().parse::<usize>().unwrap());
+                    if request.len() >= end + 4 + length {
+                        break (head, request[end + 4..end + 4 + length].to_vec());
+                    }
+                };
+                let signature = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("x-broccoli-signature: "))
+                    .unwrap_or_default()
+                    .to_string();
+
+                let response =
+                    format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                socket.write_all(response.as_bytes()).await.unwrap();
+                status = "200 OK";
+                let _ = sender.send((signature, body));
+            }
+        });
+
+        (url, received)
+    }
+
+    /// Webhooks may be https: the dispatcher opens them with a TLS
+    /// handshake, which a plain TCP server sees as a handshake record.
+    #[tokio::test]
+    async fn test_https_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("https://{}/hook", listener.local_addr().unwrap());
+        let (sender, mut received) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut record_type = [0u8; 1];
+            socket.read_exact(&mut record_type).await.unwrap();
+            let _ = sender.send(record_type[0]);
+        });
+
+        let dispatcher = Dispatcher::new(DispatcherConfig {
+            request_timeout: Duration::from_secs(1),
+            ..Default::default()
+        })
+        .unwrap();
+        let error = dispatcher.client.post(&url).send().await.unwrap_err();
+        assert!(!error.is_builder(), "{error:?}");
+
+        // 0x16 starts a TLS handshake record, here the ClientHello.
+        assert_eq!(received.try_recv(), Ok(0x16), "{error:?}");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_with_retry_and_dead_letter() {
+        dotenvy::from_path(".env").ok();
+
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let mut conn = AsyncPgConnection::establish(&database_url).await.unwrap();
+
+        let (url, mut received) = receiver().await;
+        let filter = ChangeFilter {
+            types: vec!["webhook".to_string()],
+            ..Default::default()
+        };
+        let secret = "0123456789abcdef".to_string();
+        let live_webhook = NewWebhook {
+            url,
+            secret: secret.clone(),
+            filter: filter.clone(),
+            created_by: "test".to_string(),
+        };
+        let live_webhook = crate::webhook::create_webhook(&mut conn, &live_webhook)
+            .await
+            .unwrap();
+
+        // Nothing listens on the port of a dropped listener.
+        let unreachable = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dead_webhook = NewWebhook {
+            url: format!("http://{}/hook", unreachable.local_addr().unwrap()),
+            secret: secret.clone(),
+            filter,
+            created_by: "test".to_string(),
+        };
+        drop(unreachable);
+        let dead_webhook = crate::webhook::create_webhook(&mut conn, &dead_webhook)
+            .await
+            .unwrap();
+
+        let new_vertex = NewVertex {
+            name: "webhook".to_string(),
+            type_: "webhook".to_string(),
+            created_by: "test".to_string(),
+            labels: Vec::new(),
+        };
+        let new_vertex = crate::api::create_vertex(&mut conn, &new_vertex)
+            .await
+            .unwrap();
+
+        let dispatcher = Dispatcher::new(DispatcherConfig {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(10),
+            request_timeout: Duration::from_secs(2),
+            ..Default::default()
+        })
+        .unwrap();
+        let dead_letters = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                dispatcher.run_once(&mut conn).await.unwrap();
+                let dead_letters = crate::webhook::get_dead_letters(&mut conn, dead_webhook.id)
+                    .await
+                    .unwrap();
+                if received.len() >= 2 && !dead_letters.is_empty() {
+                    return dead_letters;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        // The first attempt was answered with a 500, the retry carries the
+        // same event.
+        let (first_signature, first_body) = received.recv().await.unwrap();
+        let (signature, body) = received.recv().await.unwrap();
+        assert_eq!(first_body, body);
+        assert_eq!(first_signature, signature);
+        assert_eq!(signature, crate::webhook::sign(&secret, &body));
+
+        let event = serde_json::from_slice::<crate::change::ChangeEvent>(&body).unwrap();
+        assert_eq!(event.id, new_vertex.id);
+        assert_eq!(event.type_.as_deref(), Some("webhook"));
+
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].outbox_id, event.event_id);
+        assert_eq!(dead_letters[0].attempts, 2);
+        assert!(dead_letters[0].last_error.is_some());
+
+        crate::webhook::delete_webhook(&mut conn, live_webhook.id)
+            .await
+            .unwrap();
+        crate::webhook::delete_webhook(&mut conn, dead_webhook.id)
+            .await
+            .unwrap();
+    }
+}